@@ -0,0 +1,59 @@
+//! [`Fmtter::build`] 与 [`Fmtter::fmt_str`] 的耗时与堆分配次数
+//!
+//! 运行: `cargo bench --bench fmtter`
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use baidu_fanyi::mini_fmt::Fmtter;
+
+/// 记录分配次数的分配器
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// 常量与值交替的 50 段模板
+fn template() -> String {
+    (0..25).map(|i| format!("[{i}] %{}s", i % 4)).collect()
+}
+
+fn bench<T>(name: &str, iters: usize, mut f: impl FnMut() -> T) {
+    let allocs = ALLOCS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..iters {
+        black_box(f());
+    }
+    let nanos = start.elapsed().as_nanos() as usize / iters;
+    let allocs = (ALLOCS.load(Ordering::Relaxed) - allocs) / iters;
+    println!("{name:<8} {nanos:>8} ns/iter {allocs:>4} allocs/iter");
+}
+
+fn main() {
+    let template = template();
+    let fmtter = Fmtter::build(&template).unwrap();
+    let args = ["dst", "src", "en", "zh"];
+    bench("build", 20_000, || Fmtter::build(black_box(&template)).unwrap());
+    bench("fmt_str", 20_000, || fmtter.fmt_str(black_box(&args)));
+}
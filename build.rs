@@ -0,0 +1,7 @@
+use vergen::EmitBuilder;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // VERGEN_RUSTC_SEMVER
+    EmitBuilder::builder().rustc_semver().emit()?;
+    Ok(())
+}
@@ -0,0 +1,15 @@
+#![no_main]
+
+use baidu_fanyi::traits::FilterOutLongEmpty;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (&str, u8)| {
+    let (text, count) = input;
+    let count = usize::from(count % 16);
+    let filtered = text.filter_out_long_empty(count);
+    let mut run = 0;
+    for c in filtered.chars() {
+        run = if c.is_whitespace() { run + 1 } else { 0 };
+        assert!(run <= count, "{text:?} {count}");
+    }
+});
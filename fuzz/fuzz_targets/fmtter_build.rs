@@ -0,0 +1,11 @@
+#![no_main]
+
+use baidu_fanyi::mini_fmt::Fmtter;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|format: &str| {
+    if let Ok(fmtter) = Fmtter::build(format) {
+        let displayed = fmtter.to_string();
+        assert_eq!(Fmtter::build(&displayed), Ok(fmtter), "{displayed:?}");
+    }
+});
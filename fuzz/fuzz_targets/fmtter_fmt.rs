@@ -0,0 +1,11 @@
+#![no_main]
+
+use baidu_fanyi::mini_fmt::Fmtter;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (&str, Vec<&str>)| {
+    let (format, args) = input;
+    if let Ok(fmtter) = Fmtter::build(format) {
+        let _ = fmtter.try_fmt_str(&args);
+    }
+});
@@ -1,7 +1,82 @@
+/// 当前库的版本
+/// # Examples
+/// ```
+/// let version = baidu_fanyi::version();
+/// assert!(!version.is_empty());
+/// let core = version.split(['-', '+']).next().unwrap();
+/// let parts = core.split('.')
+///     .map(|x| x.parse::<u64>())
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert_eq!(parts.len(), 3);
+/// ```
+pub fn version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+/// 构建信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub rustc_version: &'static str,
+}
+
+/// # Examples
+/// ```
+/// let info = baidu_fanyi::build_info();
+/// assert_eq!(info.version, baidu_fanyi::version());
+/// assert!(info.rustc_version.contains('.'));
+/// ```
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: version(),
+        rustc_version: env!("VERGEN_RUSTC_SEMVER"),
+    }
+}
+
+/// 默认的输出格式, 每行先输出 `%0s` 译文 (dst), 再输出 `%1s` 原文 (src),
+/// 参数顺序见 [`response::FmtArgs`]
+/// # Examples
+/// ```
+/// use baidu_fanyi::{
+///     DEFAULT_OUT_FORMAT,
+///     mini_fmt::Fmtter,
+///     output::format_out,
+///     response::{TransLine, TransResult},
+/// };
+/// let result = TransResult {
+///     from: "zh".into(),
+///     to: "en".into(),
+///     lines: vec![TransLine { src: "你好".into(), dst: "Hello".into() }],
+/// };
+/// let fmtter = Fmtter::build(DEFAULT_OUT_FORMAT).unwrap();
+/// assert_eq!(format_out(&[fmtter], &result).unwrap(), ["Hello\n你好\n"]);
+/// ```
+pub const DEFAULT_OUT_FORMAT: &str = "%s\n%s\n";
+
+#[deny(clippy::all)]
 pub mod mini_fmt {
-    use std::{fmt::{Debug, Display}, str::FromStr};
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        fmt::{self, Debug, Display, Write},
+        hash::Hash,
+        marker::PhantomData,
+        rc::Rc,
+        str::FromStr,
+        sync::Arc,
+        time::{SystemTime, UNIX_EPOCH},
+    };
 
-    #[derive(Debug, Clone, Copy)]
+    use smallstr::SmallString;
+
+    /// 条件分支的最大嵌套层数
+    pub const MAX_NESTING: usize = 16;
+
+    /// [`FmtType::Const`] 的字符串, 不超过 16 字节时不分配堆内存
+    pub type ConstStr = SmallString<[u8; 16]>;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum FmtStyle {
         /// ToString (Display)
         Str,
@@ -14,40 +89,360 @@ pub mod mini_fmt {
         pub fn fmt_str<S>(self, str: S) -> String
             where S: Debug + Display
         {
+            let mut res = String::new();
+            self.write_to(&mut res, str);
+            res
+        }
+
+        /// 同 [`FmtStyle::fmt_str`], 但追加到 `out`
+        fn write_to<S>(self, out: &mut String, str: S)
+            where S: Debug + Display
+        {
+            // 写入 String 不会失败
+            let _ = match self {
+                Self::Str => write!(out, "{}", str),
+                Self::Repr => write!(out, "{:?}", str),
+                Self::ERepr => write!(out, "{:#?}", str),
+            };
+        }
+    }
+    impl Display for FmtStyle {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_char(match self {
+                Self::Str => 's',
+                Self::Repr => 'r',
+                Self::ERepr => 'R',
+            })
+        }
+    }
+    /// 条件格式的条件, 对当前参数求值
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum FmtCondition {
+        /// `empty`
+        IsEmpty,
+        /// `nonempty`
+        NotEmpty,
+        /// `eq(text)`
+        Eq(String),
+    }
+    impl FmtCondition {
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::mini_fmt::FmtCondition;
+        /// assert!(FmtCondition::IsEmpty.test(""));
+        /// assert!(FmtCondition::NotEmpty.test("a"));
+        /// assert!(FmtCondition::Eq("foo".into()).test("foo"));
+        /// assert!(!FmtCondition::Eq("foo".into()).test("bar"));
+        /// ```
+        pub fn test(&self, value: &str) -> bool {
             match self {
-                Self::Str => format!("{}", str),
-                Self::Repr => format!("{:?}", str),
-                Self::ERepr => format!("{:#?}", str),
+                Self::IsEmpty => value.is_empty(),
+                Self::NotEmpty => !value.is_empty(),
+                Self::Eq(s) => value == s,
             }
         }
     }
-    #[derive(Debug, Clone)]
+    impl FromStr for FmtCondition {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "empty" => Ok(Self::IsEmpty),
+                "nonempty" | "notempty" => Ok(Self::NotEmpty),
+                _ => s.strip_prefix("eq(")
+                    .and_then(|s| s.strip_suffix(')'))
+                    .map(|s| Self::Eq(s.into()))
+                    .ok_or_else(|| format!("unknown condition: {:?}", s)),
+            }
+        }
+    }
+    impl Display for FmtCondition {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Self::IsEmpty => f.write_str("empty"),
+                Self::NotEmpty => f.write_str("nonempty"),
+                Self::Eq(s) => write!(f, "eq({s})"),
+            }
+        }
+    }
+    /// 内置的具名占位符, 格式化时取当前时间
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum BuiltinKind {
+        /// `%{timestamp}s`, Unix 时间戳 (秒)
+        Timestamp,
+        /// `%{date}s`, RFC 3339 格式的 UTC 时间
+        Date,
+    }
+    impl BuiltinKind {
+        /// # Examples
+        /// ```
+        /// use std::time::{Duration, UNIX_EPOCH};
+        /// use baidu_fanyi::mini_fmt::BuiltinKind;
+        /// let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        /// assert_eq!(BuiltinKind::Timestamp.format(time), "1700000000");
+        /// assert_eq!(BuiltinKind::Date.format(time), "2023-11-14T22:13:20Z");
+        /// assert_eq!(BuiltinKind::Date.format(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        /// ```
+        pub fn format(self, time: SystemTime) -> String {
+            match self {
+                Self::Timestamp => time.duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs())
+                    .to_string(),
+                Self::Date => format_rfc3339(time, false),
+            }
+        }
+    }
+
+    /// 以 RFC 3339 格式化 UTC 时间, `millis` 时保留三位毫秒,
+    /// 早于 Unix 纪元的时间视为纪元
+    /// # Examples
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use baidu_fanyi::mini_fmt::format_rfc3339;
+    /// let at = |secs: u64, millis: u64| UNIX_EPOCH + Duration::from_millis(secs * 1000 + millis);
+    /// assert_eq!(format_rfc3339(at(0, 0), false), "1970-01-01T00:00:00Z");
+    /// assert_eq!(format_rfc3339(at(951_782_400, 0), false), "2000-02-29T00:00:00Z");
+    /// assert_eq!(format_rfc3339(at(1_735_689_599, 7), true), "2024-12-31T23:59:59.007Z");
+    /// assert_eq!(format_rfc3339(at(4_107_542_400, 0), false), "2100-03-01T00:00:00Z");
+    /// let before = UNIX_EPOCH - Duration::from_secs(1);
+    /// assert_eq!(format_rfc3339(before, true), "1970-01-01T00:00:00.000Z");
+    /// ```
+    pub fn format_rfc3339(time: SystemTime, millis: bool) -> String {
+        let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since.as_secs();
+        let (days, secs) = (secs / 86_400, secs % 86_400);
+        // 由天数求公历日期, 见 http://howardhinnant.github.io/date_algorithms.html
+        // 以 0000-03-01 为起点, 使闰日位于每年末尾
+        let z = days + 719_468;
+        let (era, doe) = (z / 146_097, z % 146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = era * 400 + yoe + u64::from(month <= 2);
+        let (hour, minute, second) = (secs / 3600, secs % 3600 / 60, secs % 60);
+        let mut res = format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}");
+        if millis {
+            write!(res, ".{:03}", since.subsec_millis()).unwrap();
+        }
+        res.push('Z');
+        res
+    }
+    impl FromStr for BuiltinKind {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "timestamp" => Ok(Self::Timestamp),
+                "date" => Ok(Self::Date),
+                _ => Err(format!("unknown placeholder: {:?}", s)),
+            }
+        }
+    }
+    impl Display for BuiltinKind {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(match self {
+                Self::Timestamp => "timestamp",
+                Self::Date => "date",
+            })
+        }
+    }
+    #[derive(Debug, Clone, PartialEq, Eq)]
     pub enum FmtType {
-        Const(String),
+        Const(ConstStr),
         Value { style: FmtStyle },
         IndexValue { id: usize, style: FmtStyle },
+        /// `%?cond{then}` 或 `%?cond{then}:{else}`
+        Conditional {
+            condition: FmtCondition,
+            then_branch: Box<FmtType>,
+            else_branch: Option<Box<FmtType>>,
+        },
+        /// `%{timestamp}s` 等, 不占用参数
+        Builtin(BuiltinKind),
+        /// `%{key}s`, 由 [`Fmtter::fmt_with_named`] 按名称取值
+        NamedValue { name: String, style: FmtStyle },
     }
     impl Default for FmtType {
         fn default() -> Self {
-            Self::Const(String::default())
+            Self::Const(ConstStr::new())
         }
     }
+    // 内联的常量段不应使 FmtType 比其他变体更大
+    const _: () = assert!(std::mem::size_of::<FmtType>() <= 40);
     impl FmtType {
-        /// 格式化并移动格式化指针
-        fn fmt_str<S>(&self, idx: &mut usize, args: &[S]) -> String
-            where S: Display + Debug
+        /// 格式化到 `out` 并移动格式化指针, `named` 用于取得具名的值
+        fn fmt_to<S, F>(&self, out: &mut String, idx: &mut usize, args: &[S], named: &F)
+            -> Result<(), String>
+            where S: Display + Debug,
+                  F: Fn(&str, FmtStyle) -> Result<String, String>,
         {
             use FmtType::*;
-            let res = format!("{}", match self {
-                Const(s) => s.into(),
+            match self {
+                Const(s) => out.push_str(s),
                 Value { style } => {
                     let tmp_idx = *idx;
                     *idx += 1;
-                    style.fmt_str(&args[tmp_idx])
+                    style.write_to(out, get_arg(args, tmp_idx)?)
                 },
-                FmtType::IndexValue { id, style } => style.fmt_str(&args[*id]),
-            });
-            res
+                FmtType::IndexValue { id, style } => style.write_to(out, get_arg(args, *id)?),
+                Conditional { condition, then_branch, else_branch } => {
+                    let value = args.get(*idx)
+                        .map(ToString::to_string)
+                        .unwrap_or_default();
+                    if condition.test(&value) {
+                        then_branch.fmt_to(out, idx, args, named)?
+                    } else if let Some(branch) = else_branch {
+                        branch.fmt_to(out, idx, args, named)?
+                    }
+                },
+                Builtin(kind) => out.push_str(&kind.format(SystemTime::now())),
+                NamedValue { name, style } => out.push_str(&named(name, *style)?),
+            }
+            Ok(())
+        }
+
+        /// 写出可由 [`Fmtter::build`] 解析回自身的格式,
+        /// `in_branch` 时转义常量中的括号, 使分支的括号保持配对
+        fn write_format(&self, f: &mut fmt::Formatter<'_>, in_branch: bool) -> fmt::Result {
+            match self {
+                Self::Const(s) => {
+                    for c in s.chars() {
+                        match c {
+                            '%' => f.write_str("%%")?,
+                            '{' if in_branch => f.write_str("%x7b")?,
+                            '}' if in_branch => f.write_str("%x7d")?,
+                            c => f.write_char(c)?,
+                        }
+                    }
+                    Ok(())
+                },
+                Self::Value { style } => write!(f, "%{style}"),
+                Self::IndexValue { id, style } => write!(f, "%{id}{style}"),
+                Self::Conditional { condition, then_branch, else_branch } => {
+                    let condition = condition.to_string();
+                    if condition.contains('{') {
+                        write!(f, "%?{{{condition}}}{{")?;
+                    } else {
+                        write!(f, "%?{condition}{{")?;
+                    }
+                    then_branch.write_format(f, true)?;
+                    f.write_char('}')?;
+                    if let Some(branch) = else_branch {
+                        f.write_str(":{")?;
+                        branch.write_format(f, true)?;
+                        f.write_char('}')?;
+                    }
+                    Ok(())
+                },
+                Self::Builtin(kind) => write!(f, "%{{{kind}}}s"),
+                Self::NamedValue { name, style } => write!(f, "%{{{name}}}{style}"),
+            }
+        }
+
+        /// 见 [`Fmtter::value_slots`]
+        fn value_slots(&self) -> Vec<(Option<usize>, FmtStyle)> {
+            match self {
+                Self::Const(_) | Self::Builtin(_) | Self::NamedValue { .. } => vec![],
+                Self::Value { style } => vec![(None, *style)],
+                Self::IndexValue { id, style } => vec![(Some(*id), *style)],
+                Self::Conditional { then_branch, else_branch, .. } => {
+                    let mut slots = then_branch.value_slots();
+                    if let Some(branch) = else_branch {
+                        slots.extend(branch.value_slots());
+                    }
+                    slots
+                },
+            }
+        }
+
+        /// 见 [`Fmtter::arg_count`], `idx` 为顺序取值的位置
+        fn arg_count(&self, idx: &mut usize) -> usize {
+            match self {
+                Self::Const(_) | Self::Builtin(_) | Self::NamedValue { .. } => 0,
+                Self::Value { .. } => {
+                    *idx += 1;
+                    *idx
+                },
+                Self::IndexValue { id, .. } => id + 1,
+                Self::Conditional { then_branch, else_branch, .. } => {
+                    let (start, mut count) = (*idx, 0);
+                    for branch in std::iter::once(then_branch).chain(else_branch) {
+                        let mut branch_idx = start;
+                        count = count.max(branch.arg_count(&mut branch_idx));
+                        *idx = (*idx).max(branch_idx);
+                    }
+                    count
+                },
+            }
+        }
+
+        /// 见 [`Fmtter::has_builtin`]
+        fn has_builtin(&self) -> bool {
+            match self {
+                Self::Builtin(_) => true,
+                Self::Conditional { then_branch, else_branch, .. } => {
+                    then_branch.has_builtin()
+                        || else_branch.as_ref().is_some_and(|branch| branch.has_builtin())
+                },
+                _ => false,
+            }
+        }
+
+        /// 见 [`Fmtter::named_slots`]
+        fn named_slots(&self) -> Vec<&str> {
+            match self {
+                Self::NamedValue { name, .. } => vec![name],
+                Self::Conditional { then_branch, else_branch, .. } => {
+                    let mut names = then_branch.named_slots();
+                    if let Some(branch) = else_branch {
+                        names.extend(branch.named_slots());
+                    }
+                    names
+                },
+                _ => vec![],
+            }
+        }
+    }
+
+    fn get_arg<S>(args: &[S], idx: usize) -> Result<&S, String> {
+        args.get(idx)
+            .ok_or_else(|| format!("missing argument {idx}, only {} given", args.len()))
+    }
+
+    /// 读取 `{...}` 中的内容, 允许嵌套的括号
+    fn take_group(chars: &mut std::str::Chars) -> Result<String, String> {
+        if chars.next() != Some('{') {
+            return Err("expected `{` in condition".into());
+        }
+        let mut depth = 0usize;
+        let mut group = String::new();
+        for c in chars.by_ref() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => return Ok(group),
+                '}' => depth -= 1,
+                _ => (),
+            }
+            group.push(c);
+        }
+        Err("unclosed `{` in condition".into())
+    }
+
+    /// 将分支构建为单个 [`FmtType`], `depth` 为分支所在的嵌套层数
+    fn build_branch(branch: &str, depth: usize) -> Result<Box<FmtType>, String> {
+        if depth >= MAX_NESTING {
+            return Err(format!("conditions nested deeper than {MAX_NESTING}"));
+        }
+        let mut args = Fmtter::build_nested(branch, depth + 1)?.args;
+        match args.len() {
+            0 => Ok(Box::default()),
+            1 => Ok(Box::new(args.remove(0))),
+            _ => Err(format!(
+                "condition branch must be a single text or value: {:?}",
+                branch,
+            )),
         }
     }
     /// 动态的格式化输入
@@ -61,16 +456,39 @@ pub mod mini_fmt {
     /// let fmtter = Fmtter::build("%s,%s,%0s,%1r,%s").unwrap();
     /// assert_eq!(&fmtter.fmt_str(&["a", "b", "c"]), "a,b,a,\"b\",c");
     ///
+    /// assert_eq!(Fmtter::build("100%%").unwrap().fmt_str::<&str>(&[]), "100%");
+    /// let fmtter = Fmtter::build("%%%s|%s").unwrap();
+    /// assert_eq!(fmtter.fmt_str(&["a", "b"]), "%a|b");
+    ///
     /// assert_eq!(&Fmtter::build("%x1b").unwrap().fmt_str::<&str>(&[]), "\x1b");
     /// assert_eq!(&Fmtter::build("%x1C").unwrap().fmt_str::<&str>(&[]), "\x1c");
     /// assert_eq!(&Fmtter::build("%u0879").unwrap().fmt_str::<&str>(&[]), "\u{0879}");
     /// assert_eq!(&Fmtter::build("%U10ffff").unwrap().fmt_str::<&str>(&[]), "\u{10ffff}");
     /// assert!(Fmtter::build("%U110000").is_err());
+    ///
+    /// let fmtter = Fmtter::build("%?eq(foo){found foo}:{not foo}").unwrap();
+    /// assert_eq!(&fmtter.fmt_str(&["foo"]), "found foo");
+    /// assert_eq!(&fmtter.fmt_str(&["bar"]), "not foo");
+    ///
+    /// let fmtter = Fmtter::build("%?{nonempty}{%s}:{-}|%1s").unwrap();
+    /// assert_eq!(&fmtter.fmt_str(&["a", "b"]), "a|b");
+    /// assert_eq!(&fmtter.fmt_str(&["", "b"]), "-|b");
+    /// assert!(Fmtter::build("%?empty{a%sb}").is_err());
+    ///
+    /// let fmtter = Fmtter::build("%{timestamp}s|%{date}s").unwrap();
+    /// let out = fmtter.fmt_str::<&str>(&[]);
+    /// let (timestamp, date) = out.split_once('|').unwrap();
+    /// assert!(timestamp.parse::<u64>().unwrap() > 1_700_000_000);
+    /// assert!(date.contains('T') && date.ends_with('Z'));
+    /// assert!(Fmtter::build("%{date}r").is_err());
+    /// assert!(Fmtter::build("%{}s").is_err());
+    /// assert!(Fmtter::build("%{dst").is_err());
     /// ```
     /// |----|-------------|
     /// | %s | Display     |
     /// | %r | Debug       |
     /// | %R | DebugExpand |
+    /// | %% | Percent     |
     /// | %n | LF          |
     /// | %N | CR          |
     /// | %t | Tab         |
@@ -79,18 +497,106 @@ pub mod mini_fmt {
     /// | %x | ASCII       |
     /// | %u | Unicode     |
     /// | %U | Unicode+    |
+    /// | %? | Condition   |
     /// |----|-------------|
     ///
     /// `%[n]...` example: `%0s`, index 0 Display
-    #[derive(Debug, Default)]
+    ///
+    /// `%{timestamp}s` is unix seconds, `%{date}s` is RFC 3339 UTC time,
+    /// other `%{key}s` are named values, see [`Fmtter::fmt_with_named`]
+    ///
+    /// `%?cond{then}:{else}` cond is `empty`, `nonempty` or `eq(text)`,
+    /// test the current argument, `:{else}` is optional
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
     pub struct Fmtter {
         args: Vec<FmtType>,
     }
+    /// 以代码构建 [`Fmtter`], 相邻的常量串会被合并
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::mini_fmt::{Fmtter, FmtterBuilder, FmtStyle};
+    /// let fmtter = FmtterBuilder::new()
+    ///     .const_str("[")
+    ///     .indexed(2, FmtStyle::Str)
+    ///     .const_str("->")
+    ///     .indexed(3, FmtStyle::Str)
+    ///     .const_str("] ")
+    ///     .value(FmtStyle::Str)
+    ///     .const_str(" ")
+    ///     .const_str("")
+    ///     .value(FmtStyle::Repr)
+    ///     .const_str("\n")
+    ///     .build();
+    /// assert_eq!(fmtter, Fmtter::build("[%2s->%3s] %s %r%n").unwrap());
+    /// ```
+    #[derive(Debug, Clone, Default)]
+    pub struct FmtterBuilder {
+        args: Vec<FmtType>,
+    }
+    impl FmtterBuilder {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn const_str(&mut self, s: &str) -> &mut Self {
+            if s.is_empty() {
+                return self;
+            }
+            if let Some(FmtType::Const(last)) = self.args.last_mut() {
+                last.push_str(s);
+            } else {
+                self.args.push(FmtType::Const(s.into()));
+            }
+            self
+        }
+
+        pub fn value(&mut self, style: FmtStyle) -> &mut Self {
+            self.args.push(FmtType::Value { style });
+            self
+        }
+
+        pub fn indexed(&mut self, id: usize, style: FmtStyle) -> &mut Self {
+            self.args.push(FmtType::IndexValue { id, style });
+            self
+        }
+
+        pub fn build(&self) -> Fmtter {
+            self.args.clone().into()
+        }
+    }
     impl From<Vec<FmtType>> for Fmtter {
         fn from(args: Vec<FmtType>) -> Self {
             Self { args }
         }
     }
+    /// 输出可由 [`Fmtter::build`] 解析回相同结果的格式,
+    /// 不支持 [`FmtterBuilder::indexed`] 构建的 10 及以上的下标
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::mini_fmt::Fmtter;
+    /// for format in ["%0s [%2s->%3s]%n", "100%% %{date}s", "%?{eq(a{b})}{%x7d}:{%1r}"] {
+    ///     let fmtter = Fmtter::build(format).unwrap();
+    ///     assert_eq!(Fmtter::build(&fmtter.to_string()).unwrap(), fmtter);
+    /// }
+    /// assert_eq!(Fmtter::build("%x25%n").unwrap().to_string(), "%%\n");
+    /// ```
+    impl Display for Fmtter {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let mut after_condition = false;
+            for arg in &self.args {
+                match arg {
+                    // 条件后的 `:` 会被当作 else 分支的开始
+                    FmtType::Const(s) if after_condition && s.starts_with(':') => {
+                        f.write_str("%x3a")?;
+                        FmtType::Const(s[1..].into()).write_format(f, false)?;
+                    },
+                    arg => arg.write_format(f, false)?,
+                }
+                after_condition = matches!(arg, FmtType::Conditional { .. });
+            }
+            Ok(())
+        }
+    }
     impl TryFrom<&str> for Fmtter {
         type Error = String;
         fn try_from(value: &str) -> Result<Self, Self::Error> {
@@ -109,18 +615,39 @@ pub mod mini_fmt {
         pub fn new() -> Self {
             Self::default()
         }
+        /// 同 [`Fmtter::build`], 但包装为 [`Arc`] 以便在多个线程间共享,
+        /// 通过 [`Deref`](std::ops::Deref) 即可调用 [`Fmtter::fmt_str`]
+        /// # Examples
+        /// ```
+        /// use std::{sync::Arc, thread};
+        /// use baidu_fanyi::mini_fmt::Fmtter;
+        /// let fmtter = Fmtter::shared("%1s -> %0s%n").unwrap();
+        /// let handles = [["one", "一"], ["two", "二"]].map(|args| {
+        ///     let fmtter = Arc::clone(&fmtter);
+        ///     thread::spawn(move || fmtter.fmt_str(&args))
+        /// });
+        /// let [a, b] = handles.map(|handle| handle.join().unwrap());
+        /// assert_eq!(a, "一 -> one\n");
+        /// assert_eq!(b, "二 -> two\n");
+        /// assert!(Fmtter::shared("%x").is_err());
+        /// ```
+        pub fn shared(fmtter: &str) -> Result<Arc<Self>, String> {
+            Self::build(fmtter).map(Arc::new)
+        }
         /// from str build
         pub fn build(fmtter: &str) -> Result<Self, String> {
+            Self::build_nested(fmtter, 0)
+        }
+        fn build_nested(fmtter: &str, depth: usize) -> Result<Self, String> {
             let mut chars = fmtter.chars();
             let mut args: Vec<FmtType> = Vec::new();
-            let mut last_val = String::new();
+            let mut last_val = ConstStr::new();
             while let Some(c) = chars.next() {
                 macro_rules! add {
                     ( $val:expr ) => {{
-                        if last_val.len() != 0 {
+                        if !last_val.is_empty() {
                             // 仅当前方有一个不为空的常量串时进行添加
-                            args.push(FmtType::Const(last_val));
-                            last_val = String::new();
+                            args.push(FmtType::Const(std::mem::take(&mut last_val)));
                         }
                         args.push($val);
                     }};
@@ -139,27 +666,22 @@ pub mod mini_fmt {
                         }
                     }};
                 }
-                macro_rules! no_use {
-                    ( ($( $a:tt )* ) [ $( $b:tt )* ]) => {
-                        ($( $a )* )
-                    };
-                }
+                /// 读取 `$len` 个十六进制数字作为一个字符
                 macro_rules! add_hex {
-                    ( ( $( $t:tt )* ) $type:tt ) => {{
-                        let chars
-                            = [$( no_use!((get_seq!())[$t]) ),*];
-                        let hex = String::from_iter(chars);
-                        if let Ok(val) = $type::from_str_radix(&hex, 16) {
-                            last_val.push(
-                                if let Some(x) = char::from_u32(val as u32) {
-                                    x
-                                } else {
-                                    return Err(
-                                        format!("{:x} to char failed", val))
-                                })
-                        } else {
-                            return Err(format!("build hex error: {:?}", hex));
-                        };
+                    ( $len:literal $type:ident ) => {{
+                        let mut hex = String::with_capacity($len);
+                        for _ in 0..$len {
+                            hex.push(get_seq!());
+                        }
+                        // from_str_radix 会接受 `+` 号
+                        let val = Some(&hex)
+                            .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()))
+                            .and_then(|hex| $type::from_str_radix(hex, 16).ok())
+                            .ok_or_else(|| format!("build hex error: {:?}", hex))?;
+                        match char::from_u32(u32::from(val)) {
+                            Some(x) => last_val.push(x),
+                            None => return Err(format!("{:x} to char failed", val)),
+                        }
                     }};
                 }
                 /// 完成最终转义序列的匹配
@@ -186,15 +708,63 @@ pub mod mini_fmt {
                                     style: style_pat!(get_seq!())
                                 })
                             },
+                            '{' => {
+                                // 具名占位符, 内置的只支持 Display
+                                let rest = chars.as_str();
+                                let end = rest.find('}')
+                                    .ok_or("unclosed `{` in placeholder")?;
+                                let name = &rest[..end];
+                                if name.is_empty() {
+                                    return Err("empty placeholder name".into());
+                                }
+                                chars = rest[end+1..].chars();
+                                let style_c = get_seq!();
+                                match name.parse::<BuiltinKind>() {
+                                    Ok(kind) if style_c == 's' => {
+                                        add!(FmtType::Builtin(kind))
+                                    },
+                                    Ok(_) => return Err(format!(
+                                        "placeholder only supports `s`: {:?}", style_c)),
+                                    Err(_) => add!(FmtType::NamedValue {
+                                        name: name.into(),
+                                        style: style_pat!(style_c),
+                                    }),
+                                }
+                            },
                             '%' => last_val.push(c), // 普通的百分号
                             'n' => last_val.push('\n'), // 换行
                             'N' => last_val.push('\r'), // 回车
                             't' => last_val.push('\t'), // 制表
                             'e' => last_val.push('\x1b'), // ESC
                             'z' => last_val.push('\x00'), // NUL
-                            'x' => add_hex!((++) u8), // ASCII
-                            'u' => add_hex!((++++) u16), // Unicode
-                            'U' => add_hex!((++++++) u32), // Unicode+
+                            'x' => add_hex!(2 u8), // ASCII
+                            'u' => add_hex!(4 u16), // Unicode
+                            'U' => add_hex!(6 u32), // Unicode+
+                            '?' => {
+                                // 条件, 可写为 `{cond}` 或 `cond`
+                                let condition = if chars.as_str().starts_with('{') {
+                                    take_group(&mut chars)?
+                                } else {
+                                    let rest = chars.as_str();
+                                    let end = rest.find('{')
+                                        .ok_or("expected `{` in condition")?;
+                                    chars = rest[end..].chars();
+                                    rest[..end].into()
+                                };
+                                let then_branch
+                                    = build_branch(&take_group(&mut chars)?, depth)?;
+                                let else_branch = if chars.as_str().starts_with(":{") {
+                                    chars.next();
+                                    Some(build_branch(&take_group(&mut chars)?, depth)?)
+                                } else {
+                                    None
+                                };
+                                add!(FmtType::Conditional {
+                                    condition: condition.parse()?,
+                                    then_branch,
+                                    else_branch,
+                                })
+                            },
                             _ => add!(FmtType::Value {
                                 style: style_pat!(next_c)
                             }),
@@ -205,59 +775,6414 @@ pub mod mini_fmt {
                     },
                 }
             }
-            if last_val.len() != 0 {
+            if !last_val.is_empty() {
                 args.push(FmtType::Const(last_val));
             }
             Ok(args.into())
         }
+        /// 是否只包含常量与内置占位符, 即不需要参数
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::mini_fmt::Fmtter;
+        /// assert!(Fmtter::build("# title%n").unwrap().is_const());
+        /// assert!(Fmtter::build("# %{date}s%n").unwrap().is_const());
+        /// assert!(!Fmtter::build("%0s").unwrap().is_const());
+        /// assert!(!Fmtter::build("%?empty{a}").unwrap().is_const());
+        /// ```
+        pub fn is_const(&self) -> bool {
+            self.args.iter().all(|arg| {
+                matches!(arg, FmtType::Const(_) | FmtType::Builtin(_))
+            })
+        }
+
+        /// 构成格式的各个部分
+        pub fn parts(&self) -> &[FmtType] {
+            &self.args
+        }
+
+        /// 依次列出每个取值的位置, `None` 为顺序取值, `Some(n)` 为 `%n`,
+        /// 条件的两个分支中的取值均会列出
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::mini_fmt::{Fmtter, FmtStyle::*};
+        /// let fmtter = Fmtter::build("%s%1r%s").unwrap();
+        /// assert_eq!(
+        ///     fmtter.value_slots().collect::<Vec<_>>(),
+        ///     [(None, Str), (Some(1), Repr), (None, Str)],
+        /// );
+        /// let fmtter = Fmtter::build("# title%n").unwrap();
+        /// assert_eq!(fmtter.value_slots().count(), 0);
+        /// let fmtter = Fmtter::build("%?empty{%1s}:{%s}").unwrap();
+        /// assert_eq!(
+        ///     fmtter.value_slots().collect::<Vec<_>>(),
+        ///     [(Some(1), Str), (None, Str)],
+        /// );
+        /// ```
+        pub fn value_slots(&self) -> impl Iterator<Item = (Option<usize>, FmtStyle)> + '_ {
+            self.args.iter().flat_map(FmtType::value_slots)
+        }
+
+        /// 格式化时最多需要的参数个数, 条件的分支按需要较多的一个计算
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::mini_fmt::Fmtter;
+        /// let count = |format| Fmtter::build(format).unwrap().arg_count();
+        /// assert_eq!(count("# title%n"), 0);
+        /// assert_eq!(count("%s%s%n"), 2);
+        /// assert_eq!(count("%5s%n"), 6);
+        /// assert_eq!(count("%s%1s"), 2);
+        /// assert_eq!(count("%?empty{%s}:{x}%s%s"), 3);
+        /// ```
+        pub fn arg_count(&self) -> usize {
+            let mut idx = 0;
+            self.args.iter()
+                .map(|arg| arg.arg_count(&mut idx))
+                .max()
+                .unwrap_or(0)
+        }
+
+        /// 依次列出每个具名值 `%{key}s` 的名称, 不包括内置占位符
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::mini_fmt::Fmtter;
+        /// let fmtter = Fmtter::build("%{dst}s %{date}s %?empty{%{src}r}").unwrap();
+        /// assert_eq!(fmtter.named_slots().collect::<Vec<_>>(), ["dst", "src"]);
+        /// ```
+        pub fn named_slots(&self) -> impl Iterator<Item = &str> + '_ {
+            self.args.iter().flat_map(FmtType::named_slots)
+        }
+
+        /// 是否含有 `%{timestamp}s` 等内置占位符, 其结果随时间变化
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::mini_fmt::Fmtter;
+        /// assert!(Fmtter::build("%s %{date}s").unwrap().has_builtin());
+        /// assert!(Fmtter::build("%?empty{x}:{%{timestamp}s}").unwrap().has_builtin());
+        /// assert!(!Fmtter::build("%s %{dst}s").unwrap().has_builtin());
+        /// ```
+        pub fn has_builtin(&self) -> bool {
+            self.args.iter().any(FmtType::has_builtin)
+        }
+
+        /// 具名值会被格式化为空串, 见 [`Fmtter::fmt_with_named`]
+        /// # Panics
+        /// 参数不足时 panic, 见 [`Fmtter::try_fmt_str`]
         pub fn fmt_str<S: Display + Debug>(&self, strs: &[S]) -> String {
+            self.try_fmt_str(strs).unwrap_or_else(|e| panic!("{e}"))
+        }
+
+        /// 同 [`Fmtter::fmt_str`], 但参数不足时返回错误
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::mini_fmt::Fmtter;
+        /// let fmtter = Fmtter::build("%s-%s").unwrap();
+        /// assert_eq!(fmtter.try_fmt_str(&["a", "b"]).unwrap(), "a-b");
+        /// assert!(fmtter.try_fmt_str(&["a"]).unwrap_err().contains("missing argument 1"));
+        /// ```
+        pub fn try_fmt_str<S: Display + Debug>(&self, strs: &[S]) -> Result<String, String> {
+            self.fmt_parts(strs, &|_, _| Ok(String::new()))
+        }
+
+        /// 同 [`Fmtter::try_fmt_str`], 具名值由 `named` 按名称取值, 取不到时返回错误
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::mini_fmt::Fmtter;
+        /// let fmtter = Fmtter::build("%{dst}s <- %1s").unwrap();
+        /// let named = |name: &str| (name == "dst").then_some("hello");
+        /// assert_eq!(fmtter.try_fmt_str_with(&["", "你好"], named).unwrap(), "hello <- 你好");
+        /// let fmtter = Fmtter::build("%{src}s").unwrap();
+        /// assert!(fmtter.try_fmt_str_with(&[""], named).unwrap_err().contains("src"));
+        /// ```
+        pub fn try_fmt_str_with<S, F>(&self, strs: &[S], named: F) -> Result<String, String>
+            where S: Display + Debug,
+                  F: Fn(&str) -> Option<S>,
+        {
+            self.fmt_parts(strs, &|name: &str, style: FmtStyle| {
+                named(name)
+                    .map(|value| style.fmt_str(value))
+                    .ok_or_else(|| format!("missing named value: {:?}", name))
+            })
+        }
+
+        fn fmt_parts<S, F>(&self, strs: &[S], named: &F) -> Result<String, String>
+            where S: Display + Debug,
+                  F: Fn(&str, FmtStyle) -> Result<String, String>,
+        {
             let mut res = String::new();
             let mut idx = 0;
             for i in &self.args {
-                res.push_str(&i.fmt_str(&mut idx, strs));
+                i.fmt_to(&mut res, &mut idx, strs, named)?;
             }
-            res
+            Ok(res)
         }
-    }
-}
-pub mod traits {
-    pub trait FilterOutLongEmpty {
-        type Output;
-        fn filter_out_long_empty(&self, count: usize) -> Self::Output;
-    }
-    impl FilterOutLongEmpty for &str {
-        type Output = String;
-        /// 过滤多余的空白符
+
+        /// 按名称取值进行格式化, 缺少的名称返回错误.
+        /// 顺序取值与 `%n` 使用按键名排序后的值, 因为 [`HashMap`] 不保留插入顺序
         /// # Examples
         /// ```
-        /// use baidu_fanyi::traits::FilterOutLongEmpty;
-        /// assert_eq!(&"a   b".filter_out_long_empty(0), "ab");
-        /// assert_eq!(&"a   b".filter_out_long_empty(1), "a b");
-        /// assert_eq!(&"a   b".filter_out_long_empty(2), "a  b");
-        /// assert_eq!(&"a   b".filter_out_long_empty(3), "a   b");
-        /// assert_eq!(&"a   b".filter_out_long_empty(4), "a   b");
-        fn filter_out_long_empty(&self, count: usize) -> Self::Output {
-            let mut res = String::with_capacity(self.len());
-            let mut continue_count: usize = 0;
-            if count == 0 {
-                #[inline]
-                fn filter(char: &char) -> bool {
-                    ! char.is_whitespace()
-                }
-                res.extend(self.chars().filter(filter))
-            } else {
-                for char in self.chars() {
-                    if char.is_whitespace() {
-                        continue_count += 1
-                    } else {
-                        continue_count = 0
-                    }
-                    if continue_count <= count {
-                        res.push(char)
-                    }
-                }
+        /// use std::collections::HashMap;
+        /// use baidu_fanyi::mini_fmt::Fmtter;
+        /// let args = HashMap::from([("dst", "hello"), ("src", "你好")]);
+        /// let fmtter = Fmtter::build("%{dst}s -> %{src}s").unwrap();
+        /// assert_eq!(fmtter.fmt_with_named(&args).unwrap(), "hello -> 你好");
+        ///
+        /// let fmtter = Fmtter::build("%{src}r: %s, %1s").unwrap();
+        /// assert_eq!(fmtter.fmt_with_named(&args).unwrap(), r#""你好": hello, 你好"#);
+        ///
+        /// let fmtter = Fmtter::build("%{from}s").unwrap();
+        /// assert!(fmtter.fmt_with_named(&args).unwrap_err().contains("from"));
+        /// ```
+        pub fn fmt_with_named<S: Display + Debug>(
+            &self,
+            args: &HashMap<&str, S>,
+        ) -> Result<String, String> {
+            let mut keys = args.keys().collect::<Vec<_>>();
+            keys.sort_unstable();
+            let values = keys.into_iter().map(|key| &args[key]).collect::<Vec<_>>();
+            self.try_fmt_str_with(&values, |name| args.get(name))
+        }
+        /// 使用 [`format_args!`] 预先格式化的结果作为唯一的参数
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::mini_fmt::Fmtter;
+        /// let fmtter = Fmtter::build("[%s] %0r").unwrap();
+        /// assert_eq!(
+        ///     fmtter.fmt_with_args(format_args!("{} {}", 1, "hello")),
+        ///     "[1 hello] \"1 hello\"",
+        /// );
+        /// ```
+        pub fn fmt_with_args(&self, args: std::fmt::Arguments) -> String {
+            self.fmt_str(&[format!("{}", args)])
+        }
+    }
+
+    /// 缓存 [`Fmtter::fmt_str`] 的结果, 以参数的 [`ToString`] 结果为键,
+    /// 供库的使用者格式化大量重复的参数; 命令行已对重复的行去重, 不使用它.
+    ///
+    /// 要求 `to_string` 相同的参数的 [`Debug`] 也相同,
+    /// 含 `%{timestamp}s` 等内置占位符时不缓存, 每次重新格式化
+    /// # Examples
+    /// ```
+    /// use std::rc::Rc;
+    /// use baidu_fanyi::mini_fmt::{Fmtter, FmtterCached};
+    /// let cached = FmtterCached::new(Fmtter::build("%s -> %r%n").unwrap());
+    /// let first = cached.fmt_str_cached(&["你好", "hello"]);
+    /// assert_eq!(&*first, "你好 -> \"hello\"\n");
+    /// let other = cached.fmt_str_cached(&["世界", "world"]);
+    /// assert_eq!(&*other, "世界 -> \"world\"\n");
+    /// // 相同的参数返回同一个缓存的字符串
+    /// assert!(Rc::ptr_eq(&first, &cached.fmt_str_cached(&["你好", "hello"])));
+    /// assert_eq!(cached.len(), 2);
+    ///
+    /// let builtin = FmtterCached::new(Fmtter::build("%{timestamp}s %s").unwrap());
+    /// builtin.fmt_str_cached(&["a"]);
+    /// assert!(builtin.is_empty());
+    /// ```
+    #[derive(Debug)]
+    pub struct FmtterCached<S> {
+        fmtter: Fmtter,
+        cache: RefCell<HashMap<Vec<String>, Rc<str>>>,
+        _marker: PhantomData<fn(&[S])>,
+    }
+    impl<S> From<Fmtter> for FmtterCached<S> {
+        fn from(fmtter: Fmtter) -> Self {
+            Self::new(fmtter)
+        }
+    }
+    impl<S> FmtterCached<S> {
+        pub fn new(fmtter: Fmtter) -> Self {
+            Self { fmtter, cache: RefCell::default(), _marker: PhantomData }
+        }
+
+        pub fn fmtter(&self) -> &Fmtter {
+            &self.fmtter
+        }
+
+        /// 缓存的结果数
+        pub fn len(&self) -> usize {
+            self.cache.borrow().len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.cache.borrow().is_empty()
+        }
+
+        /// 清空缓存, 此前返回的 [`Rc`] 仍然有效
+        pub fn clear(&self) {
+            self.cache.borrow_mut().clear();
+        }
+    }
+    impl<S: ToString + Eq + Hash + Display + Debug> FmtterCached<S> {
+        /// 同 [`Fmtter::fmt_str`], 相同的参数只格式化一次
+        /// # Panics
+        /// 参数不足时 panic
+        pub fn fmt_str_cached(&self, strs: &[S]) -> Rc<str> {
+            if self.fmtter.has_builtin() {
+                return self.fmtter.fmt_str(strs).into();
+            }
+            let key: Vec<String> = strs.iter().map(ToString::to_string).collect();
+            self.cache.borrow_mut()
+                .entry(key)
+                .or_insert_with(|| self.fmtter.fmt_str(strs).into())
+                .clone()
+        }
+    }
+
+    /// 将 `%` 转义为 `%%`, 使文本可作为格式中的常量
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::mini_fmt::{escape_format_str, unescape_format_str, Fmtter};
+    /// assert_eq!(escape_format_str("100% %s"), "100%% %%s");
+    /// for s in ["", "%", "%%", "100%", "%s%n", "a%%%b", "百分之%"] {
+    ///     let escaped = escape_format_str(s);
+    ///     assert_eq!(unescape_format_str(&escaped), s);
+    ///     assert_eq!(Fmtter::build(&escaped).unwrap().fmt_str::<&str>(&[]), s);
+    /// }
+    /// ```
+    pub fn escape_format_str(input: &str) -> String {
+        let mut res = String::with_capacity(input.len());
+        for c in input.chars() {
+            if c == '%' {
+                res.push('%');
+            }
+            res.push(c);
+        }
+        res
+    }
+
+    /// 将 `%%` 还原为 `%`, 其它内容不变
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::mini_fmt::unescape_format_str;
+    /// assert_eq!(unescape_format_str("100%% %s %%%%"), "100% %s %%");
+    /// assert_eq!(unescape_format_str("%"), "%");
+    /// ```
+    pub fn unescape_format_str(input: &str) -> String {
+        let mut res = String::with_capacity(input.len());
+        let mut chars = input.chars().peekable();
+        while let Some(c) = chars.next() {
+            res.push(c);
+            if c == '%' {
+                chars.next_if_eq(&'%');
+            }
+        }
+        res
+    }
+}
+#[cfg(feature = "clap")]
+pub mod clap_parser {
+    use std::ffi::OsStr;
+
+    use clap::{builder::TypedValueParser, error::ErrorKind, Arg, Command, Error};
+
+    use crate::mini_fmt::Fmtter;
+
+    /// 用于 clap 参数的 [`Fmtter`] 解析器
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::clap_parser::FmtterParser;
+    /// use clap::{Arg, Command};
+    /// let cmd = Command::new("test")
+    ///     .arg(Arg::new("fmt").long("fmt").value_parser(FmtterParser));
+    ///
+    /// let matches = cmd.clone()
+    ///     .try_get_matches_from(["test", "--fmt", "[%s]"])
+    ///     .unwrap();
+    /// let fmtter = matches.get_one::<baidu_fanyi::mini_fmt::Fmtter>("fmt");
+    /// assert_eq!(fmtter.unwrap().fmt_str(&["a"]), "[a]");
+    ///
+    /// assert!(cmd.try_get_matches_from(["test", "--fmt", "%q"]).is_err());
+    /// ```
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct FmtterParser;
+    impl TypedValueParser for FmtterParser {
+        type Value = Fmtter;
+
+        fn parse_ref(
+            &self,
+            cmd: &Command,
+            arg: Option<&Arg>,
+            value: &OsStr,
+        ) -> Result<Self::Value, Error> {
+            let value = value.to_str().ok_or_else(|| {
+                Error::new(ErrorKind::InvalidUtf8).with_cmd(cmd)
+            })?;
+            Fmtter::build(value).map_err(|e| {
+                let arg = arg.map_or_else(|| "...".into(), Arg::to_string);
+                Error::raw(
+                    ErrorKind::ValueValidation,
+                    format!("invalid value '{value}' for '{arg}': {e}\n"),
+                ).with_cmd(cmd)
+            })
+        }
+    }
+}
+
+/// 以字面量 `sep` 分割文本, 跳过空白的片段
+/// # Examples
+/// ```
+/// use baidu_fanyi::split_on_separator;
+/// let text = "first\n---\nsecond\n---\n---\nthird";
+/// assert_eq!(
+///     split_on_separator(text, "---"),
+///     ["first\n", "\nsecond\n", "\nthird"],
+/// );
+/// assert_eq!(split_on_separator("a.*b", ".*"), ["a", "b"]);
+/// ```
+pub fn split_on_separator<'a>(text: &'a str, sep: &str) -> Vec<&'a str> {
+    text.split(sep)
+        .filter(|segment| !segment.trim().is_empty())
+        .collect()
+}
+
+/// 去除每行首尾空白的方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrimMode {
+    #[default]
+    None,
+    Both,
+    Start,
+    End,
+}
+
+/// 按 `mode` 去除一行的首尾空白
+/// # Examples
+/// ```
+/// use baidu_fanyi::{apply_trim, TrimMode};
+/// assert_eq!(apply_trim("  hello  ", TrimMode::None), "  hello  ");
+/// assert_eq!(apply_trim("  hello  ", TrimMode::Both), "hello");
+/// assert_eq!(apply_trim("  hello  ", TrimMode::Start), "hello  ");
+/// assert_eq!(apply_trim("  hello  ", TrimMode::End), "  hello");
+/// ```
+pub fn apply_trim(line: &str, mode: TrimMode) -> &str {
+    match mode {
+        TrimMode::None => line,
+        TrimMode::Both => line.trim(),
+        TrimMode::Start => line.trim_start(),
+        TrimMode::End => line.trim_end(),
+    }
+}
+
+/// 对每行使用 [`apply_trim`], 保留换行符 (`\n` 或 `\r\n`)
+/// # Examples
+/// ```
+/// use baidu_fanyi::{trim_lines, TrimMode};
+/// assert_eq!(trim_lines("  hello  \n\tworld\r\n  ", TrimMode::Both), "hello\nworld\r\n");
+/// assert_eq!(trim_lines("  a \n  b ", TrimMode::Start), "a \nb ");
+/// assert_eq!(trim_lines("  a \n\n", TrimMode::End), "  a\n\n");
+/// ```
+pub fn trim_lines(text: &str, mode: TrimMode) -> String {
+    if mode == TrimMode::None {
+        return text.into();
+    }
+    text.split_inclusive('\n')
+        .map(|line| {
+            let body = line.strip_suffix('\n').map_or(line, |line| {
+                line.strip_suffix('\r').unwrap_or(line)
+            });
+            let newline = &line[body.len()..];
+            [apply_trim(body, mode), newline].concat()
+        })
+        .collect()
+}
+
+/// 读取以 NUL (`\0`) 分隔的记录, 如 `find -print0` 的输出,
+/// 去除每条记录末尾的 NUL, 末尾没有 NUL 的最后一条记录也会保留
+/// # Errors
+/// 读取出错, 或记录不是合法的 UTF-8 时返回错误
+/// # Examples
+/// ```
+/// use baidu_fanyi::read_null_terminated;
+/// let input: &[u8] = b"./a b.txt\0line1\nline2\0\0last";
+/// assert_eq!(
+///     read_null_terminated(input).unwrap(),
+///     ["./a b.txt", "line1\nline2", "", "last"],
+/// );
+/// assert_eq!(read_null_terminated(&b"one\0"[..]).unwrap(), ["one"]);
+/// assert!(read_null_terminated(&b""[..]).unwrap().is_empty());
+/// assert!(read_null_terminated(&b"ok\0\xff\0"[..]).is_err());
+/// ```
+pub fn read_null_terminated<R>(mut reader: R) -> std::io::Result<Vec<String>>
+    where R: std::io::BufRead,
+{
+    let mut records = vec![];
+    let mut buf = vec![];
+    while reader.read_until(b'\0', &mut buf)? != 0 {
+        if buf.last() == Some(&b'\0') {
+            buf.pop();
+        }
+        let record = String::from_utf8(std::mem::take(&mut buf))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// 统计翻译结果的行数, src 与 dst 的字符数
+/// # Examples
+/// ```
+/// use baidu_fanyi::{
+///     count_result,
+///     response::{TransLine, TransResult},
+/// };
+/// let result = TransResult {
+///     from: "zh".into(),
+///     to: "en".into(),
+///     lines: vec![
+///         TransLine { src: "你好".into(), dst: "Hello".into() },
+///         TransLine { src: "世界".into(), dst: "world".into() },
+///     ],
+/// };
+/// assert_eq!(count_result(&result), (2, 4, 10));
+/// ```
+pub fn count_result(result: &response::TransResult) -> (usize, usize, usize) {
+    result.lines.iter().fold((0, 0, 0), |(n, src, dst), line| {
+        (n + 1, src + line.src.chars().count(), dst + line.dst.chars().count())
+    })
+}
+
+/// 译文与原文不同的行所占的比例, 忽略空行, 没有行时为 0
+/// # Examples
+/// ```
+/// use baidu_fanyi::{
+///     lines_changed_ratio,
+///     response::{TransLine, TransResult},
+/// };
+/// let result = |lines: &[(&str, &str)]| TransResult {
+///     from: "en".into(),
+///     to: "zh".into(),
+///     lines: lines.iter()
+///         .map(|&(src, dst)| TransLine { src: src.into(), dst: dst.into() })
+///         .collect(),
+/// };
+/// let unchanged = result(&[("Rust", "Rust"), ("v1.0", "v1.0")]);
+/// assert_eq!(lines_changed_ratio(&unchanged), 0.0);
+/// let changed = result(&[("Rust", "Rust"), ("hello", "你好"), ("a", "一"), ("b", "二")]);
+/// assert_eq!(lines_changed_ratio(&changed), 0.75);
+/// assert_eq!(lines_changed_ratio(&result(&[])), 0.0);
+/// assert_eq!(lines_changed_ratio(&result(&[("", ""), ("hi", "嗨")])), 1.0);
+/// ```
+pub fn lines_changed_ratio(result: &response::TransResult) -> f64 {
+    let (total, changed) = result.lines.iter()
+        .filter(|line| !line.src.trim().is_empty())
+        .fold((0, 0), |(total, changed), line| {
+            (total + 1, changed + usize::from(line.src != line.dst))
+        });
+    if total == 0 {
+        return 0.0;
+    }
+    changed as f64 / total as f64
+}
+
+/// 统计待翻译文本的非空行数与字符数, 不包含换行符
+/// # Examples
+/// ```
+/// use baidu_fanyi::count_input;
+/// assert_eq!(count_input("你好\n\nworld\n"), (2, 7));
+/// ```
+pub fn count_input(text: &str) -> (usize, usize) {
+    text.lines()
+        .filter(|line| !line.is_empty())
+        .fold((0, 0), |(n, chars), line| (n + 1, chars + line.chars().count()))
+}
+
+pub mod traits {
+    pub trait FilterOutLongEmpty {
+        type Output;
+        fn filter_out_long_empty(&self, count: usize) -> Self::Output;
+    }
+    impl FilterOutLongEmpty for &str {
+        type Output = String;
+        /// 过滤多余的空白符
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::traits::FilterOutLongEmpty;
+        /// assert_eq!(&"a   b".filter_out_long_empty(0), "ab");
+        /// assert_eq!(&"a   b".filter_out_long_empty(1), "a b");
+        /// assert_eq!(&"a   b".filter_out_long_empty(2), "a  b");
+        /// assert_eq!(&"a   b".filter_out_long_empty(3), "a   b");
+        /// assert_eq!(&"a   b".filter_out_long_empty(4), "a   b");
+        fn filter_out_long_empty(&self, count: usize) -> Self::Output {
+            LongEmptyFilter::new(count).push(self)
+        }
+    }
+
+    /// 可分块输入的 [`FilterOutLongEmpty`], 跨块保留连续空白符的计数
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::traits::LongEmptyFilter;
+    /// let mut filter = LongEmptyFilter::new(2);
+    /// assert_eq!(filter.push("a  \n"), "a  ");
+    /// assert_eq!(filter.push("  b"), "b");
+    /// assert_eq!(filter.push(" \n"), " \n");
+    /// ```
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct LongEmptyFilter {
+        count: usize,
+        /// 当前连续空白符的个数
+        run: usize,
+    }
+    impl LongEmptyFilter {
+        pub fn new(count: usize) -> Self {
+            Self { count, run: 0 }
+        }
+
+        /// 过滤一块输入, 返回保留的部分
+        pub fn push(&mut self, chunk: &str) -> String {
+            let mut res = String::with_capacity(chunk.len());
+            for char in chunk.chars() {
+                if char.is_whitespace() {
+                    self.run += 1
+                } else {
+                    self.run = 0
+                }
+                if self.run <= self.count {
+                    res.push(char)
+                }
             }
             res
         }
     }
 }
+pub mod ansi {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum State {
+        Text,
+        /// 刚读到 ESC
+        Escape,
+        /// ESC 后的中间字节, 如 `ESC ( B`
+        Intermediate,
+        /// `ESC [` 或 `\x9b`
+        Csi,
+        /// `ESC ]` 等以 ST 或 BEL 结尾的字符串序列
+        Str,
+        /// 字符串序列中读到 ESC, 可能为 ST (`ESC \`)
+        StrEscape,
+        /// `ESC O`, 后接一个字符
+        Ss3,
+    }
+
+    /// 去除文本中的 ANSI 转义序列 (CSI, OSC, SS3 等),
+    /// 末尾不完整的转义序列也会被去除
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::ansi::strip_ansi;
+    /// // SGR 颜色
+    /// assert_eq!(strip_ansi("\x1b[1;31merror\x1b[0m: x"), "error: x");
+    /// assert_eq!(strip_ansi("\x1b[38;5;196mred\x1b[m"), "red");
+    /// // 光标移动与清行
+    /// assert_eq!(strip_ansi("\x1b[2K\x1b[1Gdone\x1b[3A"), "done");
+    /// // OSC 8 超链接, 以 ST 或 BEL 结尾
+    /// assert_eq!(
+    ///     strip_ansi("\x1b]8;;https://a.b\x1b\\link\x1b]8;;\x1b\\"),
+    ///     "link",
+    /// );
+    /// assert_eq!(strip_ansi("\x1b]0;title\x07text"), "text");
+    /// // SS3 与字符集选择
+    /// assert_eq!(strip_ansi("a\x1bOAb\x1b(Bc"), "abc");
+    /// // 末尾截断的序列
+    /// assert_eq!(strip_ansi("text\x1b[3"), "text");
+    /// assert_eq!(strip_ansi("text\x1b"), "text");
+    /// assert_eq!(strip_ansi("中文\u{9b}1m字"), "中文字");
+    /// ```
+    pub fn strip_ansi(text: &str) -> String {
+        let mut res = String::with_capacity(text.len());
+        let mut state = State::Text;
+        for c in text.chars() {
+            state = match (state, c) {
+                (State::Text, '\x1b') => State::Escape,
+                (State::Text, '\u{9b}') => State::Csi,
+                (State::Text, c) => {
+                    res.push(c);
+                    State::Text
+                },
+                (State::Escape, '[') => State::Csi,
+                (State::Escape, ']' | 'P' | 'X' | '^' | '_') => State::Str,
+                (State::Escape, 'O') => State::Ss3,
+                (State::Escape | State::Intermediate, '\x20'..='\x2f') => {
+                    State::Intermediate
+                },
+                (State::Escape | State::Intermediate, _) => State::Text,
+                (State::Csi, '\x40'..='\x7e') => State::Text,
+                (State::Csi, _) => State::Csi,
+                (State::Str, '\x07' | '\u{9c}') => State::Text,
+                (State::Str, '\x1b') => State::StrEscape,
+                (State::Str, _) => State::Str,
+                (State::StrEscape, '\\') => State::Text,
+                (State::StrEscape, '\x1b') => State::StrEscape,
+                (State::StrEscape, _) => State::Str,
+                (State::Ss3, _) => State::Text,
+            };
+        }
+        res
+    }
+}
+pub mod newline {
+    use std::str::FromStr;
+
+    /// 换行符风格
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub enum Newline {
+        #[default]
+        Lf,
+        Crlf,
+        /// 保持原样
+        Keep,
+    }
+    impl FromStr for Newline {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "lf" => Ok(Self::Lf),
+                "crlf" => Ok(Self::Crlf),
+                "keep" => Ok(Self::Keep),
+                _ => Err(format!("unknown newline style: {s:?}")),
+            }
+        }
+    }
+
+    /// 去除开头的 UTF-8 BOM
+    pub fn strip_bom(text: &str) -> &str {
+        text.strip_prefix('\u{feff}').unwrap_or(text)
+    }
+
+    /// 将 `\r\n` 与单独的 `\r` 统一为 `\n`
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::newline::{strip_bom, to_lf, to_crlf};
+    /// let text = "\u{feff}第一行\r\n第二行\r\n\r\nlast\rline";
+    /// let lf = to_lf(strip_bom(text));
+    /// assert_eq!(lf, "第一行\n第二行\n\nlast\nline");
+    /// assert_eq!(to_lf(&lf), lf);
+    ///
+    /// let crlf = to_crlf(&lf);
+    /// assert_eq!(crlf, "第一行\r\n第二行\r\n\r\nlast\r\nline");
+    /// assert_eq!(to_crlf(&crlf), crlf);
+    /// assert_eq!(to_lf(&crlf), lf);
+    /// ```
+    pub fn to_lf(text: &str) -> String {
+        let mut res = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\r' {
+                chars.next_if_eq(&'\n');
+                res.push('\n');
+            } else {
+                res.push(c);
+            }
+        }
+        res
+    }
+
+    /// 将换行统一为 `\r\n`, 已有的 `\r\n` 不会重复转换
+    pub fn to_crlf(text: &str) -> String {
+        to_lf(text).replace('\n', "\r\n")
+    }
+}
+pub mod head {
+    use std::io::{self, BufRead};
+
+    /// 只读取输入的开头部分
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Head {
+        /// 前 n 行
+        Lines(usize),
+        /// 不超过 n 字节的完整行
+        Bytes(usize),
+    }
+
+    /// 读取开头部分, 并去除结尾的一个换行,
+    /// 开头的空白行会被跳过且不计入行数与字节数,
+    /// 没有非空行时返回 [`io::ErrorKind::UnexpectedEof`]
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::head::{read_head, Head};
+    /// let text = "a\nb\r\nc\n";
+    /// assert_eq!(read_head(&mut text.as_bytes(), Head::Lines(2)).unwrap(), "a\nb");
+    /// assert_eq!(read_head(&mut text.as_bytes(), Head::Lines(9)).unwrap(), "a\nb\r\nc");
+    /// assert_eq!(read_head(&mut text.as_bytes(), Head::Lines(0)).unwrap(), "");
+    /// assert_eq!(read_head(&mut text.as_bytes(), Head::Bytes(5)).unwrap(), "a\nb");
+    /// assert_eq!(read_head(&mut text.as_bytes(), Head::Bytes(4)).unwrap(), "a");
+    /// assert!(read_head(&mut text.as_bytes(), Head::Bytes(1)).is_err());
+    ///
+    /// // LF 与 CRLF
+    /// assert_eq!(read_head(&mut "hello\n".as_bytes(), Head::Lines(1)).unwrap(), "hello");
+    /// assert_eq!(read_head(&mut "hello\r\n".as_bytes(), Head::Lines(1)).unwrap(), "hello");
+    /// // 跳过开头的空白行
+    /// let text = "\n  \r\nhello\n\nworld\n";
+    /// assert_eq!(read_head(&mut text.as_bytes(), Head::Lines(1)).unwrap(), "hello");
+    /// assert_eq!(read_head(&mut text.as_bytes(), Head::Lines(2)).unwrap(), "hello\n");
+    /// assert_eq!(read_head(&mut text.as_bytes(), Head::Bytes(6)).unwrap(), "hello");
+    /// // 只有空白行
+    /// let e = read_head(&mut "\n \n\t\n".as_bytes(), Head::Lines(1)).unwrap_err();
+    /// assert_eq!(e.to_string(), "input contains no non-empty lines");
+    /// assert!(read_head(&mut "".as_bytes(), Head::Bytes(9)).is_err());
+    /// ```
+    pub fn read_head(reader: &mut impl BufRead, head: Head) -> io::Result<String> {
+        String::from_utf8(read_head_bytes(reader, head)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// 同 [`read_head`], 但不校验 UTF-8
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::head::{read_head_bytes, Head};
+    /// let text = b"\n\xd6\xd0\r\nb\n";
+    /// assert_eq!(read_head_bytes(&mut &text[..], Head::Lines(1)).unwrap(), b"\xd6\xd0");
+    /// ```
+    pub fn read_head_bytes(reader: &mut impl BufRead, head: Head) -> io::Result<Vec<u8>> {
+        if head == Head::Lines(0) {
+            return Ok(vec![]);
+        }
+        let Some(mut text) = first_non_blank_line(reader)? else {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "input contains no non-empty lines",
+            ));
+        };
+        match head {
+            Head::Lines(count) => {
+                for _ in 1..count {
+                    if reader.read_until(b'\n', &mut text)? == 0 { break }
+                }
+            },
+            Head::Bytes(max) => {
+                if text.len() > max {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("first line is longer than {max} bytes"),
+                    ));
+                }
+                loop {
+                    let mut line = vec![];
+                    if reader.read_until(b'\n', &mut line)? == 0 { break }
+                    if text.len() + line.len() > max { break }
+                    text.extend_from_slice(&line);
+                }
+            },
+        }
+        if text.ends_with(b"\n") {
+            text.pop();
+            if text.ends_with(b"\r") {
+                text.pop();
+            }
+        }
+        Ok(text)
+    }
+
+    /// 读取第一个不只含空白符的行, 到达结尾时返回 `None`
+    fn first_non_blank_line(reader: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+        let mut line = vec![];
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                return Ok(None);
+            }
+            if !String::from_utf8_lossy(&line).trim().is_empty() {
+                return Ok(Some(line));
+            }
+        }
+    }
+}
+pub mod stream {
+    use std::{
+        future::Future,
+        io::{self, BufRead, Read, Write},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use futures::StreamExt;
+    use tokio::sync::mpsc;
+
+    use crate::{
+        encoding::{decode_lossy, decode_utf8},
+        output::Formatter,
+        response::TransResult,
+        traits::LongEmptyFilter,
+        translater::{split_blocks, BlockTooLarge, LimitUnit, TranslateError},
+    };
+
+    /// 逐行读取输入并分为不超过 [`LimitUnit`] 上限的块,
+    /// 任何时候只持有一个块与下一行, 超长的行按字符边界切开.
+    /// 去除开头的 BOM, 并可先以 [`BlockReader::map_lines`] 处理每行再过滤多余的空白符
+    /// # Examples
+    /// ```
+    /// use std::{fs::{self, File}, io::{BufReader, Write}};
+    /// use baidu_fanyi::{
+    ///     stream::BlockReader,
+    ///     traits::FilterOutLongEmpty,
+    ///     translater::{LimitUnit, MAX_REQUEST_CHARS},
+    /// };
+    /// let path = std::env::temp_dir()
+    ///     .join(format!("baidu_fanyi_stream_{}.txt", std::process::id()));
+    /// let mut file = File::create(&path).unwrap();
+    /// let mut text = String::new();
+    /// for i in 0..100_000 {
+    ///     let line = format!("第 {i} 行    some text\n\n\n");
+    ///     file.write_all(line.as_bytes()).unwrap();
+    ///     text.push_str(&line);
+    /// }
+    /// // 超长的一行
+    /// let long = "长".repeat(MAX_REQUEST_CHARS + 10);
+    /// file.write_all(long.as_bytes()).unwrap();
+    /// text.push_str(&long);
+    /// drop(file);
+    /// assert!(fs::metadata(&path).unwrap().len() > 2_000_000);
+    ///
+    /// for unit in [LimitUnit::Bytes, LimitUnit::Chars] {
+    ///     let file = BufReader::new(File::open(&path).unwrap());
+    ///     let mut reader = BlockReader::new(file).filter(Some(2)).unit(unit);
+    ///     let mut joined = String::new();
+    ///     for block in reader.by_ref() {
+    ///         let block = block.unwrap();
+    ///         assert!(!block.is_empty() && unit.measure(&block) <= unit.max());
+    ///         joined.push_str(&block);
+    ///     }
+    ///     assert_eq!(joined, (&*text).filter_out_long_empty(2));
+    ///     assert_eq!(reader.bytes_read(), text.len() as u64);
+    ///     assert!(reader.peak_bytes() <= 2 * unit.max_bytes());
+    /// }
+    /// fs::remove_file(&path).unwrap();
+    ///
+    /// // 非法的 UTF-8 以整个输入中的位置报告
+    /// let mut reader = BlockReader::new(&b"a\nb\xff\n"[..]);
+    /// let e = reader.next().unwrap().unwrap_err();
+    /// assert_eq!(e.to_string(), "invalid UTF-8 at byte offset 3 (line 2): 62 [ff] 0a");
+    /// let mut reader = BlockReader::new(&b"\xef\xbb\xbfa\xff"[..]).lossy(true);
+    /// assert_eq!(reader.next().unwrap().unwrap(), "a\u{FFFD}");
+    /// assert_eq!(reader.replaced(), 1);
+    /// assert!(reader.next().is_none());
+    ///
+    /// // 只含转义序列的行去除后才被视为空白
+    /// let input = &b"a\n\x1b[0m\n\x1b[0m\n\x1b[0m\nb"[..];
+    /// let mut reader = BlockReader::new(input)
+    ///     .map_lines(|line| baidu_fanyi::ansi::strip_ansi(&line))
+    ///     .filter(Some(2));
+    /// assert_eq!(reader.next().unwrap().unwrap(), "a\n\nb");
+    /// ```
+    pub struct BlockReader<R> {
+        reader: R,
+        map: Option<Box<dyn FnMut(String) -> String + Send>>,
+        filter: Option<LongEmptyFilter>,
+        lossy: bool,
+        unit: LimitUnit,
+        /// 已读取但放不进上一个块的行
+        pending: Option<String>,
+        /// 切开超长行时留下的不完整 UTF-8 序列
+        carry: Vec<u8>,
+        bytes_read: u64,
+        lines_read: usize,
+        replaced: usize,
+        peak: usize,
+        done: bool,
+    }
+    impl<R: std::fmt::Debug> std::fmt::Debug for BlockReader<R> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("BlockReader")
+                .field("reader", &self.reader)
+                .field("filter", &self.filter)
+                .field("unit", &self.unit)
+                .field("bytes_read", &self.bytes_read)
+                .finish_non_exhaustive()
+        }
+    }
+    impl<R: BufRead> BlockReader<R> {
+        pub fn new(reader: R) -> Self {
+            Self {
+                reader,
+                map: None,
+                filter: None,
+                lossy: false,
+                unit: LimitUnit::default(),
+                pending: None,
+                carry: vec![],
+                bytes_read: 0,
+                lines_read: 0,
+                replaced: 0,
+                peak: 0,
+                done: false,
+            }
+        }
+
+        /// 在过滤空白符之前处理每行, 如统一换行符与去除转义序列,
+        /// 使其与一次读取全部输入时的处理顺序相同
+        pub fn map_lines(
+            mut self,
+            map: impl FnMut(String) -> String + Send + 'static,
+        ) -> Self {
+            self.map = Some(Box::new(map));
+            self
+        }
+
+        /// 过滤超过 `count` 个的连续空白符, `None` 为不过滤
+        pub fn filter(mut self, count: Option<usize>) -> Self {
+            self.filter = count.map(LongEmptyFilter::new);
+            self
+        }
+
+        /// 替换非法的 UTF-8 序列, 而非报错
+        pub fn lossy(mut self, lossy: bool) -> Self {
+            self.lossy = lossy;
+            self
+        }
+
+        /// 块大小的计数单位
+        pub fn unit(mut self, unit: LimitUnit) -> Self {
+            self.unit = unit;
+            self
+        }
+
+        /// 已读取的字节数
+        pub fn bytes_read(&self) -> u64 {
+            self.bytes_read
+        }
+
+        /// `lossy` 时替换的非法序列个数
+        pub fn replaced(&self) -> usize {
+            self.replaced
+        }
+
+        /// 同时持有的块与行的最大字节数
+        pub fn peak_bytes(&self) -> usize {
+            self.peak
+        }
+
+        /// 读取一行, 不超过 [`LimitUnit`] 的上限
+        fn next_line(&mut self) -> io::Result<Option<String>> {
+            let mut bytes = std::mem::take(&mut self.carry);
+            let limit = self.unit.max_bytes().saturating_sub(bytes.len()) as u64;
+            let len = (&mut self.reader).take(limit).read_until(b'\n', &mut bytes)?;
+            if bytes.is_empty() {
+                return Ok(None);
+            }
+            if len != 0 && !bytes.ends_with(b"\n") {
+                // 在字符边界切开超长的行
+                if let Err(e) = std::str::from_utf8(&bytes) {
+                    if e.error_len().is_none() && e.valid_up_to() != 0 {
+                        self.carry = bytes.split_off(e.valid_up_to());
+                    }
+                }
+            }
+            let (start, first) = (self.bytes_read, self.lines_read == 0);
+            let mut read = bytes.len();
+            let mut line = if self.lossy {
+                let (line, count) = decode_lossy(&bytes);
+                self.replaced += count;
+                line
+            } else {
+                decode_utf8(bytes).map_err(|mut e| {
+                    e.offset += start as usize;
+                    e.line += self.lines_read;
+                    io::Error::new(io::ErrorKind::InvalidData, e)
+                })?
+            };
+            if self.unit.measure(&line) > self.unit.max() {
+                // 按字符计数时超长, 剩余部分留到下一行
+                let end = self.unit.prefix_len(&line, self.unit.max());
+                let mut rest = line.split_off(end).into_bytes();
+                read = read.saturating_sub(rest.len());
+                rest.append(&mut self.carry);
+                self.carry = rest;
+            }
+            self.bytes_read += read as u64;
+            self.lines_read += line.matches('\n').count();
+            let line = match (first && start == 0, line.strip_prefix('\u{feff}')) {
+                (true, Some(line)) => line.to_owned(),
+                _ => line,
+            };
+            let line = match &mut self.map {
+                Some(map) => map(line),
+                None => line,
+            };
+            Ok(Some(match &mut self.filter {
+                Some(filter) => filter.push(&line),
+                None => line,
+            }))
+        }
+    }
+    impl<R: BufRead> Iterator for BlockReader<R> {
+        type Item = io::Result<String>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let mut block = self.pending.take().unwrap_or_default();
+            let mut sum = self.unit.measure(&block);
+            while !self.done {
+                let line = match self.next_line() {
+                    Ok(Some(line)) => line,
+                    Ok(None) => {
+                        self.done = true;
+                        break;
+                    },
+                    Err(e) => {
+                        self.done = true;
+                        return Some(Err(e));
+                    },
+                };
+                self.peak = self.peak.max(block.len() + line.len());
+                let size = self.unit.measure(&line);
+                match split_blocks(&mut sum, size, self.unit.max()) {
+                    Ok(false) => block.push_str(&line),
+                    Ok(true) => {
+                        self.pending = Some(line);
+                        break;
+                    },
+                    // 恰好为最大请求大小的行
+                    Err(BlockTooLarge) if block.is_empty() => {
+                        block = line;
+                        sum = size;
+                    },
+                    Err(BlockTooLarge) => {
+                        self.pending = Some(line);
+                        break;
+                    },
+                }
+            }
+            (!block.is_empty()).then_some(Ok(block))
+        }
+    }
+
+    /// [`pipeline`] 中存活的块数, 从读取出块到写出结束
+    #[derive(Debug, Default)]
+    pub struct BlockGauge {
+        live: AtomicUsize,
+        peak: AtomicUsize,
+    }
+    impl BlockGauge {
+        fn acquire(&self) {
+            let live = self.live.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(live, Ordering::SeqCst);
+        }
+
+        fn release(&self) {
+            self.live.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        /// 当前存活的块数
+        pub fn live(&self) -> usize {
+            self.live.load(Ordering::SeqCst)
+        }
+
+        /// 同时存活的最多块数
+        pub fn peak(&self) -> usize {
+            self.peak.load(Ordering::SeqCst)
+        }
+    }
+
+    /// [`pipeline`] 的错误
+    #[derive(Debug)]
+    pub enum PipelineError<E> {
+        Read(io::Error),
+        /// `write` 返回的错误
+        Write(E),
+    }
+
+    /// 在阻塞线程中读取块, 经容量为 `depth` 的通道交给至多 `depth` 个并发的
+    /// `translate`, 再按读取的顺序交给 `write`.
+    /// 同时存活的块不超过 `2 * depth + 1`, 记录在 `gauge` 中.
+    /// 读取出错时写出之前的块后返回错误, `write` 出错时立即返回
+    /// # Examples
+    /// ```
+    /// use std::{io::Cursor, sync::Arc, time::Duration};
+    /// use baidu_fanyi::stream::{pipeline, BlockGauge, BlockReader};
+    ///
+    /// let text = format!("{}\n", "a".repeat(99)).repeat(40_000);
+    /// let reader = BlockReader::new(Cursor::new(text.clone().into_bytes()));
+    /// let gauge = Arc::new(BlockGauge::default());
+    /// let depth = 4;
+    /// let mut count = 0;
+    /// let translate = |block: String| {
+    ///     // 使后读取的块可能先完成
+    ///     count += 1;
+    ///     let delay = Duration::from_millis(3 - count % 3);
+    ///     async move {
+    ///         tokio::time::sleep(delay).await;
+    ///         block.to_uppercase()
+    ///     }
+    /// };
+    /// let mut out = String::new();
+    /// let write = |dst: String| {
+    ///     out.push_str(&dst);
+    ///     Ok::<_, ()>(())
+    /// };
+    /// let reader = tokio::runtime::Runtime::new().unwrap()
+    ///     .block_on(pipeline(reader, depth, gauge.clone(), translate, write))
+    ///     .unwrap();
+    /// assert_eq!(out, text.to_uppercase());
+    /// assert_eq!(reader.bytes_read(), text.len() as u64);
+    /// assert!(1 < gauge.peak() && gauge.peak() <= 2 * depth + 1, "{}", gauge.peak());
+    /// assert_eq!(gauge.live(), 0);
+    /// ```
+    pub async fn pipeline<R, F, Fut, T, W, E>(
+        reader: BlockReader<R>,
+        depth: usize,
+        gauge: Arc<BlockGauge>,
+        mut translate: F,
+        mut write: W,
+    ) -> Result<BlockReader<R>, PipelineError<E>>
+    where
+        R: BufRead + Send + 'static,
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = T>,
+        W: FnMut(T) -> Result<(), E>,
+    {
+        let depth = depth.max(1);
+        let (tx, rx) = mpsc::channel(depth);
+        let reader_gauge = gauge.clone();
+        let reading = tokio::task::spawn_blocking(move || {
+            let mut reader = reader;
+            while let Some(block) = reader.next() {
+                let block = match block {
+                    Ok(block) => block,
+                    Err(e) => return (reader, Some(e)),
+                };
+                reader_gauge.acquire();
+                if tx.blocking_send(block).is_err() {
+                    // 写出端已停止
+                    reader_gauge.release();
+                    break;
+                }
+            }
+            (reader, None)
+        });
+        let blocks = futures::stream::unfold(rx, |mut rx| async {
+            rx.recv().await.map(|block| (block, rx))
+        });
+        let mut results = std::pin::pin!(blocks.map(&mut translate).buffered(depth));
+        while let Some(result) = results.next().await {
+            let written = write(result);
+            gauge.release();
+            written.map_err(PipelineError::Write)?;
+        }
+        match reading.await.expect("block reader panicked") {
+            (_, Some(e)) => Err(PipelineError::Read(e)),
+            (reader, None) => Ok(reader),
+        }
+    }
+
+    /// [`run_pipeline`] 的配置
+    pub struct PipelineConfig<R, F, W> {
+        /// 按行读取的输入
+        pub input: R,
+        /// 翻译不超过单次请求上限的一块
+        pub translate: F,
+        pub format: Formatter,
+        pub output: W,
+        /// 同时翻译的块数, 即 [`pipeline`] 的 `depth`
+        pub capacity: usize,
+        /// 块大小上限的单位
+        pub limit_unit: LimitUnit,
+    }
+    impl<R, F, W> PipelineConfig<R, F, W> {
+        /// 同时翻译 4 块, 使用默认的 [`LimitUnit`]
+        pub fn new(input: R, translate: F, format: Formatter, output: W) -> Self {
+            Self {
+                input,
+                translate,
+                format,
+                output,
+                capacity: 4,
+                limit_unit: LimitUnit::default(),
+            }
+        }
+    }
+
+    /// 以 [`pipeline`] 读取, 翻译并按 `format` 输出,
+    /// 全为空白的块不翻译, 翻译或输出出错时立即返回错误
+    /// # Examples
+    /// ```
+    /// use std::{io::Cursor, time::Duration};
+    /// use baidu_fanyi::{
+    ///     mini_fmt::Fmtter,
+    ///     response::{TransLine, TransResult},
+    ///     stream::{run_pipeline, PipelineConfig},
+    ///     translater::TranslateError,
+    /// };
+    ///
+    /// let text: String = (0..500).map(|i| format!("line {i}\n")).collect();
+    /// let mut batches = 0;
+    /// let translate = |batch: String| {
+    ///     batches += 1;
+    ///     async move {
+    ///         tokio::time::sleep(Duration::from_millis(1)).await;
+    ///         let lines = batch.lines()
+    ///             .map(|src| TransLine { src: src.into(), dst: src.to_uppercase() })
+    ///             .collect();
+    ///         Ok::<_, TranslateError>(TransResult {
+    ///             from: "en".into(),
+    ///             to: "zh".into(),
+    ///             lines,
+    ///         })
+    ///     }
+    /// };
+    /// let format = vec![Fmtter::build("%s%n").unwrap()].into();
+    /// let mut out = Vec::new();
+    /// let config = PipelineConfig::new(Cursor::new(text.clone()), translate, format, &mut out);
+    /// tokio::runtime::Runtime::new().unwrap()
+    ///     .block_on(run_pipeline(config))
+    ///     .unwrap();
+    /// assert_eq!(String::from_utf8(out).unwrap(), text.to_uppercase());
+    /// assert!(batches < 500, "{batches}");
+    /// ```
+    ///
+    /// 使用 [`Translater`](crate::translater::Translater) 时 `translate` 可为
+    /// `|batch| async { Ok(TransResult::from_json(&translater.translate(batch).await?)?) }`
+    pub async fn run_pipeline<R, F, Fut, W>(
+        config: PipelineConfig<R, F, W>,
+    ) -> Result<(), TranslateError>
+    where
+        R: BufRead + Send + 'static,
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = Result<TransResult, TranslateError>>,
+        W: Write,
+    {
+        let PipelineConfig {
+            input,
+            mut translate,
+            format,
+            mut output,
+            capacity,
+            limit_unit,
+        } = config;
+        let reader = BlockReader::new(input).unit(limit_unit);
+        let translate = |block: String| {
+            let translating = (!block.trim().is_empty()).then(|| translate(block));
+            async move {
+                match translating {
+                    Some(translating) => translating.await.map(Some),
+                    None => Ok(None),
+                }
+            }
+        };
+        let write = |result: Result<Option<TransResult>, TranslateError>| {
+            match result? {
+                Some(result) => format.write_result(&result, &mut output),
+                None => Ok(()),
+            }
+        };
+        let gauge = Arc::default();
+        pipeline(reader, capacity, gauge, translate, write).await.map_err(|e| match e {
+            PipelineError::Read(e) => TranslateError::Io(e),
+            PipelineError::Write(e) => e,
+        })?;
+        output.flush().map_err(TranslateError::Io)
+    }
+}
+pub mod encoding {
+    use std::fmt::Display;
+
+    use encoding_rs::{DecoderResult, Encoding, GB18030, UTF_16BE, UTF_16LE, UTF_8};
+
+    /// 输入文本的编码
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum InputEncoding {
+        /// 根据 BOM 判断, 否则在 UTF-8 与 GB18030 中猜测
+        Auto,
+        Fixed(&'static Encoding),
+    }
+    impl InputEncoding {
+        /// 从编码名构建, 如 `gbk`, `big5`, `utf-16le`, `shift_jis`, `latin1`
+        pub fn from_name(name: &str) -> Option<Self> {
+            if name.eq_ignore_ascii_case("auto") {
+                return Some(Self::Auto);
+            }
+            Encoding::for_label(name.as_bytes()).map(Self::Fixed)
+        }
+    }
+
+    /// 解码错误
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct DecodeError {
+        pub encoding: &'static str,
+        /// 第一个非法序列的字节偏移
+        pub offset: usize,
+    }
+    impl Display for DecodeError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "invalid {} sequence at byte offset {}",
+                   self.encoding, self.offset)
+        }
+    }
+    impl std::error::Error for DecodeError {}
+
+    /// UTF-8 校验错误
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Utf8Error {
+        /// 第一个非法序列的字节偏移
+        pub offset: usize,
+        /// 非法序列所在的行, 从 1 开始
+        pub line: usize,
+        /// 附近字节的十六进制预览, 非法序列以 `[]` 括起
+        pub preview: String,
+    }
+    impl Display for Utf8Error {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "invalid UTF-8 at byte offset {} (line {}): {}",
+                   self.offset, self.line, self.preview)
+        }
+    }
+    impl std::error::Error for Utf8Error {}
+
+    /// 预览中非法序列前后保留的字节数
+    const PREVIEW_CONTEXT: usize = 8;
+
+    /// 校验并转换为 UTF-8 字符串
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::encoding::decode_utf8;
+    /// assert_eq!(decode_utf8("中文".into()).unwrap(), "中文");
+    ///
+    /// // GBK 编码的 "中文"
+    /// let err = decode_utf8(b"ab\ncd\xd6\xd0\xce\xc4".to_vec()).unwrap_err();
+    /// assert_eq!((err.offset, err.line), (5, 2));
+    /// assert_eq!(err.preview, "61 62 0a 63 64 [d6] d0 ce c4");
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "invalid UTF-8 at byte offset 5 (line 2): 61 62 0a 63 64 [d6] d0 ce c4",
+    /// );
+    ///
+    /// // 结尾不完整的序列
+    /// let err = decode_utf8(b"a\xe4\xb8".to_vec()).unwrap_err();
+    /// assert_eq!(err.preview, "61 [e4 b8]");
+    /// ```
+    pub fn decode_utf8(bytes: Vec<u8>) -> Result<String, Utf8Error> {
+        String::from_utf8(bytes).map_err(|e| {
+            let error = e.utf8_error();
+            let bytes = e.as_bytes();
+            let offset = error.valid_up_to();
+            let bad_end = offset + error.error_len().unwrap_or(bytes.len() - offset);
+            let hex = |bytes: &[u8]| {
+                bytes.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>()
+            };
+            let before = hex(&bytes[offset.saturating_sub(PREVIEW_CONTEXT)..offset]);
+            let bad = hex(&bytes[offset..bad_end]);
+            let after = hex(&bytes[bad_end..bytes.len().min(bad_end + PREVIEW_CONTEXT)]);
+            let mut preview = before;
+            preview.push(format!("[{}]", bad.join(" ")));
+            preview.extend(after);
+            Utf8Error {
+                offset,
+                line: bytes[..offset].iter().filter(|&&b| b == b'\n').count() + 1,
+                preview: preview.join(" "),
+            }
+        })
+    }
+
+    /// 将非法的 UTF-8 序列替换为 U+FFFD, 返回结果与替换的次数
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::encoding::decode_lossy;
+    /// assert_eq!(decode_lossy(b"a\xffb\xd6\xd0\xef\xbf\xbd"), ("a\u{FFFD}b\u{FFFD}\u{FFFD}\u{FFFD}".into(), 3));
+    /// assert_eq!(decode_lossy("中文".as_bytes()), ("中文".into(), 0));
+    /// ```
+    pub fn decode_lossy(bytes: &[u8]) -> (String, usize) {
+        let mut res = String::with_capacity(bytes.len());
+        let mut count = 0;
+        for chunk in bytes.utf8_chunks() {
+            res.push_str(chunk.valid());
+            if !chunk.invalid().is_empty() {
+                res.push(char::REPLACEMENT_CHARACTER);
+                count += 1;
+            }
+        }
+        (res, count)
+    }
+
+    /// 根据 BOM 或内容猜测编码
+    pub fn sniff(bytes: &[u8]) -> &'static Encoding {
+        if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+            encoding
+        } else if std::str::from_utf8(bytes).is_ok() {
+            UTF_8
+        } else {
+            GB18030
+        }
+    }
+
+    /// 解码为 UTF-8 字符串, 开头的 BOM 会被去除
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::encoding::{decode, InputEncoding};
+    /// let enc = |name| InputEncoding::from_name(name).unwrap();
+    /// assert_eq!(decode(b"\xd6\xd0\xce\xc4", enc("gbk")).unwrap(), "中文");
+    /// assert_eq!(decode(b"\x81\x30\x81\x30", enc("gb18030")).unwrap(), "\u{80}");
+    /// assert_eq!(decode(b"\xa4\xa4\xa4\xe5", enc("big5")).unwrap(), "中文");
+    /// assert_eq!(decode(b"\x2d\x4e\x87\x65", enc("utf-16le")).unwrap(), "中文");
+    /// assert_eq!(decode(b"\x4e\x2d\x65\x87", enc("utf-16be")).unwrap(), "中文");
+    /// assert_eq!(decode(b"\x93\xfa\x96\x7b", enc("shift_jis")).unwrap(), "日本");
+    /// assert_eq!(decode(b"caf\xe9", enc("latin1")).unwrap(), "café");
+    ///
+    /// // auto
+    /// let auto = InputEncoding::Auto;
+    /// assert_eq!(decode(b"\xff\xfe\x2d\x4e", auto).unwrap(), "中");
+    /// assert_eq!(decode("中文".as_bytes(), auto).unwrap(), "中文");
+    /// assert_eq!(decode(b"\xd6\xd0\xce\xc4", auto).unwrap(), "中文");
+    ///
+    /// let err = decode(b"ab\xd6\xd0\xff", enc("gbk")).unwrap_err();
+    /// assert_eq!(err.offset, 4);
+    /// ```
+    pub fn decode(
+        bytes: &[u8],
+        encoding: InputEncoding,
+    ) -> Result<String, DecodeError> {
+        let encoding = match encoding {
+            InputEncoding::Auto => sniff(bytes),
+            InputEncoding::Fixed(encoding) => encoding,
+        };
+        let mut decoder = encoding.new_decoder_with_bom_removal();
+        let len = decoder
+            .max_utf8_buffer_length_without_replacement(bytes.len())
+            .expect("input too long");
+        let mut res = String::with_capacity(len);
+        let (result, read)
+            = decoder.decode_to_string_without_replacement(bytes, &mut res, true);
+        match result {
+            DecoderResult::InputEmpty => Ok(res),
+            DecoderResult::Malformed(bad, extra) => Err(DecodeError {
+                encoding: encoding.name(),
+                offset: read - bad as usize - extra as usize,
+            }),
+            DecoderResult::OutputFull => unreachable!("output buffer is full"),
+        }
+    }
+
+    /// 将格式化完成的输出编码为 `encoding`,
+    /// 目标编码无法表示的字符会被替换为 `&#NNNN;` 形式的字符引用
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::encoding::encode_output;
+    /// use encoding_rs::{Encoding, GBK, UTF_8};
+    /// assert_eq!(encode_output("中文", GBK), b"\xd6\xd0\xce\xc4");
+    /// assert_eq!(encode_output("你好, world\n", GBK), b"\xc4\xe3\xba\xc3, world\n");
+    /// assert_eq!(encode_output("中文", UTF_8), "中文".as_bytes());
+    /// let latin1 = Encoding::for_label(b"latin1").unwrap();
+    /// assert_eq!(encode_output("café 中", latin1), b"caf\xe9 &#20013;");
+    /// let utf16le = Encoding::for_label(b"utf-16le").unwrap();
+    /// assert_eq!(encode_output("中文", utf16le), b"\x2d\x4e\x87\x65");
+    /// ```
+    pub fn encode_output(text: &str, encoding: &'static Encoding) -> Vec<u8> {
+        // encoding_rs 不支持编码为 UTF-16
+        if encoding == UTF_16LE {
+            return text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        }
+        if encoding == UTF_16BE {
+            return text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        }
+        encoding.encode(text).0.into_owned()
+    }
+}
+pub mod response {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+    use serde_json::Value;
+
+    use crate::translater::ApiError;
+
+    pub type JSONData = HashMap<String, Value>;
+
+    /// 单行翻译结果
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+    pub struct TransLine {
+        pub src: String,
+        pub dst: String,
+    }
+
+    /// 一行结果的格式化参数, [`FmtArgs::slots`] 为传给
+    /// [`Fmtter::fmt_str`](crate::mini_fmt::Fmtter::fmt_str) 的参数
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct FmtArgs<'a> {
+        /// `%0`
+        pub dst: &'a str,
+        /// `%1`
+        pub src: &'a str,
+        /// `%2`, 检测出的源语言
+        pub from: &'a str,
+        /// `%3`
+        pub to: &'a str,
+    }
+    impl<'a> FmtArgs<'a> {
+        /// 具名值 `%{dst}s` 等的名称, 顺序同 [`FmtArgs::slots`]
+        pub const NAMES: [&'static str; 4] = ["dst", "src", "from", "to"];
+
+        /// 按参数下标排列
+        pub fn slots(&self) -> [&'a str; 4] {
+            [self.dst, self.src, self.from, self.to]
+        }
+
+        /// 按 [`FmtArgs::NAMES`] 中的名称取值
+        pub fn get(&self, name: &str) -> Option<&'a str> {
+            Self::NAMES.iter()
+                .position(|&x| x == name)
+                .map(|i| self.slots()[i])
+        }
+    }
+
+    /// 错误数据最多输出的字符数
+    const MAX_DUMP_CHARS: usize = 200;
+
+    /// 响应数据中缺失或类型错误的字段
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct FormatError {
+        /// 字段路径, 如 `trans_result[3].dst`
+        pub path: String,
+        /// 截断后的出错数据
+        pub entry: String,
+    }
+    impl FormatError {
+        fn new(path: impl Into<String>, entry: serde_json::Result<String>) -> Self {
+            let entry = entry.unwrap_or_default();
+            let entry = match entry.char_indices().nth(MAX_DUMP_CHARS) {
+                Some((i, _)) => format!("{}...", &entry[..i]),
+                None => entry,
+            };
+            Self { path: path.into(), entry }
+        }
+    }
+    impl std::fmt::Display for FormatError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "result data error at `{}`: {}", self.path, self.entry)
+        }
+    }
+    impl std::error::Error for FormatError {}
+
+    /// 解析后的翻译响应, 可直接从接口返回的 json 反序列化
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::response::{TransLine, TransResult};
+    /// // 接口返回的原始数据
+    /// let fixture = r#"{
+    ///     "from": "en",
+    ///     "to": "zh",
+    ///     "trans_result": [
+    ///         {"src": "apple", "dst": "苹果"},
+    ///         {"src": "Hello World!", "dst": "你好，世界！"}
+    ///     ]
+    /// }"#;
+    /// let expected = TransResult {
+    ///     from: "en".into(),
+    ///     to: "zh".into(),
+    ///     lines: vec![
+    ///         TransLine { src: "apple".into(), dst: "苹果".into() },
+    ///         TransLine { src: "Hello World!".into(), dst: "你好，世界！".into() },
+    ///     ],
+    /// };
+    /// assert_eq!(serde_json::from_str::<TransResult>(fixture).unwrap(), expected);
+    /// let object = serde_json::from_str(fixture).unwrap();
+    /// assert_eq!(TransResult::from_json(&object).unwrap(), expected);
+    /// ```
+    #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+    pub struct TransResult {
+        /// 源语言, `from=auto` 时为检测出的语言
+        pub from: String,
+        pub to: String,
+        #[serde(rename = "trans_result")]
+        pub lines: Vec<TransLine>,
+    }
+    impl TransResult {
+        /// 从返回的 json 数据构建, 字段缺失或类型错误时返回其路径
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::response::TransResult;
+        /// let parse = |json| {
+        ///     TransResult::from_json(&serde_json::from_str(json).unwrap())
+        /// };
+        /// let e = parse(r#"{"from": "en", "to": "zh", "trans_result": null}"#)
+        ///     .unwrap_err();
+        /// assert_eq!(e.path, "trans_result");
+        /// let e = parse(r#"{"to": "zh", "trans_result": []}"#).unwrap_err();
+        /// assert_eq!(e.path, "from");
+        /// let e = parse(r#"{"from": "en", "to": 1, "trans_result": []}"#).unwrap_err();
+        /// assert_eq!(e.path, "to");
+        /// let e = parse(r#"{"from": "en", "to": "zh", "trans_result": [1]}"#)
+        ///     .unwrap_err();
+        /// assert_eq!((&*e.path, &*e.entry), ("trans_result[0]", "1"));
+        /// let e = parse(r#"{"from": "en", "to": "zh", "trans_result": [
+        ///     {"src": "a", "dst": "甲"},
+        ///     {"dst": "乙"}
+        /// ]}"#).unwrap_err();
+        /// assert_eq!((&*e.path, &*e.entry), ("trans_result[1].src", r#"{"dst":"乙"}"#));
+        /// let e = parse(r#"{"from": "en", "to": "zh", "trans_result": [
+        ///     {"src": "a", "dst": 1}
+        /// ]}"#).unwrap_err();
+        /// assert_eq!(e.path, "trans_result[0].dst");
+        ///
+        /// let long = format!(r#"{{"from": "{}"}}"#, "x".repeat(300));
+        /// let e = parse(&long).unwrap_err();
+        /// assert!(e.entry.ends_with("...") && e.entry.len() < 300);
+        /// ```
+        pub fn from_json(object: &JSONData) -> Result<Self, FormatError> {
+            // 行中的错误输出该行, 其它输出整个对象
+            let whole = |path: &str| FormatError::new(path, serde_json::to_string(object));
+            let string = |value: Option<&Value>| match value {
+                Some(Value::String(s)) => Some(s.clone()),
+                _ => None,
+            };
+            let from = string(object.get("from")).ok_or_else(|| whole("from"))?;
+            let to = string(object.get("to")).ok_or_else(|| whole("to"))?;
+            let Some(Value::Array(items)) = object.get("trans_result") else {
+                return Err(whole("trans_result"));
+            };
+            let lines = items.iter().enumerate().map(|(i, item)| {
+                let error = |path: String| FormatError::new(path, serde_json::to_string(item));
+                if !item.is_object() {
+                    return Err(error(format!("trans_result[{i}]")));
+                }
+                let field = |key| string(item.get(key))
+                    .ok_or_else(|| error(format!("trans_result[{i}].{key}")));
+                Ok(TransLine { src: field("src")?, dst: field("dst")? })
+            }).collect::<Result<_, _>>()?;
+            Ok(Self { from, to, lines })
+        }
+
+
+        /// 每一行的格式化参数
+        ///
+        /// | index | value |
+        /// |-------|-------|
+        /// | 0     | dst   |
+        /// | 1     | src   |
+        /// | 2     | from  |
+        /// | 3     | to    |
+        ///
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::{mini_fmt::Fmtter, response::TransResult};
+        /// let object = serde_json::from_str(r#"{
+        ///     "from": "zh",
+        ///     "to": "en",
+        ///     "trans_result": [{"src": "你好", "dst": "Hello"}]
+        /// }"#).unwrap();
+        /// let result = TransResult::from_json(&object).unwrap();
+        /// let fmtter = Fmtter::build("%0s [%2s->%3s]%n").unwrap();
+        /// let lines = result.fmt_args()
+        ///     .map(|args| fmtter.fmt_str(&args.slots()))
+        ///     .collect::<Vec<_>>();
+        /// assert_eq!(lines, ["Hello [zh->en]\n"]);
+        /// ```
+        pub fn fmt_args(&self) -> impl Iterator<Item = FmtArgs<'_>> {
+            self.lines.iter().map(|line| FmtArgs {
+                dst: &line.dst,
+                src: &line.src,
+                from: &self.from,
+                to: &self.to,
+            })
+        }
+    }
+
+    /// 分块翻译的结果, 失败的块记录其错误码
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct Session {
+        pub blocks: Vec<Result<TransResult, String>>,
+    }
+    impl Session {
+        /// 失败的块的下标
+        pub fn failed(&self) -> Vec<usize> {
+            self.blocks.iter()
+                .enumerate()
+                .filter(|(_, block)| block.is_err())
+                .map(|(i, _)| i)
+                .collect()
+        }
+
+        /// 合并所有成功的块
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::response::{Session, TransLine, TransResult};
+        /// let block = |src: &str, dst: &str| Ok(TransResult {
+        ///     from: "zh".into(),
+        ///     to: "en".into(),
+        ///     lines: vec![TransLine { src: src.into(), dst: dst.into() }],
+        /// });
+        /// let session = Session {
+        ///     blocks: vec![block("一", "one"), Err("54003".into()), block("三", "three")],
+        /// };
+        /// assert_eq!(session.failed(), [1]);
+        /// let merged = session.merged();
+        /// assert_eq!(merged.from, "zh");
+        /// assert_eq!(merged.lines.len(), 2);
+        /// assert_eq!(merged.lines[1].dst, "three");
+        /// ```
+        pub fn merged(&self) -> TransResult {
+            let mut results = self.blocks.iter().filter_map(|block| block.as_ref().ok());
+            let Some(first) = results.next() else {
+                return TransResult {
+                    from: String::new(),
+                    to: String::new(),
+                    lines: vec![],
+                };
+            };
+            let mut merged = first.clone();
+            for result in results {
+                merged.lines.extend_from_slice(&result.lines);
+            }
+            merged
+        }
+    }
+
+    /// 从语种识别接口返回的 json 数据中取出语言
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::response::detected_lang;
+    /// let object = serde_json::from_str(r#"{
+    ///     "error_code": 0,
+    ///     "error_msg": "success",
+    ///     "data": {"src": "en"}
+    /// }"#).unwrap();
+    /// assert_eq!(detected_lang(&object).unwrap(), "en");
+    ///
+    /// let object = serde_json::from_str(r#"{
+    ///     "error_code": 54001,
+    ///     "error_msg": "Invalid Sign"
+    /// }"#).unwrap();
+    /// assert!(detected_lang(&object).unwrap_err().contains("Invalid Sign"));
+    /// ```
+    pub fn detected_lang(object: &JSONData) -> Result<String, String> {
+        object.get("data")
+            .and_then(|data| data.get("src"))
+            .and_then(Value::as_str)
+            .map(Into::into)
+            .ok_or_else(|| match object.get("error_msg") {
+                Some(Value::String(msg)) => format!("detect error: {msg}"),
+                _ => format!("detect data error: {:#?}", object),
+            })
+    }
+
+    /// 读取保存的翻译接口响应, 可为单个 json 对象, 或每行一个对象的 NDJSON,
+    /// 每个响应为一个块, 接口报错的响应记录其错误码
+    ///
+    /// 出错时返回出错响应的序号 (从 1 开始) 与错误信息
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::response::parse_saved;
+    /// let session = parse_saved(r#"{
+    ///     "from": "en",
+    ///     "to": "zh",
+    ///     "trans_result": [{"src": "one", "dst": "一"}]
+    /// }"#).unwrap();
+    /// assert_eq!(session.merged().lines[0].dst, "一");
+    ///
+    /// let session = parse_saved(concat!(
+    ///     r#"{"from": "en", "to": "zh", "trans_result": [{"src": "a", "dst": "甲"}]}"#, "\n",
+    ///     r#"{"error_code": "54003", "error_msg": "Invalid Access Limit"}"#, "\n",
+    /// )).unwrap();
+    /// assert_eq!(session.failed(), [1]);
+    /// assert_eq!(session.blocks[1], Err("54003".into()));
+    ///
+    /// let (i, e) = parse_saved(concat!(
+    ///     r#"{"from": "en", "to": "zh", "trans_result": []}"#, "\n",
+    ///     r#"{"from": "en", "to": "zh", "trans_result": [{"src": "a"}]}"#, "\n",
+    /// )).unwrap_err();
+    /// assert_eq!(i, 2);
+    /// assert!(e.contains("`trans_result[0].dst`"), "{e}");
+    /// assert_eq!(parse_saved(r#"{"from": "#).unwrap_err().0, 1);
+    /// assert!(parse_saved("").unwrap().blocks.is_empty());
+    /// ```
+    pub fn parse_saved(text: &str) -> Result<Session, (usize, String)> {
+        let objects = serde_json::Deserializer::from_str(text).into_iter::<JSONData>();
+        let mut session = Session::default();
+        for (i, object) in objects.enumerate() {
+            let object = object.map_err(|e| (i + 1, e.to_string()))?;
+            let block = match ApiError::from_json(&object) {
+                Some(e) => Err(e.error_code().to_string()),
+                None => Ok(TransResult::from_json(&object)
+                    .map_err(|e| (i + 1, e.to_string()))?),
+            };
+            session.blocks.push(block);
+        }
+        Ok(session)
+    }
+}
+pub mod output {
+    use std::io::Write;
+
+    use crate::{
+        mini_fmt::Fmtter,
+        response::{FmtArgs, FormatError, Session, TransLine, TransResult},
+        translater::TranslateError,
+    };
+
+    /// 依次使用多个格式格式化翻译结果, 每个格式依次格式化所有行.
+    /// 每行的参数为 [`FmtArgs::slots`], 也可使用 [`FmtArgs::NAMES`] 中的具名值
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::{
+    ///     mini_fmt::Fmtter,
+    ///     output::Formatter,
+    ///     response::{TransLine, TransResult},
+    /// };
+    /// let result = TransResult {
+    ///     from: "zh".into(),
+    ///     to: "en".into(),
+    ///     lines: vec![
+    ///         TransLine { src: "你好".into(), dst: "Hello".into() },
+    ///         TransLine { src: "世界".into(), dst: "world".into() },
+    ///     ],
+    /// };
+    /// let formatter = Formatter::new(
+    ///     ["%s%n", "%{src}r -> %0r [%2s]%n"].map(|s| Fmtter::build(s).unwrap()).into(),
+    /// );
+    /// assert_eq!(formatter.format_result(&result).unwrap(), [
+    ///     "Hello\n",
+    ///     "world\n",
+    ///     "\"你好\" -> \"Hello\" [zh]\n",
+    ///     "\"世界\" -> \"world\" [zh]\n",
+    /// ]);
+    /// assert_eq!(
+    ///     formatter.format_line(&result.lines[1], ("zh", "en")).unwrap(),
+    ///     "world\n\"世界\" -> \"world\" [zh]\n",
+    /// );
+    ///
+    /// let formatter = Formatter::new(vec![Fmtter::build("%{lang}s").unwrap()]);
+    /// assert!(formatter.format_result(&result).unwrap_err().contains("lang"));
+    /// ```
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct Formatter {
+        fmtters: Vec<Fmtter>,
+    }
+    impl From<Vec<Fmtter>> for Formatter {
+        fn from(fmtters: Vec<Fmtter>) -> Self {
+            Self { fmtters }
+        }
+    }
+    impl Formatter {
+        pub fn new(fmtters: Vec<Fmtter>) -> Self {
+            Self { fmtters }
+        }
+
+        pub fn fmtters(&self) -> &[Fmtter] {
+            &self.fmtters
+        }
+
+        /// 每个格式依次格式化所有行, 每个元素为一行的一个格式的结果
+        pub fn format_result(&self, result: &TransResult) -> Result<Vec<String>, String> {
+            format_lines(&self.fmtters, result).map_err(|e| e.entry)
+        }
+
+        /// 所有格式依次格式化一行, `langs` 为源语言与目标语言
+        pub fn format_line(&self, line: &TransLine, langs: (&str, &str)) -> Result<String, String> {
+            let (from, to) = langs;
+            let args = FmtArgs { dst: &line.dst, src: &line.src, from, to };
+            self.fmtters.iter()
+                .map(|fmtter| format_args(fmtter, &args))
+                .collect()
+        }
+
+        /// 同 [`Formatter::format_result`], 但每格式化一行就写入 `out`
+        pub fn write_result<W: Write>(
+            &self,
+            result: &TransResult,
+            out: &mut W,
+        ) -> Result<(), TranslateError> {
+            write_lines(&self.fmtters, result, out)
+        }
+
+        /// 同 [`format_session_stream`]
+        pub fn write_session<W: Write>(
+            &self,
+            session: &Session,
+            marker: &str,
+            out: &mut W,
+        ) -> Result<(), TranslateError> {
+            format_session_stream(&self.fmtters, session, marker, out)
+        }
+    }
+
+    /// 使用一个格式格式化一行
+    fn format_args(fmtter: &Fmtter, args: &FmtArgs) -> Result<String, String> {
+        fmtter.try_fmt_str_with(&args.slots(), |name| args.get(name))
+    }
+
+    /// 使用一个格式格式化第 `index` 行, 错误的路径为该行在响应中的位置
+    fn format_line_at(
+        fmtter: &Fmtter,
+        index: usize,
+        args: &FmtArgs,
+    ) -> Result<String, FormatError> {
+        format_args(fmtter, args)
+            .map_err(|entry| FormatError { path: format!("trans_result[{index}]"), entry })
+    }
+
+    fn format_lines(fmtters: &[Fmtter], result: &TransResult) -> Result<Vec<String>, FormatError> {
+        let mut lines = Vec::with_capacity(result.lines.len() * fmtters.len());
+        for fmtter in fmtters {
+            for (i, args) in result.fmt_args().enumerate() {
+                lines.push(format_line_at(fmtter, i, &args)?);
+            }
+        }
+        Ok(lines)
+    }
+
+    fn write_lines<W: Write>(
+        fmtters: &[Fmtter],
+        result: &TransResult,
+        out: &mut W,
+    ) -> Result<(), TranslateError> {
+        for fmtter in fmtters {
+            for (i, args) in result.fmt_args().enumerate() {
+                let line = format_line_at(fmtter, i, &args)?;
+                out.write_all(line.as_bytes()).map_err(TranslateError::Io)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 格式化翻译结果, 每个格式依次格式化所有行.
+    /// 格式的参数不足时返回出错的行, 见 [`Formatter::format_result`]
+    pub fn format_out(
+        fmtters: &[Fmtter],
+        result: &TransResult,
+    ) -> Result<Vec<String>, FormatError> {
+        format_lines(fmtters, result)
+    }
+
+    /// 同 [`format_out`], 但每格式化一行就写入 `out`
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::{
+    ///     mini_fmt::Fmtter,
+    ///     output::{format_out, format_out_stream},
+    ///     response::{TransLine, TransResult},
+    /// };
+    /// let result = TransResult {
+    ///     from: "zh".into(),
+    ///     to: "en".into(),
+    ///     lines: vec![
+    ///         TransLine { src: "你好".into(), dst: "Hello".into() },
+    ///         TransLine { src: "世界".into(), dst: "world".into() },
+    ///     ],
+    /// };
+    /// let fmtters = ["%s%n", "%1r -> %0r [%2s]%n"]
+    ///     .map(|s| Fmtter::build(s).unwrap());
+    /// let mut out = Vec::new();
+    /// format_out_stream(&fmtters, &result, &mut out).unwrap();
+    /// assert_eq!(out, format_out(&fmtters, &result).unwrap().concat().into_bytes());
+    /// ```
+    pub fn format_out_stream<W: Write>(
+        fmtters: &[Fmtter],
+        result: &TransResult,
+        out: &mut W,
+    ) -> Result<(), TranslateError> {
+        write_lines(fmtters, result, out)
+    }
+
+    /// 默认的失败块标记, `<code>` 会被替换为错误码
+    pub const DEFAULT_ERROR_MARKER: &str = "[[translation failed: <code>]]";
+
+    /// 失败块的标记行
+    pub fn error_marker(marker: &str, code: &str) -> String {
+        format!("{}\n", marker.replace("<code>", code))
+    }
+
+    /// 同 [`format_out_stream`], 失败的块在其位置输出一行标记
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::{
+    ///     mini_fmt::Fmtter,
+    ///     output::{format_session_stream, DEFAULT_ERROR_MARKER},
+    ///     response::{Session, TransLine, TransResult},
+    /// };
+    /// let block = |src: &str, dst: &str| Ok(TransResult {
+    ///     from: "zh".into(),
+    ///     to: "en".into(),
+    ///     lines: vec![TransLine { src: src.into(), dst: dst.into() }],
+    /// });
+    /// let session = Session {
+    ///     blocks: vec![block("一", "one"), Err("54003".into()), block("三", "three")],
+    /// };
+    /// let fmtters = [Fmtter::build("%s%n").unwrap()];
+    /// let mut out = Vec::new();
+    /// format_session_stream(&fmtters, &session, DEFAULT_ERROR_MARKER, &mut out)
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "one\n[[translation failed: 54003]]\nthree\n",
+    /// );
+    /// ```
+    pub fn format_session_stream<W: Write>(
+        fmtters: &[Fmtter],
+        session: &Session,
+        marker: &str,
+        out: &mut W,
+    ) -> Result<(), TranslateError> {
+        for fmtter in fmtters.iter() {
+            for block in &session.blocks {
+                match block {
+                    Ok(result) => {
+                        format_out_stream(std::slice::from_ref(fmtter), result, out)?
+                    },
+                    Err(code) => {
+                        out.write_all(error_marker(marker, code).as_bytes())
+                            .map_err(TranslateError::Io)?
+                    },
+                }
+            }
+        }
+        Ok(())
+    }
+}
+pub mod dedup {
+    use std::collections::HashMap;
+
+    use crate::response::{Session, TransLine, TransResult};
+
+    /// 按行去重后的文本, 每个不同的非空行只保留第一次出现
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct Deduped {
+        /// 去重后的文本, 每行以换行结尾
+        pub text: String,
+        /// 原文每个非空行在 `text` 中的行号
+        positions: Vec<usize>,
+        /// 去掉的重复行的字符数, 不包含换行符
+        pub saved_chars: usize,
+    }
+    impl Deduped {
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::dedup::Deduped;
+        /// let deduped = Deduped::new("hello\nworld\n\nhello\nhello");
+        /// assert_eq!(deduped.text, "hello\nworld\n");
+        /// assert_eq!(deduped.saved_chars, 10);
+        ///
+        /// // 80% 的行是重复的样板段落
+        /// let text = (0..50)
+        ///     .map(|i| if i % 5 == 0 { format!("line {i}") } else { "boilerplate".into() })
+        ///     .collect::<Vec<_>>()
+        ///     .join("\n");
+        /// let deduped = Deduped::new(&text);
+        /// assert_eq!(deduped.text.lines().count(), 11);
+        /// assert_eq!(deduped.saved_chars, 39 * "boilerplate".len());
+        /// ```
+        pub fn new(text: &str) -> Self {
+            let mut seen = HashMap::new();
+            let mut deduped = Self::default();
+            for line in text.lines().filter(|line| !line.trim().is_empty()) {
+                let next = seen.len();
+                let pos = *seen.entry(line).or_insert(next);
+                if pos == next {
+                    deduped.text.push_str(line);
+                    deduped.text.push('\n');
+                } else {
+                    deduped.saved_chars += line.chars().count();
+                }
+                deduped.positions.push(pos);
+            }
+            deduped
+        }
+
+        /// 将 `text` 按 `blocks` 分块翻译的结果按原文的行展开,
+        /// 连续的成功行合并为一块, 失败的行使用其所在块的错误码,
+        /// 结果按位置对应原文的行, 成功的块行数不符时无法对应, 返回 `None`
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::{
+        ///     dedup::Deduped,
+        ///     response::{Session, TransLine, TransResult},
+        /// };
+        /// let deduped = Deduped::new("a\nb\na\nc\nb\n");
+        /// let blocks = ["a\nb\n", "c\n"];
+        /// let session = Session {
+        ///     blocks: vec![
+        ///         Ok(TransResult {
+        ///             from: "en".into(),
+        ///             to: "zh".into(),
+        ///             lines: vec![
+        ///                 TransLine { src: "a".into(), dst: "甲".into() },
+        ///                 TransLine { src: "b".into(), dst: "乙".into() },
+        ///             ],
+        ///         }),
+        ///         Err("54003".into()),
+        ///     ],
+        /// };
+        /// let expanded = deduped.expand(&blocks, &session).unwrap();
+        /// assert_eq!(expanded.blocks.len(), 3);
+        /// let dst: Vec<_> = expanded.blocks[0].as_ref().unwrap().lines.iter()
+        ///     .map(|line| line.dst.as_str())
+        ///     .collect();
+        /// assert_eq!(dst, ["甲", "乙", "甲"]);
+        /// assert_eq!(expanded.blocks[1], Err("54003".into()));
+        /// assert_eq!(expanded.blocks[2].as_ref().unwrap().lines[0].dst, "乙");
+        ///
+        /// // 接口合并了两行
+        /// let mut merged = session.clone();
+        /// merged.blocks[0].as_mut().unwrap().lines.pop();
+        /// assert_eq!(deduped.expand(&blocks, &merged), None);
+        /// ```
+        pub fn expand(&self, blocks: &[&str], session: &Session) -> Option<Session> {
+            let mut lines: Vec<Result<&TransLine, &str>> = vec![];
+            let mut lang = None;
+            for (block, result) in blocks.iter().zip(&session.blocks) {
+                let count = block.lines().filter(|line| !line.trim().is_empty()).count();
+                match result {
+                    Ok(result) if result.lines.len() != count => return None,
+                    Ok(result) => {
+                        lang.get_or_insert((&result.from, &result.to));
+                        lines.extend(result.lines.iter().map(Ok));
+                    },
+                    Err(code) => lines.extend((0..count).map(|_| Err(code.as_str()))),
+                }
+            }
+            let (from, to) = lang.map_or_else(Default::default, |(from, to)| {
+                (from.clone(), to.clone())
+            });
+            let mut expanded = Session::default();
+            for &pos in &self.positions {
+                let line = lines.get(pos).copied().unwrap_or(Err("missing"));
+                match (line, expanded.blocks.last_mut()) {
+                    (Ok(line), Some(Ok(result))) => result.lines.push(line.clone()),
+                    (Ok(line), _) => expanded.blocks.push(Ok(TransResult {
+                        from: from.clone(),
+                        to: to.clone(),
+                        lines: vec![line.clone()],
+                    })),
+                    (Err(code), Some(Err(last))) if last == code => (),
+                    (Err(code), _) => expanded.blocks.push(Err(code.into())),
+                }
+            }
+            Some(expanded)
+        }
+    }
+}
+pub mod diff {
+    use std::fmt::Display;
+
+    use unicode_segmentation::UnicodeSegmentation;
+
+    use crate::response::TransResult;
+
+    /// `--color-diff` 中删除内容的背景色
+    pub const DELETE_COLOR: &str = "\x1b[41m";
+    /// `--color-diff` 中添加内容的背景色
+    pub const INSERT_COLOR: &str = "\x1b[42m";
+    pub const RESET_COLOR: &str = "\x1b[0m";
+
+    /// 单词级对比的一项
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum DiffPair {
+        /// 仅在 dst 中出现
+        Added(String),
+        /// 仅在 src 中出现
+        Removed(String),
+        /// 两边共有, `(src, dst)`
+        Common(String, String),
+    }
+    impl Display for DiffPair {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Added(s) => write!(f, "+ {s}"),
+                Self::Removed(s) => write!(f, "- {s}"),
+                Self::Common(src, dst) if src == dst => write!(f, "  {src}"),
+                Self::Common(src, dst) => write!(f, "  {src} = {dst}"),
+            }
+        }
+    }
+
+    /// src 与 dst 的单词级对比结果
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct TransDiff {
+        pub pairs: Vec<DiffPair>,
+    }
+    impl TransDiff {
+        /// 对比 src 与 dst 的单词
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::diff::{TransDiff, DiffPair::*};
+        /// let diff = TransDiff::between(
+        ///     "Hello Maria, how are you?",
+        ///     "Hola Maria, ¿cómo estás?",
+        /// );
+        /// assert_eq!(diff.pairs, [
+        ///     Removed("Hello".into()),
+        ///     Added("Hola".into()),
+        ///     Common("Maria".into(), "Maria".into()),
+        ///     Removed("how".into()),
+        ///     Removed("are".into()),
+        ///     Removed("you".into()),
+        ///     Added("cómo".into()),
+        ///     Added("estás".into()),
+        /// ]);
+        ///
+        /// let diff = TransDiff::between("用Rust写", "Write in Rust");
+        /// assert_eq!(diff.pairs[0], Removed("用".into()));
+        /// assert!(diff.pairs.contains(&Common("Rust".into(), "Rust".into())));
+        /// ```
+        pub fn between(src: &str, dst: &str) -> Self {
+            let src = tokenize(src);
+            let dst = tokenize(dst);
+            // 不区分大小写, 每个单词只转换一次
+            let lower = |tokens: &[&str]| {
+                tokens.iter().map(|s| s.to_lowercase()).collect::<Vec<_>>()
+            };
+            let (mut i, mut j) = (0, 0);
+            let pairs = edit_script(&lower(&src), &lower(&dst)).into_iter()
+                .map(|op| match op {
+                    DiffOp::Equal(()) => {
+                        (i, j) = (i + 1, j + 1);
+                        DiffPair::Common(src[i - 1].into(), dst[j - 1].into())
+                    },
+                    DiffOp::Delete(()) => {
+                        i += 1;
+                        DiffPair::Removed(src[i - 1].into())
+                    },
+                    DiffOp::Insert(()) => {
+                        j += 1;
+                        DiffPair::Added(dst[j - 1].into())
+                    },
+                })
+                .collect();
+            Self { pairs }
+        }
+
+        /// 对比翻译结果的每一行
+        pub fn from_result(result: &TransResult) -> Self {
+            let pairs = result.lines.iter()
+                .flat_map(|line| Self::between(&line.src, &line.dst).pairs)
+                .collect();
+            Self { pairs }
+        }
+    }
+    impl Display for TransDiff {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            for pair in &self.pairs {
+                writeln!(f, "{pair}")?;
+            }
+            Ok(())
+        }
+    }
+
+    /// 是否为不使用空格分词的 CJK 字符
+    fn is_cjk(c: char) -> bool {
+        matches!(c,
+            '\u{3040}'..='\u{30ff}' // 平假名, 片假名
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{f900}'..='\u{faff}'
+            | '\u{20000}'..='\u{2fa1f}')
+    }
+
+    /// [`myers_diff`] 的一项编辑
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum DiffOp<T> {
+        Equal(T),
+        /// 仅在 `b` 中出现
+        Insert(T),
+        /// 仅在 `a` 中出现
+        Delete(T),
+    }
+
+    /// Myers 差分算法, 返回从 `a` 变为 `b` 的最短编辑序列,
+    /// 使用线性空间的分治版本, 连续的编辑中删除在添加之前
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::diff::{myers_diff, DiffOp::*};
+    /// assert_eq!(
+    ///     myers_diff(&[1, 2, 3], &[1, 4, 3]),
+    ///     [Equal(1), Delete(2), Insert(4), Equal(3)],
+    /// );
+    /// assert_eq!(myers_diff::<u8>(&[], &[]), []);
+    /// assert_eq!(myers_diff(&[], &['a']), [Insert('a')]);
+    ///
+    /// let (a, b) = (b"ABCABBA", b"CBABAC");
+    /// let ops = myers_diff(a, b);
+    /// assert_eq!(ops.iter().filter(|op| !matches!(op, Equal(_))).count(), 5);
+    /// let old = ops.iter().filter_map(|op| match op {
+    ///     Equal(x) | Delete(x) => Some(*x),
+    ///     Insert(_) => None,
+    /// });
+    /// assert!(old.eq(a.iter().copied()));
+    /// ```
+    pub fn myers_diff<T: Eq + Clone>(a: &[T], b: &[T]) -> Vec<DiffOp<T>> {
+        let (mut i, mut j) = (0, 0);
+        edit_script(a, b).into_iter()
+            .map(|op| match op {
+                DiffOp::Equal(()) => {
+                    (i, j) = (i + 1, j + 1);
+                    DiffOp::Equal(a[i - 1].clone())
+                },
+                DiffOp::Delete(()) => {
+                    i += 1;
+                    DiffOp::Delete(a[i - 1].clone())
+                },
+                DiffOp::Insert(()) => {
+                    j += 1;
+                    DiffOp::Insert(b[j - 1].clone())
+                },
+            })
+            .collect()
+    }
+
+    /// 不带元素的最短编辑序列, 见 [`myers_diff`]
+    fn edit_script<T: Eq>(a: &[T], b: &[T]) -> Vec<DiffOp<()>> {
+        let mut ops = Vec::with_capacity(a.len().max(b.len()));
+        diff_range(a, b, &mut ops);
+        // 连续的编辑中删除在前
+        let mut start = 0;
+        while start < ops.len() {
+            let len = ops[start..].iter()
+                .take_while(|op| !matches!(op, DiffOp::Equal(())))
+                .count();
+            ops[start..start + len].sort_by_key(|op| matches!(op, DiffOp::Insert(())));
+            start += len.max(1);
+        }
+        ops
+    }
+
+    fn diff_range<T: Eq>(a: &[T], b: &[T], ops: &mut Vec<DiffOp<()>>) {
+        let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+        let (a, b) = (&a[prefix..], &b[prefix..]);
+        let suffix = a.iter().rev().zip(b.iter().rev()).take_while(|(x, y)| x == y).count();
+        let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+        ops.extend(std::iter::repeat_n(DiffOp::Equal(()), prefix));
+        if a.is_empty() || b.is_empty() {
+            ops.extend(std::iter::repeat_n(DiffOp::Delete(()), a.len()));
+            ops.extend(std::iter::repeat_n(DiffOp::Insert(()), b.len()));
+        } else {
+            // 去掉首尾相同部分后编辑距离至少为 2, 两侧均严格变小
+            let (x, y, u, v) = middle_snake(a, b);
+            diff_range(&a[..x], &b[..y], ops);
+            ops.extend(std::iter::repeat_n(DiffOp::Equal(()), u - x));
+            diff_range(&a[u..], &b[v..], ops);
+        }
+        ops.extend(std::iter::repeat_n(DiffOp::Equal(()), suffix));
+    }
+
+    /// 最短编辑路径中间的一段对角线, 返回其起点 `(x, y)` 与终点 `(u, v)`
+    fn middle_snake<T: Eq>(a: &[T], b: &[T]) -> (usize, usize, usize, usize) {
+        let (n, m) = (a.len() as isize, b.len() as isize);
+        let max = (n + m + 1) / 2;
+        let delta = n - m;
+        let odd = delta % 2 != 0;
+        let idx = |k: isize| (k + max + 1) as usize;
+        // 正向与反向 (从末尾开始计) 在对角线 k 上最远的 x
+        let mut forward = vec![0isize; 2 * max as usize + 3];
+        let mut backward = forward.clone();
+        let step = |v: &[isize], k: isize, d: isize| {
+            if k == -d || (k != d && v[idx(k - 1)] < v[idx(k + 1)]) {
+                v[idx(k + 1)]
+            } else {
+                v[idx(k - 1)] + 1
+            }
+        };
+        for d in 0..=max {
+            for k in (-d..=d).step_by(2) {
+                let x0 = step(&forward, k, d);
+                let y0 = x0 - k;
+                let (mut x, mut y) = (x0, y0);
+                while x < n && y < m && a[x as usize] == b[y as usize] {
+                    (x, y) = (x + 1, y + 1);
+                }
+                forward[idx(k)] = x;
+                let back_k = delta - k;
+                if odd && back_k.abs() < d && x + backward[idx(back_k)] >= n {
+                    return (x0 as usize, y0 as usize, x as usize, y as usize);
+                }
+            }
+            for k in (-d..=d).step_by(2) {
+                let x0 = step(&backward, k, d);
+                let y0 = x0 - k;
+                let (mut x, mut y) = (x0, y0);
+                while x < n && y < m && a[(n - x - 1) as usize] == b[(m - y - 1) as usize] {
+                    (x, y) = (x + 1, y + 1);
+                }
+                backward[idx(k)] = x;
+                let forward_k = delta - k;
+                if !odd && forward_k.abs() <= d && x + forward[idx(forward_k)] >= n {
+                    let (x, y, x0, y0) = (n - x, m - y, n - x0, m - y0);
+                    return (x as usize, y as usize, x0 as usize, y0 as usize);
+                }
+            }
+        }
+        unreachable!("the middle snake is found within (n + m + 1) / 2 steps")
+    }
+
+    /// 按 Unicode 单词边界分割, 保留空白与标点, 拼接后即为原文
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::diff::word_tokens;
+    /// assert_eq!(word_tokens("Hello, world"), ["Hello", ",", " ", "world"]);
+    /// assert_eq!(word_tokens("你好"), ["你", "好"]);
+    /// ```
+    pub fn word_tokens(text: &str) -> Vec<&str> {
+        text.split_word_bounds().collect()
+    }
+
+    /// 以单词对比 src 与 dst 并合并为一行, 删除的内容为红色背景,
+    /// 添加的内容为绿色背景, 不着色时使用 `[-...-]` 与 `{+...+}` 标记
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::diff::render_color_diff;
+    /// assert_eq!(
+    ///     render_color_diff("the quick fox", "the slow fox", true),
+    ///     "the \x1b[41mquick\x1b[0m\x1b[42mslow\x1b[0m fox",
+    /// );
+    /// assert_eq!(
+    ///     render_color_diff("the quick fox", "the slow fox", false),
+    ///     "the [-quick-]{+slow+} fox",
+    /// );
+    /// assert_eq!(render_color_diff("same text", "same text", true), "same text");
+    /// assert_eq!(
+    ///     render_color_diff("a b", "a b c d", true),
+    ///     "a b\x1b[42m c d\x1b[0m",
+    /// );
+    /// ```
+    pub fn render_color_diff(src: &str, dst: &str, color: bool) -> String {
+        let ops = myers_diff(&word_tokens(src), &word_tokens(dst));
+        let mut out = String::new();
+        let mut i = 0;
+        while i < ops.len() {
+            // 合并相邻的同类编辑
+            let same_kind = |op: &DiffOp<&str>| {
+                std::mem::discriminant(op) == std::mem::discriminant(&ops[i])
+            };
+            let len = ops[i..].iter().take_while(|op| same_kind(op)).count();
+            let text = ops[i..i + len].iter()
+                .map(|(DiffOp::Equal(s) | DiffOp::Insert(s) | DiffOp::Delete(s))| *s)
+                .collect::<String>();
+            let (start, end) = match (&ops[i], color) {
+                (DiffOp::Equal(_), _) => ("", ""),
+                (DiffOp::Delete(_), true) => (DELETE_COLOR, RESET_COLOR),
+                (DiffOp::Insert(_), true) => (INSERT_COLOR, RESET_COLOR),
+                (DiffOp::Delete(_), false) => ("[-", "-]"),
+                (DiffOp::Insert(_), false) => ("{+", "+}"),
+            };
+            out.push_str(start);
+            out.push_str(&text);
+            out.push_str(end);
+            i += len;
+        }
+        out
+    }
+
+    /// 对翻译结果的每一行使用 [`render_color_diff`], 每行以换行结束
+    pub fn render_result(result: &TransResult, color: bool) -> String {
+        result.lines.iter()
+            .map(|line| render_color_diff(&line.src, &line.dst, color) + "\n")
+            .collect()
+    }
+
+    /// 以空白符及标点分割单词, CJK 字符每个字符为一个单词
+    pub fn tokenize(text: &str) -> Vec<&str> {
+        let mut tokens = Vec::new();
+        let mut start = None;
+        for (i, c) in text.char_indices() {
+            let cjk = is_cjk(c);
+            if cjk || !c.is_alphanumeric() {
+                if let Some(start) = start.take() {
+                    tokens.push(&text[start..i]);
+                }
+                if cjk {
+                    tokens.push(&text[i..i+c.len_utf8()]);
+                }
+            } else if start.is_none() {
+                start = Some(i);
+            }
+        }
+        if let Some(start) = start {
+            tokens.push(&text[start..]);
+        }
+        tokens
+    }
+}
+pub mod side_by_side {
+    use unicode_width::UnicodeWidthChar;
+
+    use crate::response::TransResult;
+
+    /// 两列之间的分隔
+    pub const COLUMN_SEP: &str = " │ ";
+    /// dst 列的着色
+    pub const DST_COLOR: &str = "\x1b[32m";
+    pub const RESET_COLOR: &str = "\x1b[0m";
+
+    /// 按显示宽度折行, CJK 等宽字符计为两列,
+    /// 每行至少包含一个字符
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::side_by_side::wrap;
+    /// assert_eq!(wrap("abcdef", 4), ["abcd", "ef"]);
+    /// assert_eq!(wrap("你好世界", 5), ["你好", "世界"]);
+    /// assert_eq!(wrap("", 4), [""]);
+    /// ```
+    pub fn wrap(text: &str, width: usize) -> Vec<&str> {
+        let mut lines = Vec::new();
+        let (mut start, mut cur) = (0, 0);
+        for (i, ch) in text.char_indices() {
+            let w = ch.width().unwrap_or(0);
+            if cur + w > width && i != start {
+                lines.push(&text[start..i]);
+                (start, cur) = (i, 0);
+            }
+            cur += w;
+        }
+        lines.push(&text[start..]);
+        lines
+    }
+
+    /// 文本的显示宽度
+    pub fn display_width(text: &str) -> usize {
+        text.chars().map(|ch| ch.width().unwrap_or(0)).sum()
+    }
+
+    /// 将每行的 src 与 dst 渲染为宽度为 `width` 的两列,
+    /// `color` 为真时为 dst 着色
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::{
+    ///     side_by_side::render,
+    ///     response::{TransLine, TransResult},
+    /// };
+    /// let result = TransResult {
+    ///     from: "zh".into(),
+    ///     to: "en".into(),
+    ///     lines: vec![
+    ///         TransLine { src: "你好世界".into(), dst: "Hello world".into() },
+    ///         TransLine { src: "好".into(), dst: "good".into() },
+    ///     ],
+    /// };
+    /// assert_eq!(render(&result, 19, false), concat!(
+    ///     "你好世界 │ Hello wo\n",
+    ///     "         │ rld\n",
+    ///     "好       │ good\n",
+    /// ));
+    /// assert_eq!(render(&result, 19, true), concat!(
+    ///     "你好世界 │ \x1b[32mHello wo\x1b[0m\n",
+    ///     "         │ \x1b[32mrld\x1b[0m\n",
+    ///     "好       │ \x1b[32mgood\x1b[0m\n",
+    /// ));
+    /// ```
+    pub fn render(result: &TransResult, width: usize, color: bool) -> String {
+        let total = width.saturating_sub(display_width(COLUMN_SEP)).max(2);
+        let left_width = total / 2;
+        let right_width = total - left_width;
+        let mut out = String::new();
+        for line in &result.lines {
+            let left = wrap(&line.src, left_width);
+            let right = wrap(&line.dst, right_width);
+            for i in 0..left.len().max(right.len()) {
+                let src = left.get(i).copied().unwrap_or_default();
+                let dst = right.get(i).copied().unwrap_or_default();
+                out.push_str(src);
+                let pad = left_width.saturating_sub(display_width(src));
+                out.extend(std::iter::repeat_n(' ', pad));
+                out.push_str(COLUMN_SEP);
+                if color && !dst.is_empty() {
+                    out.push_str(DST_COLOR);
+                    out.push_str(dst);
+                    out.push_str(RESET_COLOR);
+                } else {
+                    out.push_str(dst);
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+pub mod wrap {
+    use unicode_width::UnicodeWidthChar;
+
+    use crate::{
+        response::TransResult,
+        side_by_side::{display_width, wrap as wrap_chars},
+    };
+
+    /// 按显示宽度折行, 拉丁文字在空白处断开, CJK 等宽字符之间均可断开,
+    /// 宽于 `width` 的单词按字符断开, 断开处的空白被去除
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::wrap::word_wrap;
+    /// assert_eq!(word_wrap("hello world foo", 11), ["hello world", "foo"]);
+    /// assert_eq!(word_wrap("使用Rust编写的翻译工具", 10), ["使用Rust编", "写的翻译工", "具"]);
+    /// assert_eq!(word_wrap("你好 world", 6), ["你好", "world"]);
+    /// // 宽度小于最长的单词
+    /// assert_eq!(word_wrap("a translation tool", 5), ["a", "trans", "latio", "n", "tool"]);
+    /// assert_eq!(word_wrap("", 5), [""]);
+    /// ```
+    pub fn word_wrap(text: &str, width: usize) -> Vec<&str> {
+        let mut lines = Vec::new();
+        let (mut start, mut end, mut cur) = (0, 0, 0);
+        for (tok_start, tok) in tokens(text) {
+            if tok.starts_with(char::is_whitespace) { continue }
+            let tok_end = tok_start + tok.len();
+            let w = display_width(&text[end..tok_end]);
+            if cur + w <= width {
+                (end, cur) = (tok_end, cur + w);
+                continue;
+            }
+            if end != start {
+                lines.push(&text[start..end]);
+            }
+            let pieces = wrap_chars(tok, width);
+            let (last, full) = pieces.split_last().expect("wrap is not empty");
+            lines.extend_from_slice(full);
+            let last_start = tok_end - last.len();
+            (start, end, cur) = (last_start, tok_end, display_width(last));
+        }
+        if end != start || lines.is_empty() {
+            lines.push(&text[start..end]);
+        }
+        lines
+    }
+
+    /// 将文本分为空白, 单词与单个宽字符, 返回每段的起始位置
+    fn tokens(text: &str) -> Vec<(usize, &str)> {
+        #[derive(PartialEq)]
+        enum Kind { Space, Word, Wide }
+        let kind = |ch: char| if ch.is_whitespace() {
+            Kind::Space
+        } else if ch.width() == Some(2) {
+            Kind::Wide
+        } else {
+            Kind::Word
+        };
+        let mut tokens = Vec::new();
+        let mut start = 0;
+        let mut last = None;
+        for (i, ch) in text.char_indices() {
+            let k = kind(ch);
+            if i != start && (k == Kind::Wide || last.as_ref() != Some(&k)) {
+                tokens.push((start, &text[start..i]));
+                start = i;
+            }
+            last = Some(k);
+        }
+        if start != text.len() {
+            tokens.push((start, &text[start..]));
+        }
+        tokens
+    }
+
+    /// 对每个已有的行分别折行
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::wrap::wrap_lines;
+    /// assert_eq!(wrap_lines("aa bb\ncc", 2), "aa\nbb\ncc");
+    /// ```
+    pub fn wrap_lines(text: &str, width: usize) -> String {
+        text.split('\n')
+            .map(|line| word_wrap(line, width).join("\n"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 对结果中每行的 dst 折行, src 保持不变
+    pub fn wrap_result(result: &mut TransResult, width: usize) {
+        for line in &mut result.lines {
+            line.dst = wrap_lines(&line.dst, width);
+        }
+    }
+}
+pub mod markdown {
+    /// 被占位符替换后的文本
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Protected {
+        /// 替换后的文本
+        pub text: String,
+        /// 被替换的原文, 下标即为占位符编号
+        pub spans: Vec<String>,
+        tag: String,
+    }
+
+    /// 以占位符分割的行片段
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Segment<'a> {
+        Text(&'a str),
+        Span(usize),
+    }
+
+    /// 将代码块, 行内代码与 URL 替换为 `⟦BFY0⟧` 形式的占位符
+    ///
+    /// 若原文已含有 `⟦BFY` 则会换用其它标记以避免冲突
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::markdown::protect;
+    /// let readme = concat!(
+    ///     "# 安装\n",
+    ///     "\n",
+    ///     "使用 `cargo install` 安装, 详见 https://example.com/doc.\n",
+    ///     "\n",
+    ///     "```sh\n",
+    ///     "cargo install baidu_fanyi\n",
+    ///     "```\n",
+    ///     "\n",
+    ///     "- 列表:\n",
+    ///     "  ~~~\n",
+    ///     "  let x = 1;\n",
+    ///     "  ~~~\n",
+    ///     "\n",
+    ///     "    indented code\n",
+    ///     "    more code\n",
+    ///     "结束\n",
+    /// );
+    /// let protected = protect(readme);
+    /// assert_eq!(protected.text, concat!(
+    ///     "# 安装\n",
+    ///     "\n",
+    ///     "使用 ⟦BFY0⟧ 安装, 详见 ⟦BFY1⟧.\n",
+    ///     "\n",
+    ///     "⟦BFY2⟧\n",
+    ///     "\n",
+    ///     "- 列表:\n",
+    ///     "⟦BFY3⟧\n",
+    ///     "\n",
+    ///     "⟦BFY4⟧\n",
+    ///     "结束\n",
+    /// ));
+    /// assert_eq!(protected.spans[1], "https://example.com/doc");
+    /// assert_eq!(protected.restore(&protected.text), readme);
+    ///
+    /// // 翻译可能会在占位符中插入空格
+    /// assert_eq!(
+    ///     protected.restore_line("使用 ⟦BFY0⟧ 安装", "Use ⟦ BFY0 ⟧ to install"),
+    ///     Some("Use `cargo install` to install".into()),
+    /// );
+    /// assert_eq!(protected.restore_line("使用 ⟦BFY0⟧ 安装", "Use it"), None);
+    ///
+    /// let protected = protect("⟦BFY0⟧ `x`");
+    /// assert_eq!(protected.text, "⟦BFY0⟧ ⟦BFYX0⟧");
+    /// assert_eq!(protected.restore(&protected.text), "⟦BFY0⟧ `x`");
+    /// ```
+    pub fn protect(text: &str) -> Protected {
+        let mut tag = String::from("BFY");
+        while text.contains(&format!("⟦{tag}")) {
+            tag.push('X');
+        }
+        let mut res = Protected {
+            text: String::with_capacity(text.len()),
+            spans: Vec::new(),
+            tag,
+        };
+        let mut lines = text.split_inclusive('\n').peekable();
+        let mut prev_blank = true;
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim_start();
+            if let Some(fence) = fence_of(trimmed) {
+                let mut block = String::from(line);
+                for next in lines.by_ref() {
+                    block.push_str(next);
+                    if is_fence_close(next.trim(), fence) { break }
+                }
+                res.push_block(&block);
+                prev_blank = false;
+            } else if prev_blank && is_indented_code(line) {
+                let mut block = String::from(line);
+                while let Some(next) = lines.next_if(|x| is_indented_code(x)) {
+                    block.push_str(next);
+                }
+                res.push_block(&block);
+                prev_blank = false;
+            } else {
+                res.push_inline(line);
+                prev_blank = line.trim().is_empty();
+            }
+        }
+        res
+    }
+
+    /// 开启围栏代码块的标记, 如 ```` ``` ```` 或 `~~~~`
+    fn fence_of(line: &str) -> Option<&str> {
+        let c = line.chars().next().filter(|&c| c == '`' || c == '~')?;
+        let len = line.len() - line.trim_start_matches(c).len();
+        (len >= 3).then(|| &line[..len])
+    }
+
+    fn is_fence_close(line: &str, fence: &str) -> bool {
+        fence_of(line).is_some_and(|close| {
+            close.starts_with(fence) && close.len() == line.len()
+        })
+    }
+
+    fn is_indented_code(line: &str) -> bool {
+        (line.starts_with("    ") || line.starts_with('\t'))
+            && !line.trim().is_empty()
+    }
+
+    /// URL 的结束位置
+    fn url_len(s: &str) -> usize {
+        let end = s.find(|c: char| {
+            c.is_whitespace() || matches!(c, ')' | '>' | ']' | '"' | '\'')
+        }).unwrap_or(s.len());
+        s[..end].trim_end_matches(['.', ',', ';', ':', '!', '?']).len()
+    }
+
+    impl Protected {
+        fn placeholder(&self, id: usize) -> String {
+            format!("⟦{}{id}⟧", self.tag)
+        }
+
+        fn push_span(&mut self, span: &str) {
+            self.text.push_str(&self.placeholder(self.spans.len()));
+            self.spans.push(span.into());
+        }
+
+        /// 整块替换, 末尾换行保留在外部
+        fn push_block(&mut self, block: &str) {
+            let body = block.strip_suffix('\n').unwrap_or(block);
+            self.push_span(body);
+            if body.len() != block.len() {
+                self.text.push('\n');
+            }
+        }
+
+        fn push_inline(&mut self, mut line: &str) {
+            while !line.is_empty() {
+                let next = line.find(['`', 'h']).unwrap_or(line.len());
+                self.text.push_str(&line[..next]);
+                line = &line[next..];
+                if line.starts_with('`') {
+                    let ticks = line.len() - line.trim_start_matches('`').len();
+                    let rest = &line[ticks..];
+                    let close = rest.match_indices(&line[..ticks])
+                        .map(|(i, _)| i)
+                        .find(|&i| !rest[i+ticks..].starts_with('`')
+                            && !rest[..i].ends_with('`'));
+                    if let Some(close) = close {
+                        let len = ticks*2 + close;
+                        self.push_span(&line[..len]);
+                        line = &line[len..];
+                    } else {
+                        self.text.push_str(&line[..ticks]);
+                        line = rest;
+                    }
+                } else if line.starts_with("http://")
+                    || line.starts_with("https://") {
+                    let len = url_len(line);
+                    self.push_span(&line[..len]);
+                    line = &line[len..];
+                } else if !line.is_empty() {
+                    self.text.push('h');
+                    line = &line[1..];
+                }
+            }
+        }
+
+        /// 分割出文本与占位符, 允许占位符内部出现空白
+        pub fn segments<'a>(&self, s: &'a str) -> Vec<Segment<'a>> {
+            let mut res = Vec::new();
+            let mut rest = s;
+            let mut text_start = 0;
+            while let Some(i) = rest.find('⟦') {
+                let offset = s.len() - rest.len();
+                let after = &rest[i+'⟦'.len_utf8()..];
+                let parsed = after.trim_start()
+                    .strip_prefix(&*self.tag)
+                    .and_then(|x| {
+                        let digits = x.len()
+                            - x.trim_start_matches(|c: char| c.is_ascii_digit())
+                                .len();
+                        let id = x[..digits].parse::<usize>().ok()?;
+                        let tail = x[digits..].trim_start().strip_prefix('⟧')?;
+                        (id < self.spans.len()).then_some((id, tail))
+                    });
+                match parsed {
+                    Some((id, tail)) => {
+                        if text_start < offset + i {
+                            res.push(Segment::Text(&s[text_start..offset+i]));
+                        }
+                        res.push(Segment::Span(id));
+                        rest = tail;
+                        text_start = s.len() - rest.len();
+                    },
+                    None => rest = after,
+                }
+            }
+            if text_start < s.len() {
+                res.push(Segment::Text(&s[text_start..]));
+            }
+            res
+        }
+
+        /// 将占位符还原为原文
+        pub fn restore(&self, s: &str) -> String {
+            self.segments(s).into_iter()
+                .map(|seg| match seg {
+                    Segment::Text(text) => text,
+                    Segment::Span(id) => &self.spans[id],
+                })
+                .collect()
+        }
+
+        fn span_ids(&self, s: &str) -> Vec<usize> {
+            let mut ids: Vec<usize> = self.segments(s).into_iter()
+                .filter_map(|seg| match seg {
+                    Segment::Span(id) => Some(id),
+                    Segment::Text(_) => None,
+                })
+                .collect();
+            ids.sort_unstable();
+            ids
+        }
+
+        /// 还原翻译后的一行, 若 src 中的占位符在 dst 中有缺失则返回 `None`
+        pub fn restore_line(&self, src: &str, dst: &str) -> Option<String> {
+            (self.span_ids(src) == self.span_ids(dst))
+                .then(|| self.restore(dst))
+        }
+    }
+}
+pub mod html {
+    use std::collections::VecDeque;
+
+    /// 内容不解析标签的元素, 其中 `title` 的内容会被翻译
+    const RAW_TEXT_TAGS: &[&str] = &["script", "style", "title"];
+    /// 没有结束标签的元素
+    const VOID_TAGS: &[&str] = &[
+        "area", "base", "br", "col", "embed", "hr", "img", "input",
+        "link", "meta", "param", "source", "track", "wbr",
+    ];
+
+    /// 文档片段
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Segment {
+        /// 原样保留的标签, 注释, 脚本与空白
+        Markup(String),
+        /// 文本节点, 实体已解码, 连续空白已合并为一个空格
+        Text(String),
+        /// 需要翻译的属性值, 即 `alt` 与 `<meta name="description">` 的 `content`
+        Attr(String),
+    }
+
+    /// 被分割为片段的 HTML 文档
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::html::Document;
+    /// let html = concat!(
+    ///     "<!DOCTYPE html>\n",
+    ///     "<html>\n",
+    ///     "<head>\n",
+    ///     "  <title>My &amp; Page</title>\n",
+    ///     "  <meta name=\"description\" content=\"A test page\">\n",
+    ///     "  <meta name=\"viewport\" content=\"width=device-width\">\n",
+    ///     "  <style>p { color: red; }</style>\n",
+    ///     "</head>\n",
+    ///     "<body>\n",
+    ///     "  <!-- comment -->\n",
+    ///     "  <h1>Hello,\n    <em>world</em>!</h1>\n",
+    ///     "  <p>1 &lt; 2 &copy; <img src=\"cat.png\" alt=cat></p>\n",
+    ///     "  <script>if (a < b) { x = \"<p>\"; }</script>\n",
+    ///     "</body>\n",
+    ///     "</html>\n",
+    /// );
+    /// let doc = Document::parse(html);
+    /// assert_eq!(doc.texts(), [
+    ///     "My & Page", "A test page", "Hello,", "world", "!", "1 < 2 &copy;", "cat",
+    /// ]);
+    /// // 未翻译时还原为原文
+    /// assert_eq!(doc.render(&[]), html.replace("alt=cat", "alt=\"cat\""));
+    ///
+    /// let dsts = doc.texts().iter().map(|s| s.to_uppercase()).collect::<Vec<_>>();
+    /// let out = doc.render(&dsts);
+    /// assert!(out.contains("<title>MY &amp; PAGE</title>"));
+    /// assert!(out.contains("content=\"A TEST PAGE\""));
+    /// assert!(out.contains("content=\"width=device-width\""));
+    /// assert!(out.contains("<h1>HELLO,\n    <em>WORLD</em>!</h1>"));
+    /// assert!(out.contains("<p>1 &lt; 2 &COPY; <img src=\"cat.png\" alt=\"CAT\"></p>"));
+    /// assert!(out.contains("<style>p { color: red; }</style>"));
+    /// assert!(out.contains("<script>if (a < b) { x = \"<p>\"; }</script>"));
+    /// ```
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct Document {
+        pub segments: Vec<Segment>,
+    }
+    impl Document {
+        /// 使用状态机分割文档, 不校验文档是否合法
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::html::Document;
+        /// let doc = Document::parse("<TITLE>a</titles>b</Title >c");
+        /// assert_eq!(doc.texts(), ["a</titles>b", "c"]);
+        /// ```
+        pub fn parse(html: &str) -> Self {
+            let mut doc = Self::default();
+            // 打开的元素, 用于识别 `script` 等原始文本元素的结束
+            let mut open: VecDeque<String> = VecDeque::new();
+            let mut text = String::new();
+            let mut rest = html;
+            while !rest.is_empty() {
+                if let Some(tag) = open.back().filter(|tag| {
+                    RAW_TEXT_TAGS.contains(&tag.as_str())
+                }) {
+                    let end = find_close_tag(rest, tag).unwrap_or(rest.len());
+                    if tag == "title" {
+                        doc.push_text(&rest[..end]);
+                    } else {
+                        doc.push_markup(&rest[..end]);
+                    }
+                    rest = &rest[end..];
+                    open.pop_back();
+                    continue;
+                }
+                let Some(start) = rest.find('<') else {
+                    text.push_str(rest);
+                    break;
+                };
+                text.push_str(&rest[..start]);
+                rest = &rest[start..];
+                let end = match tag_end(rest) {
+                    Some(end) => end,
+                    None => {
+                        // 不是标签的 `<`
+                        text.push('<');
+                        rest = &rest[1..];
+                        continue;
+                    },
+                };
+                doc.push_text(&std::mem::take(&mut text));
+                let (tag, after) = rest.split_at(end);
+                rest = after;
+                match tag_name(tag) {
+                    Some((name, true)) => {
+                        if let Some(pos) = open.iter().rposition(|x| *x == name) {
+                            open.truncate(pos);
+                        }
+                        doc.push_markup(tag);
+                    },
+                    Some((name, false)) => {
+                        doc.push_start_tag(tag, &name);
+                        if !VOID_TAGS.contains(&name.as_str()) && !tag.ends_with("/>") {
+                            open.push_back(name);
+                        }
+                    },
+                    // 注释, 文档类型声明等
+                    None => doc.push_markup(tag),
+                }
+            }
+            doc.push_text(&text);
+            doc
+        }
+
+        fn push_markup(&mut self, markup: &str) {
+            if markup.is_empty() { return }
+            if let Some(Segment::Markup(last)) = self.segments.last_mut() {
+                last.push_str(markup);
+            } else {
+                self.segments.push(Segment::Markup(markup.into()));
+            }
+        }
+
+        /// 文本首尾的空白原样保留, 只含空白的文本不翻译
+        fn push_text(&mut self, raw: &str) {
+            let is_space = |c: char| c.is_ascii_whitespace();
+            let core = raw.trim_matches(is_space);
+            if core.is_empty() {
+                self.push_markup(raw);
+                return;
+            }
+            let lead = raw.len() - raw.trim_start_matches(is_space).len();
+            self.push_markup(&raw[..lead]);
+            self.segments.push(Segment::Text(collapse_whitespace(&decode_entities(core))));
+            self.push_markup(&raw[lead + core.len()..]);
+        }
+
+        /// 将可翻译的属性值单独分出, 未加引号的值会在输出时加上引号
+        fn push_start_tag(&mut self, tag: &str, name: &str) {
+            let attrs = attributes(tag);
+            let is_description = name == "meta" && attrs.iter().any(|attr| {
+                attr.name == "name"
+                    && attr.value.is_some_and(|(start, end)| {
+                        tag[start..end].eq_ignore_ascii_case("description")
+                    })
+            });
+            let mut pos = 0;
+            for attr in attrs {
+                let translate = attr.name == "alt"
+                    || is_description && attr.name == "content";
+                let Some((start, end)) = attr.value.filter(|_| translate) else {
+                    continue;
+                };
+                let value = decode_entities(&tag[start..end]);
+                if value.trim().is_empty() { continue }
+                let quoted = matches!(tag.as_bytes()[start - 1], b'"' | b'\'');
+                let quote = if quoted { "" } else { "\"" };
+                self.push_markup(&format!("{}{quote}", &tag[pos..start]));
+                self.segments.push(Segment::Attr(collapse_whitespace(value.trim())));
+                self.push_markup(quote);
+                pos = end;
+            }
+            self.push_markup(&tag[pos..]);
+        }
+
+        /// 需要翻译的文本, 依次为各个文本节点与属性值
+        pub fn texts(&self) -> Vec<&str> {
+            self.segments.iter()
+                .filter_map(|seg| match seg {
+                    Segment::Text(text) | Segment::Attr(text) => Some(&**text),
+                    Segment::Markup(_) => None,
+                })
+                .collect()
+        }
+
+        /// 按 [`Document::texts`] 的顺序填入译文并转义,
+        /// 缺少的译文使用原文
+        pub fn render(&self, dsts: &[String]) -> String {
+            let mut out = String::new();
+            let mut dsts = dsts.iter();
+            for seg in &self.segments {
+                match seg {
+                    Segment::Markup(markup) => out.push_str(markup),
+                    Segment::Text(text) => {
+                        let text = dsts.next().unwrap_or(text);
+                        out.push_str(&escape(text, false));
+                    },
+                    Segment::Attr(text) => {
+                        let text = dsts.next().unwrap_or(text);
+                        out.push_str(&escape(text, true));
+                    },
+                }
+            }
+            out
+        }
+    }
+
+    /// 标签的结束位置 (`>` 之后), 不是标签时返回 `None`
+    fn tag_end(rest: &str) -> Option<usize> {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            return Some(comment.find("-->").map_or(rest.len(), |i| i + 7));
+        }
+        let second = rest[1..].chars().next()?;
+        if !(second.is_ascii_alphabetic() || matches!(second, '/' | '!' | '?')) {
+            return None;
+        }
+        let mut quote = None;
+        for (i, c) in rest.char_indices() {
+            match (quote, c) {
+                (None, '"' | '\'') => quote = Some(c),
+                (Some(q), c) if q == c => quote = None,
+                (None, '>') => return Some(i + 1),
+                _ => (),
+            }
+        }
+        Some(rest.len())
+    }
+
+    /// 小写的标签名及其是否为结束标签, 注释等返回 `None`
+    fn tag_name(tag: &str) -> Option<(String, bool)> {
+        let (body, is_end) = match tag[1..].strip_prefix('/') {
+            Some(body) => (body, true),
+            None => (&tag[1..], false),
+        };
+        let name = body
+            .split(|c: char| c.is_ascii_whitespace() || matches!(c, '/' | '>'))
+            .next()?;
+        name.starts_with(|c: char| c.is_ascii_alphabetic())
+            .then(|| (name.to_ascii_lowercase(), is_end))
+    }
+
+    /// 结束标签 `</tag` 的位置, 不区分大小写, 标签名后需为 `>` 或空白
+    fn find_close_tag(rest: &str, tag: &str) -> Option<usize> {
+        let bytes = rest.as_bytes();
+        rest.match_indices("</")
+            .map(|(i, _)| i)
+            .find(|&i| {
+                let end = i + 2 + tag.len();
+                bytes.get(i + 2..end)
+                    .is_some_and(|name| name.eq_ignore_ascii_case(tag.as_bytes()))
+                    && bytes.get(end)
+                        .is_none_or(|&b| b == b'>' || b == b'/' || b.is_ascii_whitespace())
+            })
+    }
+
+    struct Attribute {
+        /// 小写的属性名
+        name: String,
+        /// 值在标签中的范围, 不含引号
+        value: Option<(usize, usize)>,
+    }
+
+    /// 解析开始标签中的属性
+    fn attributes(tag: &str) -> Vec<Attribute> {
+        let bytes = tag.as_bytes();
+        let mut attrs = vec![];
+        // 跳过 `<` 与标签名
+        let mut i = 1;
+        while i < bytes.len() && !is_attr_sep(bytes[i]) { i += 1 }
+        loop {
+            while i < bytes.len() && is_attr_sep(bytes[i]) { i += 1 }
+            if i >= bytes.len() { break }
+            let start = i;
+            while i < bytes.len() && !is_attr_sep(bytes[i]) && bytes[i] != b'=' { i += 1 }
+            let name = tag[start..i].to_ascii_lowercase();
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() { i += 1 }
+            let mut value = None;
+            if bytes.get(i) == Some(&b'=') {
+                i += 1;
+                while i < bytes.len() && bytes[i].is_ascii_whitespace() { i += 1 }
+                match bytes.get(i) {
+                    Some(&q @ (b'"' | b'\'')) => {
+                        let end = tag[i + 1..].find(q as char)
+                            .map_or(tag.len(), |n| i + 1 + n);
+                        value = Some((i + 1, end));
+                        i = end + 1;
+                    },
+                    Some(_) => {
+                        let start = i;
+                        while i < bytes.len()
+                            && !bytes[i].is_ascii_whitespace()
+                            && bytes[i] != b'>'
+                        {
+                            i += 1
+                        }
+                        value = Some((start, i));
+                    },
+                    None => (),
+                }
+            }
+            attrs.push(Attribute { name, value });
+        }
+        attrs
+    }
+
+    fn is_attr_sep(b: u8) -> bool {
+        b.is_ascii_whitespace() || matches!(b, b'/' | b'>')
+    }
+
+    fn collapse_whitespace(text: &str) -> String {
+        text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// 解码常见的字符引用, 未知的实体原样保留
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::html::decode_entities;
+    /// assert_eq!(decode_entities("a &amp; b &lt;&gt; &#20320;&#x597D; &copy; & c"),
+    ///            "a & b <> 你好 &copy; & c");
+    /// ```
+    pub fn decode_entities(text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('&') {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let decoded = rest[1..].find(';').and_then(|end| {
+                let name = &rest[1..end + 1];
+                let c = match name {
+                    "amp" => '&',
+                    "lt" => '<',
+                    "gt" => '>',
+                    "quot" => '"',
+                    "apos" => '\'',
+                    "nbsp" => '\u{a0}',
+                    _ => {
+                        let num = name.strip_prefix('#')?;
+                        let code = match num.strip_prefix(['x', 'X']) {
+                            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                            None => num.parse().ok()?,
+                        };
+                        char::from_u32(code)?
+                    },
+                };
+                Some((c, end + 2))
+            });
+            match decoded {
+                Some((c, len)) => {
+                    out.push(c);
+                    rest = &rest[len..];
+                },
+                None => {
+                    out.push('&');
+                    rest = &rest[1..];
+                },
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// 转义 `<`, `>` 与不构成实体的 `&`, 属性值还会转义引号
+    fn escape(text: &str, attr: bool) -> String {
+        let mut out = String::with_capacity(text.len());
+        for (i, c) in text.char_indices() {
+            match c {
+                '&' if !is_entity(&text[i..]) => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '"' if attr => out.push_str("&quot;"),
+                '\'' if attr => out.push_str("&#39;"),
+                _ => out.push(c),
+            }
+        }
+        out
+    }
+
+    /// 是否以 `&name;` 形式的实体开头
+    fn is_entity(text: &str) -> bool {
+        text[1..].find(';').is_some_and(|end| {
+            let name = &text[1..end + 1];
+            !name.is_empty()
+                && name.len() <= 32
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '#')
+        })
+    }
+}
+pub mod ndjson {
+    use serde_json::{Map, Value};
+
+    use crate::translater::LimitUnit;
+
+    /// `--ndjson` 中的一条记录
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Record {
+        /// 所在行号, 从 1 开始
+        pub line: usize,
+        pub object: Map<String, Value>,
+        pub text: String,
+        pub from: Option<String>,
+        pub to: Option<String>,
+    }
+    impl Record {
+        pub fn id(&self) -> Option<&Value> {
+            self.object.get("id")
+        }
+
+        /// 加入翻译结果
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::ndjson::parse_record;
+        /// let record = parse_record(1, r#"{"id":"k1","text":"你好"}"#, "text")
+        ///     .unwrap();
+        /// assert_eq!(
+        ///     record.finish("Hello", "zh").to_string(),
+        ///     r#"{"id":"k1","text":"你好","dst":"Hello","detected_from":"zh"}"#,
+        /// );
+        /// ```
+        pub fn finish(self, dst: &str, detected_from: &str) -> Value {
+            let mut object = self.object;
+            object.insert("dst".into(), dst.into());
+            object.insert("detected_from".into(), detected_from.into());
+            object.into()
+        }
+    }
+
+    /// 代替失败记录输出的错误对象
+    pub fn error_object(id: Option<&Value>, line: usize, msg: &str) -> Value {
+        let mut object = Map::new();
+        object.insert("id".into(), id.cloned().unwrap_or(Value::Null));
+        object.insert("line".into(), line.into());
+        object.insert("error".into(), msg.into());
+        object.into()
+    }
+
+    /// 解析一行记录, 失败时返回错误对象
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::ndjson::parse_record;
+    /// let record = parse_record(
+    ///     1,
+    ///     r#"{"id": "k1", "text": "你好", "to": "en"}"#,
+    ///     "text",
+    /// ).unwrap();
+    /// assert_eq!(record.text, "你好");
+    /// assert_eq!(record.from, None);
+    /// assert_eq!(record.to.as_deref(), Some("en"));
+    ///
+    /// let err = parse_record(2, r#"{"id": "k2", "text": 3}"#, "text")
+    ///     .unwrap_err();
+    /// assert_eq!(err["id"], "k2");
+    /// assert_eq!(err["line"], 2);
+    ///
+    /// let err = parse_record(3, r#"{"id": "k3", "#, "text").unwrap_err();
+    /// assert!(err["id"].is_null());
+    /// assert!(parse_record(4, "[1]", "text").is_err());
+    /// assert!(parse_record(5, r#"{"text": " "}"#, "text").is_err());
+    /// ```
+    pub fn parse_record(
+        line: usize,
+        src: &str,
+        text_field: &str,
+    ) -> Result<Record, Value> {
+        let object = match serde_json::from_str(src) {
+            Ok(Value::Object(object)) => object,
+            Ok(_) => return Err(error_object(None, line, "not a json object")),
+            Err(e) => {
+                let msg = format!("invalid json: {e}");
+                return Err(error_object(None, line, &msg))
+            },
+        };
+        let err = |msg: &str| Err(error_object(object.get("id"), line, msg));
+        let text = match object.get(text_field) {
+            Some(Value::String(text)) if text.trim().is_empty() => {
+                return err(&format!("field `{text_field}` is empty"))
+            },
+            Some(Value::String(text)) => text.clone(),
+            Some(_) => return err(&format!("field `{text_field}` is not a string")),
+            None => return err(&format!("missing field `{text_field}`")),
+        };
+        let lang = |key| match object.get(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(lang)) => Ok(Some(lang.clone())),
+            Some(_) => Err(format!("field `{key}` is not a string")),
+        };
+        let (from, to) = match (lang("from"), lang("to")) {
+            (Ok(from), Ok(to)) => (from, to),
+            (Err(e), _) | (_, Err(e)) => return err(&e),
+        };
+        Ok(Record { line, object, text, from, to })
+    }
+
+    /// 一次请求翻译的多条记录
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Batch {
+        pub from: String,
+        pub to: String,
+        /// 在记录列表中的下标
+        pub indexes: Vec<usize>,
+    }
+
+    /// 将语言相同的记录合并到同一请求中, 结果不保证记录的顺序
+    ///
+    /// 包含换行的记录单独请求; 源语言为 `auto` 的记录也单独请求,
+    /// 因为接口对整个请求只检测一种语言, 合并后混合语言的记录会被误判
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::{ndjson::{batches, parse_record}, translater::LimitUnit};
+    /// let records = [
+    ///     r#"{"text": "a", "to": "en"}"#,
+    ///     r#"{"text": "b", "to": "jp"}"#,
+    ///     r#"{"text": "c\nd", "to": "en"}"#,
+    ///     r#"{"text": "e", "to": "en"}"#,
+    ///     r#"{"text": "f"}"#,
+    ///     r#"{"text": "g", "from": "auto", "to": "en"}"#,
+    ///     r#"{"text": "h", "from": "auto", "to": "en"}"#,
+    /// ].iter()
+    ///     .enumerate()
+    ///     .map(|(i, line)| parse_record(i + 1, line, "text").unwrap())
+    ///     .collect::<Vec<_>>();
+    /// let groups = batches(&records, "zh", "zh", LimitUnit::Chars)
+    ///     .into_iter()
+    ///     .map(|batch| (batch.to, batch.indexes))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(groups, [
+    ///     ("en".into(), vec![2]),
+    ///     ("en".into(), vec![5]),
+    ///     ("en".into(), vec![6]),
+    ///     ("en".into(), vec![0, 3]),
+    ///     ("jp".into(), vec![1]),
+    ///     ("zh".into(), vec![4]),
+    /// ]);
+    ///
+    /// // 3000 字节, 1000 字符
+    /// let long = format!(r#"{{"text": "{}"}}"#, "中".repeat(999));
+    /// let records = vec![parse_record(1, &long, "text").unwrap(); 2];
+    /// assert_eq!(batches(&records, "zh", "en", LimitUnit::Bytes).len(), 2);
+    /// assert_eq!(batches(&records, "zh", "en", LimitUnit::Chars).len(), 1);
+    /// assert_eq!(batches(&records, "auto", "en", LimitUnit::Chars).len(), 2);
+    /// ```
+    pub fn batches(
+        records: &[Record],
+        default_from: &str,
+        default_to: &str,
+        unit: LimitUnit,
+    ) -> Vec<Batch> {
+        let mut res = Vec::new();
+        // 未满的合并请求及其大小
+        let mut open: Vec<(Batch, usize)> = Vec::new();
+        for (i, record) in records.iter().enumerate() {
+            let batch = Batch {
+                from: record.from.as_deref().unwrap_or(default_from).into(),
+                to: record.to.as_deref().unwrap_or(default_to).into(),
+                indexes: vec![i],
+            };
+            if record.text.contains('\n') || batch.from == "auto" {
+                res.push(batch);
+                continue;
+            }
+            let size = unit.measure(&record.text) + 1;
+            let pos = open.iter().position(|(other, _)| {
+                other.from == batch.from && other.to == batch.to
+            });
+            match pos {
+                Some(pos) if open[pos].1 + size <= unit.max() => {
+                    open[pos].0.indexes.push(i);
+                    open[pos].1 += size;
+                },
+                Some(pos) => {
+                    let (full, _) = std::mem::replace(&mut open[pos], (batch, size));
+                    res.push(full);
+                },
+                None => open.push((batch, size)),
+            }
+        }
+        res.extend(open.into_iter().map(|(batch, _)| batch));
+        res
+    }
+}
+pub mod config {
+    use std::{
+        env,
+        fmt::Display,
+        fs,
+        io,
+        path::PathBuf,
+    };
+
+    /// 读取 appid 与 appkey 的错误
+    #[derive(Debug)]
+    pub enum ConfigError {
+        /// 无法获取 `HOME` 环境变量
+        Home(env::VarError),
+        Read { path: PathBuf, err: io::Error },
+        /// 配置文件不足两行
+        MissingLines,
+    }
+    impl Display for ConfigError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Home(e) => write!(f, "get home error: {e}"),
+                Self::Read { path, err } => {
+                    write!(f, "read config file error. path: {path:?}, msg: {err}")
+                },
+                Self::MissingLines => write!(f, "config lines < 2"),
+            }
+        }
+    }
+    impl std::error::Error for ConfigError {}
+
+    /// 配置文件路径, `$HOME/.baidufanyi_key`
+    pub fn config_path() -> Result<PathBuf, ConfigError> {
+        let mut path = PathBuf::new();
+        path.push(env::var("HOME").map_err(ConfigError::Home)?);
+        path.push(".baidufanyi_key");
+        Ok(path)
+    }
+
+    /// 解析配置文件内容, 第一行为 appid, 第二行为 appkey
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::config::parse_id_and_key;
+    /// let [id, key] = parse_id_and_key("2015063000000001\nkey\n").unwrap();
+    /// assert_eq!(id, "2015063000000001");
+    /// assert_eq!(key, "key");
+    /// assert!(parse_id_and_key("only id").is_err());
+    /// ```
+    pub fn parse_id_and_key(content: &str) -> Result<[String; 2], ConfigError> {
+        let mut lines = content.lines();
+        match (lines.next(), lines.next()) {
+            (Some(id), Some(key)) => Ok([id.into(), key.into()]),
+            _ => Err(ConfigError::MissingLines),
+        }
+    }
+
+    /// 读取配置文件中的 appid 与 appkey
+    pub async fn get_id_and_key() -> Result<[String; 2], ConfigError> {
+        let path = config_path()?;
+        match tokio::fs::read_to_string(&path).await {
+            Ok(content) => parse_id_and_key(&content),
+            Err(err) => Err(ConfigError::Read { path, err }),
+        }
+    }
+
+    /// 在阻塞线程池中调用 [`get_id_and_key_sync`], 不阻塞异步运行时
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use baidu_fanyi::config::get_id_and_key_async;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let home = std::env::temp_dir()
+    ///     .join(format!("baidu_fanyi_home_{}", std::process::id()));
+    /// std::fs::create_dir_all(&home).unwrap();
+    /// std::fs::write(home.join(".baidufanyi_key"), "id\nkey\n").unwrap();
+    /// std::env::set_var("HOME", &home);
+    ///
+    /// let res = tokio::time::timeout(Duration::from_secs(5), get_id_and_key_async())
+    ///     .await
+    ///     .expect("get_id_and_key_async blocked the runtime");
+    /// assert_eq!(res.unwrap(), ["id", "key"]);
+    /// std::fs::remove_dir_all(&home).unwrap();
+    /// # }
+    /// ```
+    pub async fn get_id_and_key_async() -> Result<[String; 2], ConfigError> {
+        tokio::task::spawn_blocking(get_id_and_key_sync)
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+    }
+
+    /// [`get_id_and_key`] 的同步版本, 用于异步上下文之外,
+    /// 会阻塞当前线程, 异步代码中应使用 [`get_id_and_key_async`]
+    pub fn get_id_and_key_sync() -> Result<[String; 2], ConfigError> {
+        let path = config_path()?;
+        match fs::read_to_string(&path) {
+            Ok(content) => parse_id_and_key(&content),
+            Err(err) => Err(ConfigError::Read { path, err }),
+        }
+    }
+}
+pub mod log {
+    use std::{
+        fs::{File, OpenOptions},
+        io::{self, Write},
+        path::Path,
+        str::FromStr,
+        sync::Mutex,
+        time::{Duration, SystemTime},
+    };
+
+    use serde::Serialize;
+
+    use crate::mini_fmt::format_rfc3339;
+
+    /// 一次接口调用的日志
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+    pub struct LogEntry {
+        /// RFC 3339 格式的调用时间
+        pub timestamp: String,
+        pub from_lang: String,
+        pub to_lang: String,
+        pub src_chars: usize,
+        pub dst_chars: usize,
+        pub latency_ms: u64,
+        /// 成功时为 `None`
+        pub api_error_code: Option<i64>,
+    }
+    impl LogEntry {
+        /// 以当前时间构建
+        pub fn now(
+            from_lang: &str,
+            to_lang: &str,
+            src_chars: usize,
+            dst_chars: usize,
+            latency: Duration,
+            api_error_code: Option<i64>,
+        ) -> Self {
+            Self {
+                timestamp: format_rfc3339(SystemTime::now(), true),
+                from_lang: from_lang.into(),
+                to_lang: to_lang.into(),
+                src_chars,
+                dst_chars,
+                latency_ms: latency.as_millis().try_into().unwrap_or(u64::MAX),
+                api_error_code,
+            }
+        }
+    }
+
+    /// 记录哪些调用
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum LogLevel {
+        All,
+        /// 仅记录接口返回错误码的调用
+        ErrorsOnly,
+    }
+    impl FromStr for LogLevel {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "all" => Ok(Self::All),
+                "errors-only" => Ok(Self::ErrorsOnly),
+                _ => Err(format!("invalid log level `{s}`, expected all|errors-only")),
+            }
+        }
+    }
+
+    /// 以 JSON Lines 追加写入的调用日志, 只记录收到了响应的调用
+    ///
+    /// 同一进程内每条记录以一次写入追加, 不会交错,
+    /// 多个进程写入同一文件时不加锁, 过长的记录可能交错
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use baidu_fanyi::log::{CallLog, LogEntry, LogLevel};
+    /// let path = std::env::temp_dir()
+    ///     .join(format!("baidu_fanyi-log-{}.jsonl", std::process::id()));
+    /// let _ = std::fs::remove_file(&path);
+    /// let log = CallLog::open(&path, LogLevel::ErrorsOnly).unwrap();
+    /// let ok = LogEntry::now("en", "zh", 5, 2, Duration::from_millis(30), None);
+    /// let err = LogEntry::now("en", "zh", 5, 0, Duration::from_millis(9), Some(54003));
+    /// log.record(&ok).unwrap();
+    /// log.record(&err).unwrap();
+    ///
+    /// let content = std::fs::read_to_string(&path).unwrap();
+    /// std::fs::remove_file(&path).unwrap();
+    /// let lines: Vec<serde_json::Value> = content.lines()
+    ///     .map(|line| serde_json::from_str(line).unwrap())
+    ///     .collect();
+    /// assert_eq!(lines.len(), 1);
+    /// assert_eq!(lines[0]["api_error_code"], 54003);
+    /// assert_eq!(lines[0]["latency_ms"], 9);
+    /// assert!(lines[0]["timestamp"].as_str().unwrap().ends_with('Z'));
+    /// ```
+    #[derive(Debug)]
+    pub struct CallLog {
+        file: Mutex<File>,
+        level: LogLevel,
+    }
+    impl CallLog {
+        /// 以追加模式打开, 不存在时创建
+        pub fn open(path: impl AsRef<Path>, level: LogLevel) -> io::Result<Self> {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            Ok(Self { file: Mutex::new(file), level })
+        }
+
+        /// 按日志级别写入一条记录
+        pub fn record(&self, entry: &LogEntry) -> io::Result<()> {
+            if self.level == LogLevel::ErrorsOnly && entry.api_error_code.is_none() {
+                return Ok(());
+            }
+            let mut line = serde_json::to_string(entry)?;
+            line.push('\n');
+            self.file.lock().unwrap().write_all(line.as_bytes())
+        }
+    }
+}
+pub mod stats {
+    use std::{fmt::Display, time::Duration};
+
+    use serde_json::{json, Value};
+
+    /// 百度翻译高级版每百万字符的价格 (元)
+    pub const DEFAULT_PRICE_PER_MILLION: f64 = 49.0;
+
+    /// 一次运行的统计
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::stats::Stats;
+    /// let mut stats = Stats::default();
+    /// stats.record_input("你好\nworld");
+    /// stats.record_request("你好", 1);
+    /// stats.record_request("world", 0);
+    /// assert_eq!(stats.input_bytes, 12);
+    /// assert_eq!(stats.input_chars, 8);
+    /// assert_eq!(stats.requests, 2);
+    /// assert_eq!(stats.retries, 1);
+    /// assert_eq!(stats.billed_chars, 7);
+    /// assert_eq!(stats.sent_bytes, 11);
+    /// assert_eq!(stats.cost(1_000_000.0), 7.0);
+    /// assert_eq!(stats.to_json(49.0)["billed_chars"], 7);
+    /// assert_eq!(stats.to_json(49.0)["sent_bytes"], 11);
+    /// ```
+    #[derive(Debug, Clone, Default, PartialEq)]
+    pub struct Stats {
+        /// 过滤后的输入字节数
+        pub input_bytes: usize,
+        /// 过滤后的输入字符数
+        pub input_chars: usize,
+        /// 成功的请求数
+        pub requests: usize,
+        /// 重试次数
+        pub retries: usize,
+        /// 翻译缓存命中次数, 当前版本没有缓存, 总为 0
+        pub cache_hits: usize,
+        /// 作为 `q` 发送的字符数, 即计费字符数
+        pub billed_chars: usize,
+        /// 作为 `q` 发送的字节数
+        pub sent_bytes: usize,
+        /// 去重后未发送的字符数
+        pub dedup_chars: usize,
+        pub elapsed: Duration,
+    }
+    impl Stats {
+        pub fn record_input(&mut self, text: &str) {
+            self.input_bytes += text.len();
+            self.input_chars += text.chars().count();
+        }
+
+        pub fn record_request(&mut self, q: &str, retries: u32) {
+            self.requests += 1;
+            self.retries += retries as usize;
+            self.billed_chars += q.chars().count();
+            self.sent_bytes += q.len();
+        }
+
+        /// 估算费用
+        pub fn cost(&self, price_per_million: f64) -> f64 {
+            self.billed_chars as f64 * price_per_million / 1_000_000.0
+        }
+
+        pub fn to_json(&self, price_per_million: f64) -> Value {
+            json!({
+                "input_bytes": self.input_bytes,
+                "input_chars": self.input_chars,
+                "requests": self.requests,
+                "retries": self.retries,
+                "cache_hits": self.cache_hits,
+                "billed_chars": self.billed_chars,
+                "sent_bytes": self.sent_bytes,
+                "dedup_chars": self.dedup_chars,
+                "elapsed_secs": self.elapsed.as_secs_f64(),
+                "estimated_cost": self.cost(price_per_million),
+            })
+        }
+
+        /// 带有价格的可读输出
+        pub fn display(&self, price_per_million: f64) -> impl Display + '_ {
+            StatsDisplay { stats: self, price_per_million }
+        }
+    }
+
+    struct StatsDisplay<'a> {
+        stats: &'a Stats,
+        price_per_million: f64,
+    }
+    impl Display for StatsDisplay<'_> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let stats = self.stats;
+            writeln!(f, "input: {} bytes, {} chars",
+                     stats.input_bytes, stats.input_chars)?;
+            writeln!(f, "requests: {}, retries: {}, cache hits: {}",
+                     stats.requests, stats.retries, stats.cache_hits)?;
+            writeln!(f, "billed chars: {}, sent bytes: {}, estimated cost: {:.4}",
+                     stats.billed_chars, stats.sent_bytes,
+                     stats.cost(self.price_per_million))?;
+            writeln!(f, "deduplicated chars: {}", stats.dedup_chars)?;
+            writeln!(f, "elapsed: {:.3}s", stats.elapsed.as_secs_f64())
+        }
+    }
+}
+/// 命令行界面的多语言消息
+///
+/// 每条消息在 [`Msg`] 的列表中给出各语言的模板, 增加语言时只需修改本模块,
+/// 模板使用 [`Fmtter`] 的语法, `%0s` 为第一个参数, `%%` 为 `%`
+pub mod i18n {
+    use std::{fmt::Display, str::FromStr};
+
+    use crate::{
+        mini_fmt::Fmtter,
+        translater::{lang_from_locale, ApiError, TranslateError},
+    };
+
+    /// 界面语言
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub enum Lang {
+        #[default]
+        En,
+        /// 简体中文
+        Zh,
+    }
+    impl Lang {
+        pub const ALL: [Self; 2] = [Self::En, Self::Zh];
+
+        /// 从 `zh_CN.UTF-8` 形式的 locale 取得界面语言, 不支持的语言为 `None`
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::i18n::Lang;
+        /// assert_eq!(Lang::from_locale("zh_CN.UTF-8"), Some(Lang::Zh));
+        /// assert_eq!(Lang::from_locale("zh_TW"), Some(Lang::Zh));
+        /// assert_eq!(Lang::from_locale("en_US.UTF-8"), Some(Lang::En));
+        /// assert_eq!(Lang::from_locale("de_DE.UTF-8"), None);
+        /// assert_eq!(Lang::from_locale("C"), None);
+        /// ```
+        pub fn from_locale(locale: &str) -> Option<Self> {
+            match lang_from_locale(locale)? {
+                "en" => Some(Self::En),
+                "zh" | "cht" => Some(Self::Zh),
+                _ => None,
+            }
+        }
+
+        /// 按 `LC_ALL`, `LC_MESSAGES`, `LANG` 的优先级从 locale 取得界面语言,
+        /// 不支持时为英文
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::i18n::Lang;
+        /// std::env::remove_var("LC_ALL");
+        /// std::env::remove_var("LC_MESSAGES");
+        /// std::env::set_var("LANG", "zh_CN.UTF-8");
+        /// assert_eq!(Lang::from_env(), Lang::Zh);
+        /// std::env::set_var("LC_MESSAGES", "ja_JP.UTF-8");
+        /// assert_eq!(Lang::from_env(), Lang::En);
+        /// ```
+        pub fn from_env() -> Self {
+            ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+                .filter_map(|name| std::env::var(name).ok())
+                .find(|value| !value.is_empty())
+                .and_then(|locale| Self::from_locale(&locale))
+                .unwrap_or_default()
+        }
+    }
+    impl FromStr for Lang {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "en" => Ok(Self::En),
+                "zh" => Ok(Self::Zh),
+                _ => Err(format!("unknown language {:?}, expected en or zh", s)),
+            }
+        }
+    }
+    impl Display for Lang {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(match self {
+                Self::En => "en",
+                Self::Zh => "zh",
+            })
+        }
+    }
+
+    macro_rules! messages {
+        ( $( $key:ident { en: $en:expr, zh: $zh:expr $(,)? } )* ) => {
+            /// 命令行的消息, 由 [`Msg::format`] 以界面语言格式化
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub enum Msg {
+                $( $key, )*
+            }
+            impl Msg {
+                /// 所有消息
+                pub const ALL: &'static [Self] = &[ $( Self::$key, )* ];
+
+                /// 消息在 `lang` 中的模板
+                pub fn template(self, lang: Lang) -> &'static str {
+                    match self {
+                        $( Self::$key => match lang {
+                            Lang::En => $en,
+                            Lang::Zh => $zh,
+                        }, )*
+                    }
+                }
+            }
+        };
+    }
+
+    messages! {
+        ErrorPrefix { en: "Error: ", zh: "错误: " }
+        WarningPrefix { en: "warning: ", zh: "警告: " }
+
+        ArgumentMissing { en: "argument missing %0s", zh: "缺少 %0s 的参数" }
+        InvalidOption { en: "invalid option %0s", zh: "无效的选项 %0s" }
+        OptionMissing { en: "missing required option %0s", zh: "缺少必需的选项 %0s" }
+        OptionDuplicated {
+            en: "option %0s given more than once",
+            zh: "选项 %0s 给出了多次",
+        }
+        UnexpectedArgument { en: "unexpected argument %0s", zh: "多余的参数 %0s" }
+        Conflicts { en: "%0s conflicts with %1s", zh: "%0s 与 %1s 冲突" }
+        Requires { en: "%0s requires %1s", zh: "%0s 需要 %1s" }
+        RequiresEither { en: "%0s requires %1s or %2s", zh: "%0s 需要 %1s 或 %2s" }
+        InvalidValue { en: "invalid %0s value `%1s`", zh: "%0s 的值 `%1s` 无效" }
+        InvalidValueExpected {
+            en: "invalid %0s value `%1s`, expected %2s",
+            zh: "%0s 的值 `%1s` 无效, 应为%2s",
+        }
+        PositiveInteger { en: "a positive integer", zh: "正整数" }
+        NonNegativeInteger { en: "a non-negative integer", zh: "非负整数" }
+        Percentage { en: "a percentage in 0..=100", zh: "百分比 0..=100" }
+        LangNames { en: "en or zh", zh: "en 或 zh" }
+        ParseInt { en: "parse to int error `%0s`", zh: "解析整数出错 `%0s`" }
+        ParsePrice { en: "parse price error `%0s`", zh: "解析价格出错 `%0s`" }
+        NoInput {
+            en: "free argument missing, pass a file, -, or pipe text in",
+            zh: "缺少输入, 请给出文件或 -, 或通过管道输入文本",
+        }
+        InitArgs {
+            en: "expected <APPID> <APPKEY> or nothing",
+            zh: "应给出 <APPID> <APPKEY> 或不给出参数",
+        }
+        CacheAction { en: "expected clear or stats", zh: "应为 clear 或 stats" }
+        CacheEmpty {
+            en: "entries: 0, no translation cache in this version",
+            zh: "条目: 0, 此版本没有翻译缓存",
+        }
+        CacheNothingToClear {
+            en: "nothing to clear, no translation cache in this version",
+            zh: "没有需要清除的内容, 此版本没有翻译缓存",
+        }
+        InvalidGlob { en: "invalid glob `%0s` %1s", zh: "无效的 glob `%0s` %1s" }
+        UnknownEncoding { en: "unknown encoding `%0s`", zh: "未知的编码 `%0s`" }
+        UnknownOutputEncoding {
+            en: "unknown output encoding `%0s`",
+            zh: "未知的输出编码 `%0s`",
+        }
+        ConstArgument {
+            en: "%0s can not use argument `%1s`",
+            zh: "%0s 不能使用参数 `%1s`",
+        }
+        BuildFmtter {
+            en: "on `%0s` build fmtter error: %1s",
+            zh: "构建格式 `%0s` 出错: %1s",
+        }
+        UnknownPlaceholder {
+            en: "on `%0s` unknown placeholder `%%{%1s}`",
+            zh: "格式 `%0s` 中有未知的占位符 `%%{%1s}`",
+        }
+        TooManyArguments {
+            en: "`%0s` uses %1s arguments, only %2s are available",
+            zh: "格式 `%0s` 使用了 %1s 个参数, 但只有 %2s 个",
+        }
+        LineCountZero { en: "line count must be positive", zh: "行数必须为正数" }
+        InvalidHeader {
+            en: "invalid header `%0s`, expected `Name: value`",
+            zh: "无效的请求头 `%0s`, 应为 `Name: value`",
+        }
+        ReadTemplate {
+            en: "read template file `%0s` error `%1s`",
+            zh: "读取模板文件 `%0s` 出错 `%1s`",
+        }
+        ConflictsFormats {
+            en: "%0s conflicts with other formats",
+            zh: "%0s 与其他格式冲突",
+        }
+        EmptyFormat {
+            en: "empty format, it would print nothing",
+            zh: "格式为空, 不会输出任何内容",
+        }
+        NoValueFormat {
+            en: "no format contains a value like %%s, %%r or %%0s, \
+                translation will not be printed",
+            zh: "没有格式包含 %%s, %%r 或 %%0s 之类的值, 不会输出译文",
+        }
+        OneFormat {
+            en: "%0s supports only one format",
+            zh: "%0s 只支持一个格式",
+        }
+        InPlaceStdin { en: "--in-place can not use stdin", zh: "--in-place 不能用于 stdin" }
+        WatchOneFile {
+            en: "--watch requires exactly one input file",
+            zh: "--watch 需要恰好一个输入文件",
+        }
+        IsDirectory {
+            en: "`%0s` is a directory, use --recursive",
+            zh: "`%0s` 是目录, 请使用 --recursive",
+        }
+        GlobNotDirectory {
+            en: "--glob only filters files in directories, `%0s` is not a directory",
+            zh: "--glob 只筛选目录中的文件, `%0s` 不是目录",
+        }
+        SameOutput {
+            en: "`%0s` and `%1s` would both be written to `%2s`",
+            zh: "`%0s` 与 `%1s` 会写入同一个文件 `%2s`",
+        }
+
+        ReadText { en: "read text error `%0s`", zh: "读取文本出错 `%0s`" }
+        Write { en: "write error `%0s`", zh: "写入出错 `%0s`" }
+        WriteInPlace { en: "write in place error `%0s`", zh: "原地写入出错 `%0s`" }
+        WriteFile { en: "write file error `%0s`", zh: "写入文件出错 `%0s`" }
+        CreateDir { en: "create dir error `%0s`", zh: "创建目录出错 `%0s`" }
+        OpenLog {
+            en: "open log file %0s error `%1s`",
+            zh: "打开日志文件 %0s 出错 `%1s`",
+        }
+        WatchFailed { en: "watch %0s error `%1s`", zh: "监视 %0s 出错 `%1s`" }
+        ReplacedInvalid {
+            en: "%0s: replaced %1s invalid UTF-8 sequences",
+            zh: "%0s: 替换了 %1s 个无效的 UTF-8 序列",
+        }
+        DedupMismatch {
+            en: "translated lines do not match the source lines, \
+                translating again without deduplication",
+            zh: "译文行数与原文不符, 不去重重新翻译",
+        }
+        TryEncoding {
+            en: "%0s, try another --encoding or --encoding auto",
+            zh: "%0s, 请尝试其他 --encoding 或 --encoding auto",
+        }
+        TryLossy {
+            en: "%0s, try --encoding gbk, --encoding auto or --lossy",
+            zh: "%0s, 请尝试 --encoding gbk, --encoding auto 或 --lossy",
+        }
+        InputTooLarge {
+            en: "input is larger than --max-input-bytes %0s, \
+                use --head-bytes or --stream",
+            zh: "输入超过了 --max-input-bytes %0s, 请使用 --head-bytes 或 --stream",
+        }
+        InputTooLargeLen {
+            en: "input (%0s bytes) is larger than --max-input-bytes %1s, \
+                use --head-bytes or --stream",
+            zh: "输入 (%0s 字节) 超过了 --max-input-bytes %1s, \
+                请使用 --head-bytes 或 --stream",
+        }
+        InputEmpty { en: "input is empty", zh: "输入为空" }
+        Skipped { en: "%0s: %1s, skipped", zh: "%0s: %1s, 已跳过" }
+        StrippedAnsi {
+            en: "stripped %0s bytes of ansi escapes",
+            zh: "去除了 %0s 字节的 ANSI 转义序列",
+        }
+        BlocksInMemory {
+            en: "%0s: at most %1s blocks in memory",
+            zh: "%0s: 内存中至多有 %1s 个块",
+        }
+        Response { en: "response: %0s", zh: "响应: %0s" }
+        Salt { en: "salt: %0s", zh: "盐值: %0s" }
+        Progress { en: "progress: %0s", zh: "进度: %0s" }
+        DetectedLang {
+            en: "Detected source language: %0s",
+            zh: "检测到源语言: %0s",
+        }
+        SameAsInput {
+            en: "translation is the same as input",
+            zh: "译文与输入相同",
+        }
+        TooFewTranslated {
+            en: "only %0s%% of lines were translated, expected at least %1s%%",
+            zh: "只有 %0s%% 的行被翻译, 至少应为 %1s%%",
+        }
+        BlocksFailed {
+            en: "translation failed for blocks %0s",
+            zh: "以下块翻译失败: %0s",
+        }
+        RecordLine { en: "line %0s: %1s", zh: "第 %0s 行: %1s" }
+        SavedResponse { en: "response #%0s: %1s", zh: "第 %0s 个响应: %1s" }
+        Interrupted {
+            en: "interrupted, waiting for running files, \
+                press Ctrl-C again to abort",
+            zh: "已中断, 正在等待进行中的文件, 再次按 Ctrl-C 立即退出",
+        }
+        NotStarted {
+            en: "interrupted, %0s files not started",
+            zh: "已中断, %0s 个文件未开始",
+        }
+        FilesSummary {
+            en: "%0s files translated, %1s skipped, %2s failed",
+            zh: "已翻译 %0s 个文件, 跳过 %1s 个, 失败 %2s 个",
+        }
+
+        ConfigExists {
+            en: "config file %0s exists, use --force to overwrite",
+            zh: "配置文件 %0s 已存在, 使用 --force 覆盖",
+        }
+        ReadPrompt { en: "read %0s error `%1s`", zh: "读取 %0s 出错 `%1s`" }
+        EmptyIdOrKey {
+            en: "appid and appkey can not be empty",
+            zh: "appid 与 appkey 不能为空",
+        }
+        WriteConfig {
+            en: "write config file %0s error `%1s`",
+            zh: "写入配置文件 %0s 出错 `%1s`",
+        }
+        ConfigWritten { en: "config written to %0s", zh: "配置已写入 %0s" }
+
+        Timeout { en: "timeout count >= %0s", zh: "超时次数 >= %0s" }
+        RequestFailed { en: "request error: %0s", zh: "请求出错: %0s" }
+        HttpFailed { en: "http error %0s: %1s", zh: "HTTP 错误 %0s: %1s" }
+        WriteFailed { en: "write error: %0s", zh: "写入出错: %0s" }
+        Api { en: "API error %0s (%1s): %2s", zh: "接口错误 %0s (%1s): %2s" }
+        ApiUnknown { en: "API error %0s (%1s)", zh: "接口错误 %0s (%1s)" }
+        HintRetryLater { en: "retry later", zh: "请稍后重试" }
+        HintUnauthorized {
+            en: "check that the appid is correct and the service is enabled",
+            zh: "检查 appid 是否正确, 以及服务是否已开通",
+        }
+        HintMissingParams {
+            en: "check that the text and language codes are not empty",
+            zh: "检查文本与语言代码是否为空",
+        }
+        HintInvalidSign {
+            en: "check that your appkey matches the appid",
+            zh: "检查 appkey 与 appid 是否匹配",
+        }
+        HintFrequencyLimited {
+            en: "lower the request frequency and retry",
+            zh: "降低请求频率后重试",
+        }
+        HintInsufficientBalance { en: "recharge your account", zh: "为账户充值" }
+        HintLongQuery {
+            en: "lower the frequency of long requests and retry",
+            zh: "降低长文本的请求频率后重试",
+        }
+        HintIllegalIp {
+            en: "check the IP allowlist in the console",
+            zh: "检查控制台中的 IP 白名单",
+        }
+        HintUnsupportedLang {
+            en: "check the from and to language codes",
+            zh: "检查源语言与目标语言的代码",
+        }
+        HintServiceClosed {
+            en: "enable the service in the console",
+            zh: "在控制台中开通服务",
+        }
+
+        HelpUsage { en: "Usage: ", zh: "用法: " }
+        HelpOptions { en: "Options:", zh: "选项:" }
+        HelpNote { en: "NOTE:", zh: "说明:" }
+        HelpDetect {
+            en: concat!(
+                "    print detected language of each <FILE>\n",
+                "    <FILE> is - use stdin, default when stdin is piped\n",
+            ),
+            zh: concat!(
+                "    输出每个 <FILE> 检测出的语言\n",
+                "    <FILE> 为 - 时读取 stdin, stdin 为管道时默认读取 stdin\n",
+            ),
+        }
+        HelpLangs { en: "    list common language codes\n", zh: "    列出常用的语言代码\n" }
+        HelpInit {
+            en: "    write config file, read from stdin if no arguments\n",
+            zh: "    写入配置文件, 没有参数时从 stdin 读取\n",
+        }
+        HelpCache {
+            en: "    clear or show stats of translation cache\n",
+            zh: "    清除翻译缓存或查看其统计\n",
+        }
+        HelpTranslate {
+            en: concat!(
+                "    <FILE> is - use stdin, default when stdin is piped\n",
+                "    <FILE> is directory need --recursive and --out-dir\n",
+                "    default format is dst and src on terminal, otherwise --dst-only\n",
+                "    empty input is skipped without request, see --fail-empty\n",
+                "    input over 10 MiB is refused, see --max-input-bytes and --stream\n",
+                "    failed blocks are marked in output and exit with 7, see --fail-fast\n",
+                "    api errors exit with 5, raw response is shown with --verbose\n",
+                "    BAIDU_FANYI_SALT env sets a fixed salt to reproduce sign errors\n",
+                "    messages are in --lang, or from LANG env, en|zh\n",
+                "    config file in %0s,\n",
+                "        line1: appid, line2: appkey\n",
+            ),
+            zh: concat!(
+                "    <FILE> 为 - 时读取 stdin, stdin 为管道时默认读取 stdin\n",
+                "    <FILE> 为目录时需要 --recursive 与 --out-dir\n",
+                "    终端上默认输出译文与原文, 否则同 --dst-only\n",
+                "    空的输入不发送请求直接跳过, 见 --fail-empty\n",
+                "    拒绝超过 10 MiB 的输入, 见 --max-input-bytes 与 --stream\n",
+                "    失败的块在输出中标记, 并以 7 退出, 见 --fail-fast\n",
+                "    接口错误以 5 退出, --verbose 时输出原始响应\n",
+                "    环境变量 BAIDU_FANYI_SALT 设置固定的盐值, 用于复现签名错误\n",
+                "    消息语言由 --lang 或环境变量 LANG 决定, en|zh\n",
+                "    配置文件位于 %0s,\n",
+                "        第一行: appid, 第二行: appkey\n",
+            ),
+        }
+        HelpFormat { en: "Format:", zh: "格式:" }
+        HelpFormatNote {
+            en: concat!(
+                "    `%%[n]...` example: `%%0s`, index 0 Display\n",
+                "    `%%{timestamp}s` unix seconds, `%%{date}s` RFC 3339 UTC time\n",
+                "    `%%{dst}s` etc. are named arguments, see Format arguments\n",
+                "    `%%?cond{then}:{else}` cond: empty|nonempty|eq(text)\n",
+            ),
+            zh: concat!(
+                "    `%%[n]...` 例如: `%%0s`, 以 Display 输出第 0 个参数\n",
+                "    `%%{timestamp}s` Unix 秒数, `%%{date}s` RFC 3339 UTC 时间\n",
+                "    `%%{dst}s` 等为具名参数, 见格式参数\n",
+                "    `%%?cond{then}:{else}` cond: empty|nonempty|eq(text)\n",
+            ),
+        }
+        HelpFormatArgs { en: "Format arguments:", zh: "格式参数:" }
+        HelpFormatArgsNote {
+            en: concat!(
+                "    %%0 dst, %%1 src, %%2 from (detected), %%3 to\n",
+                "    or by name: %%{dst}, %%{src}, %%{from}, %%{to}\n",
+            ),
+            zh: concat!(
+                "    %%0 译文, %%1 原文, %%2 源语言 (检测出的), %%3 目标语言\n",
+                "    或按名称: %%{dst}, %%{src}, %%{from}, %%{to}\n",
+            ),
+        }
+        HelpCommands { en: "Commands:", zh: "子命令:" }
+        HelpCommandsNote {
+            en: concat!(
+                "    translate  translate files (default)\n",
+                "    detect     detect language of files\n",
+                "    langs      list language codes\n",
+                "    init       write config file\n",
+                "    cache      manage translation cache\n",
+                "    use `%0s <command> -h` show help of command\n",
+            ),
+            zh: concat!(
+                "    translate  翻译文件 (默认)\n",
+                "    detect     检测文件的语言\n",
+                "    langs      列出语言代码\n",
+                "    init       写入配置文件\n",
+                "    cache      管理翻译缓存\n",
+                "    使用 `%0s <command> -h` 查看子命令的帮助\n",
+            ),
+        }
+
+        OptHelp { en: "show help", zh: "显示帮助" }
+        OptVersion { en: "show version", zh: "显示版本" }
+        OptLang {
+            en: "message language, en|zh (default:from LANG)",
+            zh: "消息语言, en|zh (默认:取自 LANG)",
+        }
+        OptInitForce { en: "overwrite existing config file", zh: "覆盖已有的配置文件" }
+        OptFrom { en: "from lang", zh: "源语言" }
+        OptTo {
+            en: "to lang (default:from locale, or zh if same as from lang)",
+            zh: "目标语言 (默认:取自 locale, 与源语言相同时为 zh)",
+        }
+        OptFromDetectOnly {
+            en: "detect from lang once per input, then lock it",
+            zh: "每个输入只检测一次源语言, 之后锁定",
+        }
+        OptLine {
+            en: "read first count lines (default:1), as -l3 or --line=3",
+            zh: "读取开头的 count 行 (默认:1), 如 -l3 或 --line=3",
+        }
+        OptHeadBytes {
+            en: "read whole lines up to n bytes",
+            zh: "读取不超过 n 字节的完整行",
+        }
+        OptMaxInputBytes {
+            en: "refuse larger input (default:10485760, 0 is no limit)",
+            zh: "拒绝更大的输入 (默认:10485760, 0 为不限制)",
+        }
+        OptStream {
+            en: "read and translate large input block by block",
+            zh: "逐块读取并翻译大的输入",
+        }
+        OptLowMem {
+            en: "like --stream, but read, translate and write \
+                in parallel, blocks in flight (default:4)",
+            zh: "同 --stream, 但读取, 翻译与输出同时进行, \
+                blocks 为同时翻译的块数 (默认:4)",
+        }
+        OptLimitUnit {
+            en: "request size unit, chars|bytes (default:chars)",
+            zh: "请求大小的单位, chars|bytes (默认:chars)",
+        }
+        OptStdinSeparator {
+            en: "split stdin and translate each part",
+            zh: "分割 stdin 并分别翻译每段",
+        }
+        OptFmt { en: "formatters (multiple)", zh: "输出格式 (可多次)" }
+        OptTemplateFile {
+            en: "read formatter from file (conflicts -m)",
+            zh: "从文件读取输出格式 (与 -m 冲突)",
+        }
+        OptDstOnly {
+            en: "only output translation, same as -m '%%s%%n'",
+            zh: "只输出译文, 同 -m '%%s%%n'",
+        }
+        OptSrcOnly {
+            en: "only output source, same as -m '%%1s%%n'",
+            zh: "只输出原文, 同 -m '%%1s%%n'",
+        }
+        OptPrepend {
+            en: "output text before translation, like -m, once on stdout, or per output file",
+            zh: "在译文前输出的文本, 同 -m, stdout 上只输出一次, 或每个输出文件一次",
+        }
+        OptAppend {
+            en: "output text after translation, like -m, once on stdout, or per output file",
+            zh: "在译文后输出的文本, 同 -m, stdout 上只输出一次, 或每个输出文件一次",
+        }
+        OptEmptyCount {
+            en: "filter out empty count (default:2)",
+            zh: "过滤连续的空行数 (默认:2)",
+        }
+        OptNoFilter {
+            en: "send whitespace untouched (conflicts -o)",
+            zh: "不处理空白直接发送 (与 -o 冲突)",
+        }
+        OptReadNullTerminated {
+            en: "NUL separated records like find -print0, output ends each with NUL",
+            zh: "记录以 NUL 分隔, 如 find -print0, 输出的每条记录以 NUL 结尾",
+        }
+        OptTrim {
+            en: "trim whitespace around each line",
+            zh: "去除每行首尾的空白",
+        }
+        OptTrimStart { en: "trim whitespace at start of each line", zh: "去除每行开头的空白" }
+        OptTrimEnd { en: "trim whitespace at end of each line", zh: "去除每行末尾的空白" }
+        OptMarkdown {
+            en: "keep markdown code and urls untranslated",
+            zh: "不翻译 Markdown 中的代码与链接",
+        }
+        OptDiff { en: "show word diff of src and dst", zh: "按单词显示原文与译文的差异" }
+        OptColorDiff {
+            en: "show src changed into dst, colored by words",
+            zh: "按单词以颜色显示原文到译文的改动",
+        }
+        OptCount {
+            en: "only output line and char count of result",
+            zh: "只输出结果的行数与字符数",
+        }
+        OptCountOnly {
+            en: "only count input, without translating",
+            zh: "只统计输入, 不翻译",
+        }
+        OptNdjson {
+            en: "translate text field of json lines",
+            zh: "翻译每行 json 的文本字段",
+        }
+        OptTextField {
+            en: "text field of --ndjson (default:text)",
+            zh: "--ndjson 的文本字段 (默认:text)",
+        }
+        OptFailFast {
+            en: "abort on first failed block or record",
+            zh: "第一个块或记录失败时中止",
+        }
+        OptNoDedup {
+            en: "translate every repeated line separately",
+            zh: "重复的行也分别翻译",
+        }
+        OptFailEmpty { en: "exit with 6 if input is empty", zh: "输入为空时以 6 退出" }
+        OptAssertTranslated {
+            en: "exit with 8 if every dst line equals its src",
+            zh: "每行译文都与原文相同时以 8 退出",
+        }
+        OptAssertTranslatedRatio {
+            en: "exit with 8 if under n%% of dst lines changed",
+            zh: "译文不同的行少于 n%% 时以 8 退出",
+        }
+        OptErrorMarker {
+            en: "line of failed block, <code> is error code",
+            zh: "失败块输出的行, <code> 为错误码",
+        }
+        OptSideBySide {
+            en: "show src and dst in two columns",
+            zh: "以两列显示原文与译文",
+        }
+        OptWrap {
+            en: "wrap dst at cols, 0 is terminal width",
+            zh: "在 cols 列处折行译文, 0 为终端宽度",
+        }
+        OptColor {
+            en: "color dst column or diff, auto|always|never",
+            zh: "为译文列或差异着色, auto|always|never",
+        }
+        OptRecursive { en: "translate files in directories", zh: "翻译目录中的文件" }
+        OptJobs {
+            en: "translate up to n files at once (default:1)",
+            zh: "至多同时翻译 n 个文件 (默认:1)",
+        }
+        OptGlob {
+            en: "only translate matched files in directories",
+            zh: "只翻译目录中匹配的文件",
+        }
+        OptOutDir {
+            en: "write results to files in directory",
+            zh: "将结果写入目录中的文件",
+        }
+        OptForce {
+            en: "translate even output is newer than input",
+            zh: "即使输出比输入新也翻译",
+        }
+        OptDryRun { en: "only list input and output files", zh: "只列出输入与输出文件" }
+        OptInPlace {
+            en: "overwrite input files, backup if SUFFIX is attached (-i.bak)",
+            zh: "覆盖输入文件, 紧跟 SUFFIX 时 (-i.bak) 备份",
+        }
+        OptStripAnsi {
+            en: "strip ansi escapes, auto|always|never",
+            zh: "去除 ANSI 转义序列, auto|always|never",
+        }
+        OptEncoding {
+            en: "input encoding, e.g. gbk|big5|utf-16le|auto",
+            zh: "输入的编码, 如 gbk|big5|utf-16le|auto",
+        }
+        OptOutputEncoding {
+            en: "output encoding, e.g. gbk|latin1 (default:utf-8)",
+            zh: "输出的编码, 如 gbk|latin1 (默认:utf-8)",
+        }
+        OptLossy {
+            en: "replace invalid UTF-8 with U+FFFD",
+            zh: "以 U+FFFD 替换无效的 UTF-8",
+        }
+        OptNewline { en: "lf|crlf|keep (default:lf)", zh: "lf|crlf|keep (默认:lf)" }
+        OptVerbose { en: "show more info to stderr", zh: "在 stderr 输出更多信息" }
+        OptStats { en: "show usage stats to stderr", zh: "在 stderr 输出用量统计" }
+        OptStatsJson {
+            en: "show usage stats as json to stdout",
+            zh: "在 stdout 以 json 输出用量统计",
+        }
+        OptPricePerMillion {
+            en: "price for estimated cost (default:49)",
+            zh: "估算费用的单价 (默认:49)",
+        }
+        OptProgress { en: "show request progress to stderr", zh: "在 stderr 输出请求进度" }
+        OptLogFile {
+            en: "append json log of each api call to file",
+            zh: "将每次接口调用的 json 日志追加到文件",
+        }
+        OptLogLevel { en: "all|errors-only (default:all)", zh: "all|errors-only (默认:all)" }
+        OptWatch {
+            en: "retranslate input file when it changes",
+            zh: "输入文件改变时重新翻译",
+        }
+        OptWatchDebounce {
+            en: "debounce of --watch (default:300)",
+            zh: "--watch 的防抖时间 (默认:300)",
+        }
+        OptWatchSep {
+            en: "print text between --watch runs, not clear",
+            zh: "在 --watch 的每次翻译之间输出文本, 而非清屏",
+        }
+        OptFromJson {
+            en: "format saved api responses in file, without request",
+            zh: "格式化文件中保存的接口响应, 不发送请求",
+        }
+        OptHeader { en: "extra request header (multiple)", zh: "额外的请求头 (可多次)" }
+        OptContext {
+            en: "context in source language, for ambiguous text",
+            zh: "与原文同语言的上下文, 用于有歧义的文本",
+        }
+        OptUserAgent { en: "User-Agent request header", zh: "User-Agent 请求头" }
+    }
+
+    impl Msg {
+        /// 以 `lang` 的模板格式化, `args` 依次为 `%0s`, `%1s`, ...
+        ///
+        /// # Panics
+        /// 模板无法构建或参数不足时 panic
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::i18n::{Lang, Msg};
+        /// let args: [&dyn std::fmt::Display; 2] = [&"--diff", &"--color-diff"];
+        /// assert_eq!(
+        ///     Msg::Conflicts.format(Lang::En, &args),
+        ///     "--diff conflicts with --color-diff",
+        /// );
+        /// assert_eq!(
+        ///     Msg::Conflicts.format(Lang::Zh, &args),
+        ///     "--diff 与 --color-diff 冲突",
+        /// );
+        /// assert_eq!(
+        ///     Msg::TooFewTranslated.format(Lang::Zh, &[&"12.5", &50]),
+        ///     "只有 12.5% 的行被翻译, 至少应为 50%",
+        /// );
+        /// ```
+        ///
+        /// 所有模板都可以构建, 且各语言使用的参数相同
+        /// ```
+        /// use baidu_fanyi::{i18n::{Lang, Msg}, mini_fmt::Fmtter};
+        /// for &msg in Msg::ALL {
+        ///     let slots = Lang::ALL.map(|lang| {
+        ///         let fmtter = Fmtter::build(msg.template(lang)).unwrap();
+        ///         let mut slots = fmtter.value_slots()
+        ///             .map(|(i, _)| i.expect("index of argument"))
+        ///             .collect::<Vec<_>>();
+        ///         slots.sort_unstable();
+        ///         slots.dedup();
+        ///         slots
+        ///     });
+        ///     assert_eq!(slots[0], slots[1], "{msg:?}");
+        /// }
+        /// ```
+        pub fn format(self, lang: Lang, args: &[&dyn Display]) -> String {
+            let args = args.iter().map(ToString::to_string).collect::<Vec<_>>();
+            Fmtter::build(self.template(lang))
+                .and_then(|fmtter| fmtter.try_fmt_str(&args))
+                .unwrap_or_else(|e| panic!("message {self:?} in {lang}: {e}"))
+        }
+    }
+
+    /// [`ApiError`] 的修改建议, 未列出的错误码没有建议
+    fn api_hint(e: &ApiError) -> Option<Msg> {
+        Some(match e {
+            ApiError::RequestTimeout | ApiError::SystemError => Msg::HintRetryLater,
+            ApiError::Unauthorized => Msg::HintUnauthorized,
+            ApiError::MissingParams => Msg::HintMissingParams,
+            ApiError::InvalidSign => Msg::HintInvalidSign,
+            ApiError::FrequencyLimited => Msg::HintFrequencyLimited,
+            ApiError::InsufficientBalance => Msg::HintInsufficientBalance,
+            ApiError::LongQueryTooFrequent => Msg::HintLongQuery,
+            ApiError::IllegalIp => Msg::HintIllegalIp,
+            ApiError::UnsupportedLang => Msg::HintUnsupportedLang,
+            ApiError::ServiceClosed => Msg::HintServiceClosed,
+            ApiError::Unknown { .. } => return None,
+        })
+    }
+
+    /// 以 `lang` 描述接口错误, 错误码后为接口给出的错误信息
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::{i18n::{api_error, Lang}, translater::ApiError};
+    /// assert_eq!(
+    ///     api_error(Lang::Zh, &ApiError::InvalidSign),
+    ///     "接口错误 54001 (Invalid Sign): 检查 appkey 与 appid 是否匹配",
+    /// );
+    /// assert_eq!(
+    ///     api_error(Lang::En, &ApiError::InvalidSign),
+    ///     ApiError::InvalidSign.to_string(),
+    /// );
+    /// ```
+    pub fn api_error(lang: Lang, e: &ApiError) -> String {
+        let code = e.error_code();
+        match api_hint(e) {
+            Some(hint) => {
+                let hint = hint.format(lang, &[]);
+                Msg::Api.format(lang, &[&code, &e.message(), &hint])
+            },
+            None => Msg::ApiUnknown.format(lang, &[&code, &e.message()]),
+        }
+    }
+
+    /// 以 `lang` 描述翻译错误, 网络库与响应数据的错误信息保持原样
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::{i18n::{translate_error, Lang}, translater::TranslateError};
+    /// let e = TranslateError::Timeout(3);
+    /// assert_eq!(translate_error(Lang::En, &e), "timeout count >= 3");
+    /// assert_eq!(translate_error(Lang::Zh, &e), "超时次数 >= 3");
+    /// ```
+    pub fn translate_error(lang: Lang, e: &TranslateError) -> String {
+        match e {
+            TranslateError::Timeout(count) => Msg::Timeout.format(lang, &[count]),
+            TranslateError::Request(e) => Msg::RequestFailed.format(lang, &[e]),
+            TranslateError::Response(e) => e.clone(),
+            TranslateError::ApiError(e, _) => api_error(lang, e),
+            TranslateError::Http { status, body_snippet } => {
+                Msg::HttpFailed.format(lang, &[status, body_snippet])
+            },
+            TranslateError::Io(e) => Msg::WriteFailed.format(lang, &[e]),
+        }
+    }
+}
+pub mod translater {
+    use std::{
+        fmt::Display,
+        str::FromStr,
+        sync::{Arc, Mutex, OnceLock},
+        time::{Duration, Instant},
+    };
+
+    use reqwest::{
+        header::{
+            HeaderMap,
+            HeaderName,
+            HeaderValue,
+            CONTENT_TYPE,
+            RETRY_AFTER,
+            USER_AGENT,
+        },
+        Client,
+        Error as RError,
+        Response,
+        StatusCode,
+    };
+    use md5::{
+        self,
+        Digest
+    };
+    use rand::Rng;
+    use serde_json::Value;
+    use tokio::{
+        sync::{watch, Semaphore},
+        task::JoinSet,
+    };
+    use unicode_segmentation::UnicodeSegmentation;
+
+    use crate::{
+        diff::TransDiff,
+        html::Document,
+        i18n::{self, Lang},
+        log::{CallLog, LogEntry},
+        response::{detected_lang, FormatError, JSONData, TransResult},
+        stats::Stats,
+    };
+
+    macro_rules! literals {
+        ( $(
+                #define $name:ident = $value:expr;
+        )* ) => {
+            $(
+                macro_rules! $name {
+                    () => {
+                        $value
+                    }
+                }
+            )*
+        };
+    }
+    literals!{
+        #define API_END_POINT = "http://api.fanyi.baidu.com";
+        #define API_INTERFACE_PATH = "/api/trans/vip/translate";
+        #define API_DETECT_PATH = "/api/trans/vip/language";
+    }
+
+
+    pub const URL: &str = concat!(API_END_POINT!(), API_INTERFACE_PATH!());
+    pub const DETECT_URL: &str = concat!(API_END_POINT!(), API_DETECT_PATH!());
+    pub const DEFAULT_FROM_LANG: &str = "auto";
+    /// 目标语言不能为 `auto`, 无法从环境推断时使用中文
+    pub const DEFAULT_TO_LANG: &str = "zh";
+    pub const MAX_TIMEOUT_COUNT: u32 = 2;
+    pub const MAX_ERROR_COUNT: u32 = 2;
+    /// 没有 `Retry-After` 头时重试前的等待时间
+    pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+    /// 重试前最长的等待时间
+    pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+    /// 按字节计数时单次请求的上限
+    pub const MAX_REQUEST_BYTES: usize = 3000;
+    /// 按字符计数时单次请求的上限, 即文档中 `q` 的长度限制
+    pub const MAX_REQUEST_CHARS: usize = 6000;
+    /// 默认的 User-Agent 请求头
+    pub const DEFAULT_USER_AGENT: &str
+        = concat!("baidu_fanyi/", env!("CARGO_PKG_VERSION"));
+    /// 接口的成功错误码
+    pub const SUCCESS_CODE: i64 = 52000;
+
+    /// 常用的语言代码及其名称
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::LANGS;
+    /// assert!(LANGS.iter().any(|&(code, _)| code == "zh"));
+    /// assert!(LANGS.iter().any(|&(code, _)| code == "en"));
+    /// ```
+    pub const LANGS: &[(&str, &str)] = &[
+        ("auto", "Auto detect"),
+        ("zh", "Chinese"),
+        ("cht", "Traditional Chinese"),
+        ("yue", "Cantonese"),
+        ("wyw", "Classical Chinese"),
+        ("en", "English"),
+        ("jp", "Japanese"),
+        ("kor", "Korean"),
+        ("fra", "French"),
+        ("spa", "Spanish"),
+        ("th", "Thai"),
+        ("ara", "Arabic"),
+        ("ru", "Russian"),
+        ("pt", "Portuguese"),
+        ("de", "German"),
+        ("it", "Italian"),
+        ("el", "Greek"),
+        ("nl", "Dutch"),
+        ("pl", "Polish"),
+        ("bul", "Bulgarian"),
+        ("est", "Estonian"),
+        ("dan", "Danish"),
+        ("fin", "Finnish"),
+        ("cs", "Czech"),
+        ("rom", "Romanian"),
+        ("slo", "Slovenian"),
+        ("swe", "Swedish"),
+        ("hu", "Hungarian"),
+        ("vie", "Vietnamese"),
+    ];
+
+    /// 将 `zh_CN.UTF-8` 形式的 locale 映射为百度的语言代码
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::lang_from_locale;
+    /// assert_eq!(lang_from_locale("zh_CN.UTF-8"), Some("zh"));
+    /// assert_eq!(lang_from_locale("zh_TW.UTF-8"), Some("cht"));
+    /// assert_eq!(lang_from_locale("en_US.UTF-8"), Some("en"));
+    /// assert_eq!(lang_from_locale("ja_JP"), Some("jp"));
+    /// assert_eq!(lang_from_locale("fr_FR@euro"), Some("fra"));
+    /// assert_eq!(lang_from_locale("C.UTF-8"), None);
+    /// assert_eq!(lang_from_locale("POSIX"), None);
+    /// assert_eq!(lang_from_locale(""), None);
+    /// ```
+    pub fn lang_from_locale(locale: &str) -> Option<&'static str> {
+        let locale = locale.split(['.', '@']).next().unwrap_or_default();
+        let (lang, region) = locale.split_once(['_', '-']).unwrap_or((locale, ""));
+        Some(match (&*lang.to_lowercase(), &*region.to_uppercase()) {
+            ("zh", "TW" | "HK" | "MO") => "cht",
+            ("zh", _) => "zh",
+            ("en", _) => "en",
+            ("ja", _) => "jp",
+            ("ko", _) => "kor",
+            ("fr", _) => "fra",
+            ("es", _) => "spa",
+            ("th", _) => "th",
+            ("ar", _) => "ara",
+            ("ru", _) => "ru",
+            ("pt", _) => "pt",
+            ("de", _) => "de",
+            ("it", _) => "it",
+            ("el", _) => "el",
+            ("nl", _) => "nl",
+            ("pl", _) => "pl",
+            ("bg", _) => "bul",
+            ("et", _) => "est",
+            ("da", _) => "dan",
+            ("fi", _) => "fin",
+            ("cs", _) => "cs",
+            ("ro", _) => "rom",
+            ("sl", _) => "slo",
+            ("sv", _) => "swe",
+            ("hu", _) => "hu",
+            ("vi", _) => "vie",
+            _ => return None,
+        })
+    }
+
+    /// 按 `LC_ALL`, `LC_MESSAGES`, `LANG` 的优先级从 locale 推断目标语言,
+    /// 无法推断时为 [`DEFAULT_TO_LANG`]
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::{default_to_lang, DEFAULT_TO_LANG};
+    /// std::env::remove_var("LC_ALL");
+    /// std::env::remove_var("LC_MESSAGES");
+    /// std::env::set_var("LANG", "de_DE.UTF-8");
+    /// assert_eq!(default_to_lang(), "de");
+    /// std::env::set_var("LC_ALL", "ko_KR.UTF-8");
+    /// assert_eq!(default_to_lang(), "kor");
+    /// std::env::set_var("LC_ALL", "C");
+    /// assert_eq!(default_to_lang(), DEFAULT_TO_LANG);
+    /// ```
+    pub fn default_to_lang() -> &'static str {
+        ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|locale| lang_from_locale(&locale))
+            .unwrap_or(DEFAULT_TO_LANG)
+    }
+
+    /// 源语言为 `from` 时的默认目标语言, 即 [`default_to_lang`],
+    /// 但与 `from` 相同时为 [`DEFAULT_TO_LANG`], 源语言也是中文时为 `en`,
+    /// 避免如英文 locale 下翻译英文时原样返回
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::default_to_lang_for;
+    /// std::env::remove_var("LC_ALL");
+    /// std::env::remove_var("LC_MESSAGES");
+    /// std::env::set_var("LANG", "en_US.UTF-8");
+    /// assert_eq!(default_to_lang_for("auto"), "en");
+    /// assert_eq!(default_to_lang_for("jp"), "en");
+    /// assert_eq!(default_to_lang_for("en"), "zh");
+    /// std::env::set_var("LANG", "zh_CN.UTF-8");
+    /// assert_eq!(default_to_lang_for("zh"), "en");
+    /// ```
+    pub fn default_to_lang_for(from: &str) -> &'static str {
+        match default_to_lang() {
+            to if to != from => to,
+            _ if from != DEFAULT_TO_LANG => DEFAULT_TO_LANG,
+            _ => "en",
+        }
+    }
+
+    /// 发送请求前检查语言对, 错误时返回错误信息, 否则返回警告
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::check_langs;
+    /// assert_eq!(check_langs("auto", "en"), Ok(vec![]));
+    /// assert_eq!(
+    ///     check_langs("en", "auto").unwrap_err(),
+    ///     "target language can not be `auto`, use -t",
+    /// );
+    /// assert_eq!(check_langs("en", "en").unwrap(), [
+    ///     "source and target language are both `en`, input is returned unchanged",
+    /// ]);
+    /// assert_eq!(check_langs("xx", "zh").unwrap(), [
+    ///     "`xx` is not a common language code, see `langs`",
+    /// ]);
+    /// ```
+    pub fn check_langs(from: &str, to: &str) -> Result<Vec<String>, String> {
+        if to == "auto" {
+            return Err("target language can not be `auto`, use -t".into());
+        }
+        let mut warnings = vec![];
+        if from == to {
+            warnings.push(format!(
+                "source and target language are both `{to}`, \
+                input is returned unchanged"));
+        }
+        // LANGS 只包含常用语言, 其它代码仍交给接口判断
+        for lang in [from, to] {
+            if !LANGS.iter().any(|&(code, _)| code == lang) {
+                warnings.push(format!("`{lang}` is not a common language code, see `langs`"));
+            }
+        }
+        Ok(warnings)
+    }
+
+
+    /// 请求大小的计数单位
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::LimitUnit;
+    /// assert_eq!(LimitUnit::default(), LimitUnit::Chars);
+    /// assert_eq!("bytes".parse(), Ok(LimitUnit::Bytes));
+    /// assert!("lines".parse::<LimitUnit>().is_err());
+    /// assert_eq!(LimitUnit::Bytes.measure("中a"), 4);
+    /// assert_eq!(LimitUnit::Chars.measure("中a"), 2);
+    /// assert_eq!(LimitUnit::Bytes.prefix_len("中文", 4), 3);
+    /// assert_eq!(LimitUnit::Chars.prefix_len("中文", 1), 3);
+    /// assert_eq!(LimitUnit::Chars.prefix_len("中文", 9), 6);
+    /// ```
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub enum LimitUnit {
+        /// UTF-8 字节, 上限为 [`MAX_REQUEST_BYTES`]
+        Bytes,
+        /// 字符, 与计费方式一致, 上限为 [`MAX_REQUEST_CHARS`]
+        #[default]
+        Chars,
+    }
+    impl LimitUnit {
+        /// 单次请求的上限
+        pub fn max(self) -> usize {
+            match self {
+                Self::Bytes => MAX_REQUEST_BYTES,
+                Self::Chars => MAX_REQUEST_CHARS,
+            }
+        }
+
+        /// 不超过上限的文本最多的字节数
+        pub fn max_bytes(self) -> usize {
+            match self {
+                Self::Bytes => MAX_REQUEST_BYTES,
+                Self::Chars => MAX_REQUEST_CHARS * 4,
+            }
+        }
+
+        pub fn measure(self, text: &str) -> usize {
+            match self {
+                Self::Bytes => text.len(),
+                Self::Chars => text.chars().count(),
+            }
+        }
+
+        /// 不超过 `n` 个单位的最长前缀的字节数, 位于字符边界
+        pub fn prefix_len(self, text: &str, n: usize) -> usize {
+            match self {
+                Self::Bytes => {
+                    let mut end = n.min(text.len());
+                    while !text.is_char_boundary(end) { end -= 1 }
+                    end
+                },
+                Self::Chars => text.char_indices().nth(n).map_or(text.len(), |(i, _)| i),
+            }
+        }
+    }
+    impl FromStr for LimitUnit {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "bytes" => Ok(Self::Bytes),
+                "chars" => Ok(Self::Chars),
+                _ => Err(format!("invalid limit unit `{s}`, expected bytes|chars")),
+            }
+        }
+    }
+    impl Display for LimitUnit {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(match self {
+                Self::Bytes => "bytes",
+                Self::Chars => "chars",
+            })
+        }
+    }
+
+
+    /// [`split_blocks`] 中单个子块不小于最大请求大小
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct BlockTooLarge;
+    impl Display for BlockTooLarge {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("block reaches the request size limit")
+        }
+    }
+    impl std::error::Error for BlockTooLarge {}
+
+    /// 传入累计大小与上限
+    /// 修改大小计数并且返回是否需要分配新的一个块
+    pub fn split_blocks(
+        sum: &mut usize,
+        this: usize,
+        max: usize,
+    ) -> Result<bool, BlockTooLarge> {
+        if this < max {
+            let num = *sum + this;
+            Ok(if num < max {
+                *sum = num;
+                false
+            } else {
+                // 旧子块加上新子块超出了最大块大小
+                // 将统计大小赋值为新块大小并通知新建块
+                // 比较的子块将被放入新块
+                *sum = this;
+                true
+            })
+        } else {
+            // 大于最大请求大小
+            Err(BlockTooLarge)
+        }
+    }
+
+
+    /// 同 [`split_text_blocks_by`], 使用默认的 [`LimitUnit`]
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::split_text_blocks;
+    /// let line = format!("{}\n", "a".repeat(1999));
+    /// let text = line.repeat(5);
+    /// let blocks = split_text_blocks(&text);
+    /// assert_eq!(blocks, [line.repeat(2), line.repeat(2), line.clone()]);
+    ///
+    /// let long = format!("{}\n", "b".repeat(7000));
+    /// let text = format!("{line}{long}{line}");
+    /// assert_eq!(split_text_blocks(&text), [&line, &long, &line]);
+    /// assert_eq!(split_text_blocks(""), [""]);
+    /// ```
+    pub fn split_text_blocks(text: &str) -> Vec<&str> {
+        split_text_blocks_by(text, LimitUnit::default())
+    }
+
+    /// 按行将文本分为多个小于 `unit` 上限的块,
+    /// 超长的行单独成块, 空文本返回一个空块
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::{split_text_blocks_by, LimitUnit::*};
+    /// let count = |text, unit| split_text_blocks_by(text, unit).len();
+    ///
+    /// // 1000 字节, 1000 字符
+    /// let ascii = format!("{}\n", "a".repeat(999)).repeat(5);
+    /// assert_eq!((count(&ascii, Bytes), count(&ascii, Chars)), (3, 1));
+    /// // 2998 字节, 1000 字符
+    /// let cjk = format!("{}\n", "中".repeat(999)).repeat(5);
+    /// assert_eq!((count(&cjk, Bytes), count(&cjk, Chars)), (5, 1));
+    /// // 4001 字节, 2001 字符: 按字节超长, 按字符每块两行
+    /// let line = format!("{}\n", "中a".repeat(1000));
+    /// let mixed = line.repeat(5);
+    /// assert_eq!(split_text_blocks_by(&mixed, Bytes), [&line; 5]);
+    /// assert_eq!(
+    ///     split_text_blocks_by(&mixed, Chars),
+    ///     [line.repeat(2), line.repeat(2), line.clone()],
+    /// );
+    /// ```
+    pub fn split_text_blocks_by(text: &str, unit: LimitUnit) -> Vec<&str> {
+        let mut blocks = vec![];
+        let (mut start, mut pos, mut sum) = (0, 0, 0);
+        for line in text.split_inclusive('\n') {
+            match split_blocks(&mut sum, unit.measure(line), unit.max()) {
+                Ok(false) => (),
+                Ok(true) => {
+                    blocks.push(&text[start..pos]);
+                    start = pos;
+                },
+                Err(BlockTooLarge) => {
+                    if pos > start {
+                        blocks.push(&text[start..pos]);
+                    }
+                    blocks.push(line);
+                    start = pos + line.len();
+                    sum = 0;
+                },
+            }
+            pos += line.len();
+        }
+        if start < text.len() || blocks.is_empty() {
+            blocks.push(&text[start..]);
+        }
+        blocks
+    }
+
+
+    /// 中日文句末标点
+    const CJK_SENTENCE_ENDS: &[char] = &['。', '！', '？', '…', '!', '?'];
+    /// 跟随在句末标点后的闭合符号, 归入前一句
+    const CJK_CLOSERS: &[char] = &['」', '』', '”', '’', '）', '》', '】', '"', '\''];
+
+    /// 按中日文句末标点切分句子, 标点及其后的闭合符号归入前一句
+    fn cjk_sentences(text: &str) -> Vec<&str> {
+        enum State { Text, End }
+        let mut sentences = vec![];
+        let (mut start, mut state) = (0, State::Text);
+        for (i, ch) in text.char_indices() {
+            state = match state {
+                State::Text if CJK_SENTENCE_ENDS.contains(&ch) => State::End,
+                State::Text => State::Text,
+                State::End if CJK_SENTENCE_ENDS.contains(&ch)
+                    || CJK_CLOSERS.contains(&ch) => State::End,
+                State::End => {
+                    sentences.push(&text[start..i]);
+                    start = i;
+                    State::Text
+                },
+            };
+        }
+        if start < text.len() {
+            sentences.push(&text[start..]);
+        }
+        sentences
+    }
+
+    /// 按字形簇将文本硬切为不超过 `max_bytes` 的片段,
+    /// 单个字形簇超长时单独成段
+    fn hard_split(text: &str, max_bytes: usize) -> Vec<String> {
+        let mut pieces = vec![];
+        let mut cur = String::new();
+        for g in text.graphemes(true) {
+            if !cur.is_empty() && cur.len() + g.len() > max_bytes {
+                pieces.push(std::mem::take(&mut cur));
+            }
+            cur.push_str(g);
+        }
+        if !cur.is_empty() {
+            pieces.push(cur);
+        }
+        pieces
+    }
+
+    /// 在中日文句末标点 (。！？…) 处将文本分为不超过 `max_bytes` 的块,
+    /// 仅当单个句子超过 `max_bytes` 时才按字形簇硬切
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::split_cjk_sentences;
+    /// let text = "今天天气很好。我们去公园吧！";
+    /// assert_eq!(split_cjk_sentences(text, 100), [text]);
+    /// assert_eq!(split_cjk_sentences(text, 30),
+    ///            ["今天天气很好。", "我们去公园吧！"]);
+    /// assert_eq!(split_cjk_sentences("他说：“好。”然后走了。", 24),
+    ///            ["他说：“好。”", "然后走了。"]);
+    /// assert_eq!(split_cjk_sentences("一二三四五", 6), ["一二", "三四", "五"]);
+    /// assert!(split_cjk_sentences("", 10).is_empty());
+    /// ```
+    pub fn split_cjk_sentences(text: &str, max_bytes: usize) -> Vec<String> {
+        let mut blocks = vec![];
+        let mut cur = String::new();
+        for sentence in cjk_sentences(text) {
+            if cur.len() + sentence.len() <= max_bytes {
+                cur.push_str(sentence);
+                continue;
+            }
+            if !cur.is_empty() {
+                blocks.push(std::mem::take(&mut cur));
+            }
+            if sentence.len() <= max_bytes {
+                cur.push_str(sentence);
+            } else {
+                let mut pieces = hard_split(sentence, max_bytes);
+                // 最后一段可与后续句子合并
+                cur = pieces.pop().unwrap_or_default();
+                blocks.extend(pieces);
+            }
+        }
+        if !cur.is_empty() {
+            blocks.push(cur);
+        }
+        blocks
+    }
+
+
+    /// 构建 md5 值, 官方示例是 utf-8 编码, 而 rust 字符串为 utf-8, 因此不用转换
+    fn make_md5(s: &str) -> Digest {
+        md5::compute(s.as_bytes())
+    }
+
+
+    /// 随机盐值的下界, 官方文档要求盐值在 [32768,65536]
+    pub const SALT_MIN: u32 = 32768;
+    /// 随机盐值的上界, 包含在内
+    pub const SALT_MAX: u32 = 65536;
+    /// 覆盖盐值的环境变量, 用于复现签名问题
+    pub const SALT_ENV: &str = "BAIDU_FANYI_SALT";
+
+    /// 盐值的来源
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::{SaltSource, SALT_MAX, SALT_MIN};
+    /// for _ in 0..1000 {
+    ///     let salt: u32 = SaltSource::Random.next().parse().unwrap();
+    ///     assert!((SALT_MIN..=SALT_MAX).contains(&salt));
+    /// }
+    ///
+    /// assert_eq!(SaltSource::Fixed("0042".into()).next(), "0042");
+    /// ```
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub enum SaltSource {
+        /// [`SALT_MIN`] 到 [`SALT_MAX`] 间的随机数
+        #[default]
+        Random,
+        /// 固定的盐值, 原样用于请求与签名
+        Fixed(String),
+    }
+    impl SaltSource {
+        /// 设置了 [`SALT_ENV`] 时为其值, 否则为随机
+        pub fn from_env() -> Self {
+            std::env::var(SALT_ENV).map_or(Self::Random, Self::Fixed)
+        }
+
+        /// 生成一个盐值
+        pub fn next(&self) -> String {
+            match self {
+                Self::Random => rand::thread_rng()
+                    .gen_range(SALT_MIN..=SALT_MAX)
+                    .to_string(),
+                Self::Fixed(salt) => salt.clone(),
+            }
+        }
+    }
+    impl Display for SaltSource {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Random => write!(f, "random in [{SALT_MIN}, {SALT_MAX}]"),
+                Self::Fixed(salt) => write!(f, "{salt:?} from {SALT_ENV}"),
+            }
+        }
+    }
+
+
+    /// `Content-Type` 由 [`RequestBuilder::form`](reqwest::RequestBuilder::form) 设置,
+    /// 其它请求头来自 [`Client`] 的默认请求头
+    async fn post(
+        client: &Client,
+        headers: &HeaderMap,
+        url: &str,
+        data: &JSONData
+        ) -> Result<Response, RError> {
+        client.post(url)
+            .headers(headers.clone())
+            .form(data)
+            .send().await
+    }
+
+
+    /// 响应状态是否应当重试, 即 429 与 5xx
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::should_retry;
+    /// use reqwest::StatusCode;
+    /// assert!(should_retry(StatusCode::TOO_MANY_REQUESTS));
+    /// assert!(should_retry(StatusCode::BAD_GATEWAY));
+    /// assert!(!should_retry(StatusCode::OK));
+    /// assert!(!should_retry(StatusCode::FORBIDDEN));
+    /// ```
+    pub fn should_retry(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+
+    /// 重试前的等待时间, 优先使用 `Retry-After` 头的秒数
+    fn retry_delay(response: &Response) -> Duration {
+        response.headers().get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map_or(DEFAULT_RETRY_DELAY, Duration::from_secs)
+            .min(MAX_RETRY_DELAY)
+    }
+
+    /// 发送请求, 超时, 出错或响应状态为 [`should_retry`] 时重试,
+    /// 返回响应与重试次数, 重试次数用尽时返回最后的响应
+    /// # Examples
+    /// ```no_run
+    /// use baidu_fanyi::{response::JSONData, translater::post_retry};
+    ///
+    /// let mut payload = JSONData::new();
+    /// payload.insert("q".into(), "你好".into());
+    /// let (response, retries) = tokio::runtime::Runtime::new().unwrap()
+    ///     .block_on(post_retry(&reqwest::Client::new(), "http://127.0.0.1/", &payload))
+    ///     .unwrap();
+    /// println!("{} after {retries} retries", response.status());
+    /// ```
+    pub async fn post_retry(
+        client: &Client,
+        url: &str,
+        payload: &JSONData,
+    ) -> Result<(Response, u32), TranslateError> {
+        post_retry_with(client, &HeaderMap::new(), url, payload).await
+    }
+
+    /// 同 [`post_retry`], 每个请求另外带有 `headers`, 同名时覆盖 [`Client`] 的默认请求头
+    async fn post_retry_with(
+        client: &Client,
+        headers: &HeaderMap,
+        url: &str,
+        payload: &JSONData,
+    ) -> Result<(Response, u32), TranslateError> {
+        let mut timeout_count: u32 = 0;
+        let mut error_count: u32 = 0;
+        loop {
+            match post(client, headers, url, payload).await {
+                Ok(val) if should_retry(val.status()) => {
+                    error_count += 1;
+                    if error_count >= MAX_ERROR_COUNT {
+                        break Ok((val, timeout_count + error_count - 1))
+                    }
+                    tokio::time::sleep(retry_delay(&val)).await;
+                },
+                Ok(val) => break Ok((val, timeout_count + error_count)),
+                Err(e) => {
+                    if e.is_timeout() {
+                        timeout_count += 1
+                    } else {
+                        error_count += 1
+                    }
+                    if timeout_count >= MAX_TIMEOUT_COUNT {
+                        return Err(TranslateError::Timeout(timeout_count))
+                    }
+                    if error_count >= MAX_ERROR_COUNT {
+                        return Err(TranslateError::Request(e))
+                    }
+                }
+            }
+        }
+    }
+
+
+    /// 错误信息中响应体最多保留的字节数
+    const MAX_SNIPPET_BYTES: usize = 500;
+
+    /// 截断响应体并转义其中的控制字符
+    fn body_snippet(body: &[u8]) -> String {
+        let truncated = body.len() > MAX_SNIPPET_BYTES;
+        let body = String::from_utf8_lossy(&body[..body.len().min(MAX_SNIPPET_BYTES)]);
+        let mut snippet = body.chars()
+            .map(|ch| if ch.is_control() {
+                ch.escape_default().to_string()
+            } else {
+                ch.to_string()
+            })
+            .collect::<String>();
+        if truncated {
+            snippet.push_str("...");
+        }
+        snippet
+    }
+
+    /// 响应状态与 `Content-Type`
+    fn response_meta(response: &Response) -> (StatusCode, Option<String>) {
+        let content_type = response.headers().get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(Into::into);
+        (response.status(), content_type)
+    }
+
+    /// 解析响应体, 检查响应状态, JSON 格式与接口错误码
+    ///
+    /// 代理或认证页面常返回 HTML, 因此错误中包含截断后的响应体
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::{parse_body, TranslateError};
+    /// use reqwest::StatusCode;
+    ///
+    /// let html = b"<html>\n<title>Login</title></html>";
+    /// let e = parse_body(StatusCode::OK, Some("text/html"), html).unwrap_err();
+    /// let msg = e.to_string();
+    /// assert!(msg.contains("content-type: text/html"), "{msg}");
+    /// assert!(msg.contains(r"<html>\n<title>"), "{msg}");
+    /// assert!(!e.is_retryable());
+    ///
+    /// let e = parse_body(StatusCode::BAD_GATEWAY, Some("text/html"), html).unwrap_err();
+    /// assert!(matches!(e, TranslateError::Http { status: StatusCode::BAD_GATEWAY, .. }));
+    /// assert_eq!(e.code(), "502");
+    /// assert!(e.is_retryable());
+    /// let body = "x".repeat(2000);
+    /// let e = parse_body(StatusCode::NOT_FOUND, None, body.as_bytes()).unwrap_err();
+    /// let TranslateError::Http { body_snippet, .. } = &e else { unreachable!() };
+    /// assert_eq!(body_snippet.len(), 503);
+    /// assert!(!e.is_retryable());
+    ///
+    /// let e = parse_body(StatusCode::OK, Some("application/json"), br#"{"from": "en""#)
+    ///     .unwrap_err();
+    /// assert!(e.to_string().contains(r#"{"from": "en""#));
+    ///
+    /// let object = parse_body(StatusCode::OK, None, br#"{"from": "en"}"#).unwrap();
+    /// assert_eq!(object["from"], "en");
+    /// ```
+    pub fn parse_body(
+        status: StatusCode,
+        content_type: Option<&str>,
+        body: &[u8],
+    ) -> Result<JSONData, TranslateError> {
+        if !status.is_success() {
+            return Err(TranslateError::Http {
+                status,
+                body_snippet: body_snippet(body),
+            });
+        }
+        let object = serde_json::from_slice::<JSONData>(body).map_err(|e| {
+            TranslateError::Response(format!(
+                "data to json error: {e}, content-type: {}, body: {}",
+                content_type.unwrap_or("none"),
+                body_snippet(body),
+            ))
+        })?;
+        match ApiError::from_json(&object) {
+            Some(e) => Err(TranslateError::ApiError(e, object.into())),
+            None => Ok(object),
+        }
+    }
+
+    /// 发送翻译请求并解析响应, 返回响应与重试次数, 成功时记录到 `stats`
+    async fn send_translate(
+        client: &Client,
+        headers: &HeaderMap,
+        payload: &JSONData,
+        stats: Option<&Mutex<Stats>>,
+        log: Option<&CallLog>,
+        progress: impl Fn(TranslateProgress),
+    ) -> Result<(JSONData, u32), TranslateError> {
+        let start = Instant::now();
+        progress(TranslateProgress::Connecting);
+        let (result, retries) = post_retry_with(client, headers, URL, payload).await?;
+        progress(TranslateProgress::Waiting);
+        let (status, content_type) = response_meta(&result);
+        let body = result.bytes().await.map_err(TranslateError::Request)?;
+        progress(TranslateProgress::Parsing);
+        let data = parse_body(status, content_type.as_deref(), &body);
+        progress(TranslateProgress::Done);
+        let q = payload.get("q").and_then(|q| q.as_str());
+        if let (Some(stats), Some(q), Ok(_)) = (stats, q, &data) {
+            stats.lock().unwrap().record_request(q, retries);
+        }
+        if let Some(log) = log {
+            let get = |key| payload.get(key).and_then(Value::as_str).unwrap_or_default();
+            let dst_chars = data.as_ref().ok()
+                .and_then(|object| TransResult::from_json(object).ok())
+                .map_or(0, |result| {
+                    result.lines.iter().map(|line| line.dst.chars().count()).sum()
+                });
+            let api_error_code = match &data {
+                Err(TranslateError::ApiError(e, _)) => Some(e.error_code()),
+                _ => None,
+            };
+            let entry = LogEntry::now(
+                get("from"),
+                get("to"),
+                get("q").chars().count(),
+                dst_chars,
+                start.elapsed(),
+                api_error_code,
+            );
+            // 日志写入失败不影响翻译
+            let _ = log.record(&entry);
+        }
+        data.map(|data| (data, retries))
+    }
+
+
+    /// 翻译错误
+    #[derive(Debug)]
+    pub enum TranslateError {
+        /// 超时次数达到上限
+        Timeout(u32),
+        /// 请求错误次数达到上限
+        Request(RError),
+        /// 响应数据错误
+        Response(String),
+        /// 接口返回的错误码, 及完整的响应数据
+        ApiError(ApiError, Box<JSONData>),
+        /// 响应状态不为 2xx
+        Http { status: StatusCode, body_snippet: String },
+        /// 写出结果错误
+        Io(std::io::Error),
+    }
+    impl Display for TranslateError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(&i18n::translate_error(Lang::En, self))
+        }
+    }
+    impl std::error::Error for TranslateError {}
+    impl From<FormatError> for TranslateError {
+        fn from(e: FormatError) -> Self {
+            Self::Response(e.to_string())
+        }
+    }
+    impl TranslateError {
+        /// 用于错误标记的简短错误码
+        pub fn code(&self) -> String {
+            match self {
+                Self::Timeout(_) => "timeout".into(),
+                Self::Request(e) => e.status()
+                    .map_or_else(|| "request".into(), |s| s.as_u16().to_string()),
+                Self::Response(_) => "response".into(),
+                Self::ApiError(e, _) => e.error_code().to_string(),
+                Self::Http { status, .. } => status.as_u16().to_string(),
+                Self::Io(_) => "io".into(),
+            }
+        }
+
+        /// 稍后重试是否可能成功, 网络错误, 5xx 与部分接口错误码可重试
+        pub fn is_retryable(&self) -> bool {
+            match self {
+                Self::Timeout(_) | Self::Request(_) => true,
+                Self::Http { status, .. } => should_retry(*status),
+                Self::ApiError(e, _) => e.is_retryable(),
+                Self::Response(_) | Self::Io(_) => false,
+            }
+        }
+
+        /// 接口报错的同时返回的部分翻译结果
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::translater::{ApiError, TranslateError};
+        /// let object: baidu_fanyi::response::JSONData = serde_json::from_str(r#"{
+        ///     "error_code": "54003",
+        ///     "error_msg": "Invalid Access Limit",
+        ///     "from": "en",
+        ///     "to": "zh",
+        ///     "trans_result": [{"src": "one", "dst": "一"}]
+        /// }"#).unwrap();
+        /// let e = ApiError::from_json(&object).unwrap();
+        /// let e = TranslateError::ApiError(e, object.into());
+        /// assert_eq!(e.partial().unwrap().lines[0].dst, "一");
+        ///
+        /// let object = serde_json::from_str(r#"{
+        ///     "error_code": "54001",
+        ///     "error_msg": "Invalid Sign"
+        /// }"#).unwrap();
+        /// let e = TranslateError::ApiError(ApiError::InvalidSign, object);
+        /// assert!(e.partial().is_none());
+        /// ```
+        pub fn partial(&self) -> Option<TransResult> {
+            match self {
+                Self::ApiError(_, body) => TransResult::from_json(body).ok(),
+                _ => None,
+            }
+        }
+    }
+
+    /// 接口返回的错误码, 见 <https://fanyi-api.baidu.com/doc/21>
+    ///
+    /// 可重试的错误码: 52001, 52002, 54003, 54005,
+    /// 其余错误需要修改请求或账户设置后才能成功
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum ApiError {
+        /// 52001 请求超时, 可重试
+        RequestTimeout,
+        /// 52002 系统错误, 可重试
+        SystemError,
+        /// 52003 未授权用户, 检查 appid 是否正确或服务是否开通
+        Unauthorized,
+        /// 54000 必填参数为空
+        MissingParams,
+        /// 54001 签名错误, 检查 appid 与密钥
+        InvalidSign,
+        /// 54003 访问频率受限, 降低频率后可重试
+        FrequencyLimited,
+        /// 54004 账户余额不足
+        InsufficientBalance,
+        /// 54005 长 query 请求频繁, 降低频率后可重试
+        LongQueryTooFrequent,
+        /// 58000 客户端 IP 非法, 检查控制台中填写的 IP
+        IllegalIp,
+        /// 58001 译文语言方向不支持
+        UnsupportedLang,
+        /// 58002 服务当前已关闭
+        ServiceClosed,
+        /// 未列出的错误码
+        Unknown { code: i64, message: String },
+    }
+    impl ApiError {
+        /// 从错误码与错误信息构建
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::translater::ApiError;
+        /// assert_eq!(ApiError::from_code(54001, "Invalid Sign"), ApiError::InvalidSign);
+        /// assert_eq!(
+        ///     ApiError::from_code(90107, "unknown"),
+        ///     ApiError::Unknown { code: 90107, message: "unknown".into() },
+        /// );
+        /// assert_eq!(ApiError::from_code(90107, "").error_code(), 90107);
+        /// ```
+        pub fn from_code(code: i64, message: &str) -> Self {
+            match code {
+                52001 => Self::RequestTimeout,
+                52002 => Self::SystemError,
+                52003 => Self::Unauthorized,
+                54000 => Self::MissingParams,
+                54001 => Self::InvalidSign,
+                54003 => Self::FrequencyLimited,
+                54004 => Self::InsufficientBalance,
+                54005 => Self::LongQueryTooFrequent,
+                58000 => Self::IllegalIp,
+                58001 => Self::UnsupportedLang,
+                58002 => Self::ServiceClosed,
+                code => Self::Unknown { code, message: message.into() },
+            }
+        }
+
+        /// 从返回的 json 数据中取出错误, 没有错误码或为成功码时返回 `None`
+        ///
+        /// 错误码可能为字符串或数字
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::translater::ApiError;
+        /// let object = serde_json::from_str(r#"{
+        ///     "error_code": "54003",
+        ///     "error_msg": "Invalid Access Limit"
+        /// }"#).unwrap();
+        /// let e = ApiError::from_json(&object).unwrap();
+        /// assert_eq!(e, ApiError::FrequencyLimited);
+        /// assert!(e.is_retryable());
+        ///
+        /// let object = serde_json::from_str(r#"{"error_code": 0}"#).unwrap();
+        /// assert_eq!(ApiError::from_json(&object), None);
+        /// let object = serde_json::from_str(r#"{"from": "en"}"#).unwrap();
+        /// assert_eq!(ApiError::from_json(&object), None);
+        /// ```
+        pub fn from_json(object: &JSONData) -> Option<Self> {
+            let code = match object.get("error_code")? {
+                Value::String(code) => code.parse().ok()?,
+                code => code.as_i64()?,
+            };
+            if code == 0 || code == SUCCESS_CODE {
+                return None;
+            }
+            let message = object.get("error_msg")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            Some(Self::from_code(code, message))
+        }
+
+        /// 接口错误码
+        pub fn error_code(&self) -> i64 {
+            match self {
+                Self::RequestTimeout => 52001,
+                Self::SystemError => 52002,
+                Self::Unauthorized => 52003,
+                Self::MissingParams => 54000,
+                Self::InvalidSign => 54001,
+                Self::FrequencyLimited => 54003,
+                Self::InsufficientBalance => 54004,
+                Self::LongQueryTooFrequent => 54005,
+                Self::IllegalIp => 58000,
+                Self::UnsupportedLang => 58001,
+                Self::ServiceClosed => 58002,
+                Self::Unknown { code, .. } => *code,
+            }
+        }
+
+        /// 错误码对应的错误信息, 未列出的错误码为接口返回的信息
+        pub fn message(&self) -> &str {
+            match self {
+                Self::RequestTimeout => "Request Timeout",
+                Self::SystemError => "System Error",
+                Self::Unauthorized => "Unauthorized User",
+                Self::MissingParams => "Required Params Missing",
+                Self::InvalidSign => "Invalid Sign",
+                Self::FrequencyLimited => "Invalid Access Limit",
+                Self::InsufficientBalance => "Insufficient Balance",
+                Self::LongQueryTooFrequent => "Long Query Too Frequent",
+                Self::IllegalIp => "Invalid Client IP",
+                Self::UnsupportedLang => "Language Not Supported",
+                Self::ServiceClosed => "Service Closed",
+                Self::Unknown { message, .. } => message,
+            }
+        }
+
+        /// 稍后重试是否可能成功
+        pub fn is_retryable(&self) -> bool {
+            matches!(self,
+                | Self::RequestTimeout
+                | Self::SystemError
+                | Self::FrequencyLimited
+                | Self::LongQueryTooFrequent)
+        }
+    }
+    impl Display for ApiError {
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::translater::ApiError;
+        /// assert_eq!(
+        ///     ApiError::InvalidSign.to_string(),
+        ///     "API error 54001 (Invalid Sign): check that your appkey matches the appid",
+        /// );
+        /// assert_eq!(
+        ///     ApiError::from_code(90107, "Unknown Error").to_string(),
+        ///     "API error 90107 (Unknown Error)",
+        /// );
+        /// ```
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(&i18n::api_error(Lang::En, self))
+        }
+    }
+    impl std::error::Error for ApiError {}
+
+    /// 自定义请求头错误
+    #[derive(Debug)]
+    pub enum HeaderError {
+        /// `Content-Type` 由表单编码决定, 不可覆盖
+        ContentType,
+        InvalidName(String),
+        InvalidValue(String),
+        /// 构建 [`Client`] 失败
+        Client(RError),
+    }
+    impl Display for HeaderError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::ContentType => write!(f, "can not override Content-Type, \
+                    requests are always sent as url encoded form"),
+                Self::InvalidName(name) => write!(f, "invalid header name {name:?}"),
+                Self::InvalidValue(value) => {
+                    write!(f, "invalid header value {value:?}")
+                },
+                Self::Client(e) => write!(f, "build client error: {e}"),
+            }
+        }
+    }
+    impl std::error::Error for HeaderError {}
+
+    /// 检查并转换自定义请求头
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::{check_header, HeaderError};
+    /// let (name, value) = check_header("X-Org-Token", "abc").unwrap();
+    /// assert_eq!(name, "x-org-token");
+    /// assert_eq!(value, "abc");
+    /// assert!(matches!(
+    ///     check_header("content-type", "text/plain"),
+    ///     Err(HeaderError::ContentType),
+    /// ));
+    /// assert!(matches!(
+    ///     check_header("bad name", "x"),
+    ///     Err(HeaderError::InvalidName(_)),
+    /// ));
+    /// assert!(matches!(
+    ///     check_header("X-A", "a\nb"),
+    ///     Err(HeaderError::InvalidValue(_)),
+    /// ));
+    /// ```
+    pub fn check_header(
+        name: &str,
+        value: &str,
+    ) -> Result<(HeaderName, HeaderValue), HeaderError> {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| HeaderError::InvalidName(name.into()))?;
+        if name == CONTENT_TYPE {
+            return Err(HeaderError::ContentType);
+        }
+        let value = HeaderValue::from_str(value)
+            .map_err(|_| HeaderError::InvalidValue(value.into()))?;
+        Ok((name, value))
+    }
+
+    /// 构建带有自定义请求头的 [`Translater`]
+    /// # Examples
+    /// ```
+    /// use baidu_fanyi::translater::Translater;
+    ///
+    /// let translater = Translater::builder("id", "key")
+    ///     .header("X-Org-Token", "secret").unwrap()
+    ///     .user_agent("my-app/1.0").unwrap()
+    ///     .build().unwrap();
+    /// assert!(Translater::builder("id", "key").header("X-Bad", "a\nb").is_err());
+    /// ```
+    #[derive(Debug, Clone)]
+    pub struct TranslaterBuilder<'a> {
+        id: &'a str,
+        key: &'a str,
+        headers: HeaderMap,
+        context: Option<String>,
+    }
+    impl<'a> TranslaterBuilder<'a> {
+        pub fn new(id: &'a str, key: &'a str) -> Self {
+            Self { id, key, headers: HeaderMap::new(), context: None }
+        }
+
+        /// 添加请求头, 同名时覆盖
+        pub fn header(
+            &mut self,
+            name: &str,
+            value: &str,
+        ) -> Result<&mut Self, HeaderError> {
+            let (name, value) = check_header(name, value)?;
+            self.headers.insert(name, value);
+            Ok(self)
+        }
+
+        pub fn user_agent(&mut self, ua: &str) -> Result<&mut Self, HeaderError> {
+            self.header(USER_AGENT.as_str(), ua)
+        }
+
+        /// 同 [`Translater::with_context`]
+        pub fn with_context(&mut self, ctx: String) -> &mut Self {
+            self.context = Some(ctx);
+            self
+        }
+
+        pub fn build(&self) -> Result<Translater<'a>, HeaderError> {
+            let client = Client::builder()
+                .user_agent(DEFAULT_USER_AGENT)
+                .default_headers(self.headers.clone())
+                .build()
+                .map_err(HeaderError::Client)?;
+            let mut translater = Translater::new_with_client(self.id, self.key, client);
+            translater.context.clone_from(&self.context);
+            Ok(translater)
+        }
+    }
+
+    /// 同 [`Client::new`], 但 `User-Agent` 为 [`DEFAULT_USER_AGENT`]
+    /// # Panics
+    /// 同 [`Client::new`], 无法初始化 TLS 时 panic
+    fn default_client() -> Client {
+        Client::builder()
+            .user_agent(DEFAULT_USER_AGENT)
+            .build()
+            .expect("TLS backend cannot be initialized")
+    }
+
+    /// 单次翻译的进度
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TranslateProgress {
+        /// 发送请求中
+        Connecting,
+        /// 已收到响应头, 等待响应体
+        Waiting,
+        /// 解析响应数据
+        Parsing,
+        Done,
+    }
+    impl Display for TranslateProgress {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(match self {
+                Self::Connecting => "connecting",
+                Self::Waiting => "waiting",
+                Self::Parsing => "parsing",
+                Self::Done => "done",
+            })
+        }
+    }
+
+    /// 单次翻译的耗时与用量, 可由调用者跨多次调用汇总
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct TranslateMeta {
+        /// 从构建请求到解析出结果的耗时
+        pub latency: Duration,
+        /// 作为 `q` 发送的字符数, 即计费字符数
+        pub src_chars: usize,
+        /// 所有行译文的字符数
+        pub dst_chars: usize,
+        /// 重试次数
+        pub retries: u32,
+        /// 是否命中翻译缓存, 当前版本没有缓存, 总为 `false`
+        pub cache_hit: bool,
+    }
+
+    /// 代替本地计算签名的回调, 见 [`Translater::with_sign_callback`]
+    type SignCallback = Arc<dyn Fn(&str, &str) -> String + Send + Sync>;
+    /// 生成盐值后的回调, 见 [`Translater::with_salt_callback`]
+    type SaltCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+    #[derive(Clone)]
+    pub struct Translater<'a> {
+        id: &'a str,
+        key: &'a str,
+        salt: String,
+        salt_source: SaltSource,
+        from_lang: &'a str,
+        to_lang: &'a str,
+        /// 由 [`Translater::lock_from_lang`] 锁定的源语言, 克隆间共享
+        locked_from: Arc<OnceLock<String>>,
+        client: Client,
+        stats: Option<Arc<Mutex<Stats>>>,
+        log: Option<Arc<CallLog>>,
+        /// 作为 `context` 发送的上下文
+        context: Option<String>,
+        sign_callback: Option<SignCallback>,
+        salt_callback: Option<SaltCallback>,
+        /// 每个请求另外带有的请求头, 如 [`Translater::with_user_agent`]
+        headers: HeaderMap,
+    }
+    impl<'a> Translater<'a> {
+        pub fn new(id: &'a str, key: &'a str) -> Self {
+            Self::new_with_client(id, key, default_client())
+        }
+
+        /// 之后的请求使用 `ua` 作为 `User-Agent`,
+        /// 保留 [`Client`] 原有的代理等配置与其它默认请求头
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::translater::{HeaderError, Translater};
+        /// let translater = Translater::new("id", "key").with_user_agent("my-app/1.0");
+        /// assert!(translater.is_ok());
+        /// let err = Translater::new("id", "key").with_user_agent("a\nb").err().unwrap();
+        /// assert!(matches!(err, HeaderError::InvalidValue(_)));
+        /// ```
+        pub fn with_user_agent(mut self, ua: &str) -> Result<Self, HeaderError> {
+            let (name, value) = check_header(USER_AGENT.as_str(), ua)?;
+            self.headers.insert(name, value);
+            Ok(self)
+        }
+
+        /// 同 [`TranslaterBuilder::new`]
+        pub fn builder(id: &'a str, key: &'a str) -> TranslaterBuilder<'a> {
+            TranslaterBuilder::new(id, key)
+        }
+
+        /// 发送请求所使用的 [`Client`]
+        pub fn client(&self) -> &Client {
+            &self.client
+        }
+
+        /// 同 [`crate::version`]
+        pub fn crate_version() -> &'static str {
+            crate::version()
+        }
+
+        /// 使用自定义的 [`Client`] 发送请求, 可用于配置代理, TLS 等
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::translater::Translater;
+        /// let client = reqwest::Client::builder()
+        ///     .user_agent("my-app/1.0")
+        ///     .build()
+        ///     .unwrap();
+        /// let translater = Translater::new_with_client("id", "key", client);
+        /// assert_eq!(translater.from_lang(), "auto");
+        /// ```
+        pub fn new_with_client(id: &'a str, key: &'a str, client: Client) -> Self {
+            Self {
+                id,
+                key,
+                salt: String::new(),
+                salt_source: SaltSource::Random,
+                from_lang: DEFAULT_FROM_LANG,
+                to_lang: DEFAULT_TO_LANG,
+                locked_from: Arc::default(),
+                client,
+                stats: None,
+                log: None,
+                context: None,
+                sign_callback: None,
+                salt_callback: None,
+                headers: HeaderMap::new(),
+            }
+        }
+
+        /// 将每次请求记录到 `stats` 中
+        pub fn set_stats(&mut self, stats: Arc<Mutex<Stats>>) -> &mut Self {
+            self.stats = Some(stats);
+            self
+        }
+
+        /// 将每次翻译接口调用写入 `log`
+        pub fn set_log(&mut self, log: Arc<CallLog>) -> &mut Self {
+            self.log = Some(log);
+            self
+        }
+
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::translater::Translater;
+        /// let mut translater = Translater::new("id", "key");
+        /// translater.set_from_lang("zh").set_to_lang("en");
+        /// assert_eq!(translater.from_lang(), "zh");
+        /// assert_eq!(translater.to_lang(), "en");
+        /// ```
+        pub fn set_from_lang(&mut self, from: &'a str) -> &mut Self {
+            self.from_lang = from;
+            self
+        }
+
+        pub fn set_to_lang(&mut self, to: &'a str) -> &mut Self {
+            self.to_lang = to;
+            self
+        }
+
+        /// 更新盐值
+        pub fn update_salt(&mut self) {
+            self.salt = self.salt_source.next();
+            if let Some(callback) = &self.salt_callback {
+                callback(&self.salt)
+            }
+        }
+
+        /// 每次 [`Translater::update_salt`] 后以新的盐值调用 `f`,
+        /// 即每个请求发送前调用一次, 如输出盐值以便复现签名问题
+        /// # Examples
+        /// ```
+        /// use std::sync::{Arc, Mutex};
+        /// use baidu_fanyi::translater::{SaltSource, Translater};
+        ///
+        /// let salts = Arc::new(Mutex::new(vec![]));
+        /// let log = Arc::clone(&salts);
+        /// let mut translater = Translater::new("id", "key")
+        ///     .with_salt_callback(move |salt| log.lock().unwrap().push(salt.to_owned()));
+        /// translater.set_salt_source(SaltSource::Fixed("42".into()));
+        /// translater.update_salt();
+        /// assert_eq!(*salts.lock().unwrap(), ["42"]);
+        /// ```
+        pub fn with_salt_callback<F>(mut self, f: F) -> Self
+            where F: Fn(&str) + Send + Sync + 'static,
+        {
+            self.salt_callback = Some(Arc::new(f));
+            self
+        }
+
+        /// 设置之后请求使用的盐值来源
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::translater::{SaltSource, Translater};
+        /// let mut translater = Translater::new("id", "key");
+        /// translater.set_salt_source(SaltSource::Fixed("0042".into()));
+        /// translater.update_salt();
+        /// let payload = translater.build_payload("你好".into());
+        /// assert_eq!(payload["salt"], "0042");
+        /// let sign = format!("{:x}", md5::compute("id你好0042key"));
+        /// assert_eq!(payload["sign"], sign);
+        /// ```
+        pub fn set_salt_source(&mut self, source: SaltSource) -> &mut Self {
+            self.salt_source = source;
+            self
+        }
+
+        pub fn salt_source(&self) -> &SaltSource {
+            &self.salt_source
+        }
+
+        /// 固定使用 `salt` 作为盐值, 使签名可以复现,
+        /// 不在 [`SALT_MIN`] 到 [`SALT_MAX`] 间的盐值见 [`Translater::set_salt_source`]
+        /// # Panics
+        /// `salt` 小于 [`SALT_MIN`] 时 panic
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::translater::Translater;
+        /// let translater = Translater::new("id", "key").with_salt(32768);
+        /// assert_eq!(translater.get_sign("hello"), "a92fb19edfaf18833360a2ce0916e893");
+        /// let payload = translater.build_payload("hello".into());
+        /// assert_eq!(payload["salt"], "32768");
+        ///
+        /// let translater = Translater::new("id", "key").with_salt(65535);
+        /// assert_eq!(translater.get_sign("hello"), "12ecbae2bb7a955bc9fb5dc8c7ba5f0b");
+        /// ```
+        /// ```should_panic
+        /// baidu_fanyi::translater::Translater::new("id", "key").with_salt(42);
+        /// ```
+        pub fn with_salt(mut self, salt: u16) -> Self {
+            assert!(u32::from(salt) >= SALT_MIN, "salt {salt} is less than {SALT_MIN}");
+            self.salt_source = SaltSource::Fixed(salt.to_string());
+            self.update_salt();
+            self
+        }
+
+        /// 每次请求以 `context` 参数发送上下文, 帮助翻译有歧义的文本,
+        /// 上下文应与原文为同一语言. 不支持该参数的接口版本会忽略它,
+        /// 上下文不参与签名
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::translater::Translater;
+        /// let translater = Translater::new("id", "key").with_salt(32768);
+        /// assert!(!translater.build_payload("bank".into()).contains_key("context"));
+        ///
+        /// let translater = translater.with_context("river and fishing".into());
+        /// assert_eq!(translater.context(), Some("river and fishing"));
+        /// let payload = translater.build_payload("bank".into());
+        /// assert_eq!(payload["context"], "river and fishing");
+        /// assert_eq!(payload["sign"], translater.get_sign("bank"));
+        ///
+        /// let mut builder = Translater::builder("id", "key");
+        /// builder.with_context("金融".into());
+        /// let translater = builder.build().unwrap();
+        /// assert_eq!(translater.build_payload("银行".into())["context"], "金融");
+        /// ```
+        pub fn with_context(mut self, ctx: String) -> Self {
+            self.context = Some(ctx);
+            self
+        }
+
+        pub fn context(&self) -> Option<&str> {
+            self.context.as_deref()
+        }
+
+        /// 构建请求荷载
+        /// 'appid': appid,
+        /// 'q': query,
+        /// 'from': from_lang,
+        /// 'to': to_lang,
+        /// 'salt': salt,
+        /// 'sign': sign,
+        /// 'context': context, 仅在设置了上下文时, 见 [`Translater::with_context`]
+        ///
+        /// 发送的表单中的 `q` 与签名所用的文本逐字节相同
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::translater::Translater;
+        /// let mut translater = Translater::new("appid", "key");
+        /// translater.update_salt();
+        /// let payload = translater.build_payload("a&b=c".into());
+        /// let salt = payload["salt"].as_str().unwrap();
+        /// let sign = format!("{:x}", md5::compute(format!("appida&b=c{salt}key")));
+        /// assert_eq!(payload["q"], "a&b=c");
+        /// assert_eq!(payload["sign"], sign.as_str());
+        /// assert!(!payload.contains_key("context"));
+        /// ```
+        pub fn build_payload(&self, message: String) -> JSONData {
+            const KEY_COUNT: usize = 7;
+            let sign = self.get_sign(&message); // 初始化签名
+            let mut data = JSONData::with_capacity(KEY_COUNT);
+            debug_assert!(data.capacity() >= KEY_COUNT); // 可能分配更多
+
+            data.insert("appid".to_string(), self.id.into());
+            data.insert("q".into(), message.into());
+            data.insert("from".into(), self.from_lang().into());
+            data.insert("to".into(), self.to_lang.into());
+            data.insert("salt".into(), self.salt.clone().into());
+            data.insert("sign".into(), sign.into());
+            if let Some(context) = &self.context {
+                data.insert("context".into(), context.as_str().into());
+            }
+            data
+        }
+
+        /// 请求翻译
+        /// 复制一份 Translater 进行配置获取
+        pub async fn translate(
+            &self,
+            message: String,
+        ) -> Result<JSONData, TranslateError> {
+            self.translate_progress(message, |_| ()).await
+        }
+
+        /// 以 `context` 作为本次请求的上下文请求翻译, 见 [`Translater::with_context`]
+        pub async fn translate_with_context(
+            &self,
+            context: &str,
+            text: String,
+        ) -> Result<JSONData, TranslateError> {
+            self.clone().with_context(context.into()).translate(text).await
+        }
+
+        /// 请求翻译, 并将进度发送到 `tx`
+        pub async fn translate_with_watch(
+            &self,
+            text: String,
+            tx: watch::Sender<TranslateProgress>,
+        ) -> Result<JSONData, TranslateError> {
+            self.translate_progress(text, |progress| {
+                tx.send_replace(progress);
+            }).await
+        }
+
+        async fn translate_progress(
+            &self,
+            message: String,
+            progress: impl Fn(TranslateProgress),
+        ) -> Result<JSONData, TranslateError> {
+            let mut this = self.clone();
+            this.update_salt(); // 需要先初始化盐值
+            let payload: JSONData = this.build_payload(message);
+            send_translate(
+                &self.client,
+                &self.headers,
+                &payload,
+                self.stats.as_deref(),
+                self.log.as_deref(),
+                progress,
+            ).await.map(|(data, _)| data)
+        }
+
+        /// 请求翻译, 并返回本次翻译的耗时与用量,
+        /// 不需要 [`Translater::set_stats`]
+        /// # Examples
+        /// ```no_run
+        /// use baidu_fanyi::translater::Translater;
+        /// let translater = Translater::new("id", "key");
+        /// let (result, meta) = tokio::runtime::Runtime::new().unwrap()
+        ///     .block_on(translater.translate_with_metadata("你好".into()))
+        ///     .unwrap();
+        /// println!("{} in {:?}", result.lines[0].dst, meta.latency);
+        /// assert!(!meta.cache_hit);
+        /// ```
+        pub async fn translate_with_metadata(
+            &self,
+            text: String,
+        ) -> Result<(TransResult, TranslateMeta), TranslateError> {
+            let start = Instant::now();
+            let src_chars = text.chars().count();
+            let mut this = self.clone();
+            this.update_salt();
+            let payload = this.build_payload(text);
+            let (object, retries) = send_translate(
+                &self.client,
+                &self.headers,
+                &payload,
+                self.stats.as_deref(),
+                self.log.as_deref(),
+                |_| (),
+            ).await?;
+            let result = TransResult::from_json(&object)?;
+            let meta = TranslateMeta {
+                latency: start.elapsed(),
+                src_chars,
+                dst_chars: result.lines.iter().map(|line| line.dst.chars().count()).sum(),
+                retries,
+                cache_hit: false,
+            };
+            Ok((result, meta))
+        }
+
+        /// 并发翻译多条文本, 最多同时发送 `concurrency` 个请求,
+        /// 结果顺序与输入顺序一致, 任一请求失败时返回该错误
+        /// # Examples
+        /// ```no_run
+        /// # async fn f() {
+        /// use baidu_fanyi::translater::Translater;
+        /// let translater = Translater::new("id", "key");
+        /// let messages = ["一", "二", "三"].map(String::from).to_vec();
+        /// let results = translater
+        ///     .translate_batch_ordered(messages, 4)
+        ///     .await
+        ///     .unwrap();
+        /// assert_eq!(results[0].lines[0].src, "一");
+        /// assert_eq!(results[2].lines[0].src, "三");
+        /// # }
+        /// ```
+        pub async fn translate_batch_ordered(
+            &self,
+            messages: Vec<String>,
+            concurrency: usize,
+        ) -> Result<Vec<TransResult>, TranslateError> {
+            let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+            let mut tasks = JoinSet::new();
+            let count = messages.len();
+            for (index, message) in messages.into_iter().enumerate() {
+                let mut this = self.clone();
+                this.update_salt();
+                let payload = this.build_payload(message);
+                let Translater { client, headers, stats, log, .. } = this;
+                let semaphore = semaphore.clone();
+                tasks.spawn(async move {
+                    let _permit = semaphore.acquire_owned().await
+                        .expect("semaphore closed");
+                    let data = send_translate(
+                        &client,
+                        &headers,
+                        &payload,
+                        stats.as_deref(),
+                        log.as_deref(),
+                        |_| (),
+                    ).await;
+                    (index, data)
+                });
+            }
+            let mut results: Vec<Option<TransResult>> = vec![None; count];
+            while let Some(joined) = tasks.join_next().await {
+                let (index, data) = joined.expect("translate task panicked");
+                let (data, _) = data?;
+                let result = TransResult::from_json(&data)?;
+                results[index] = Some(result);
+            }
+            Ok(results.into_iter()
+                .map(|result| result.expect("all tasks joined"))
+                .collect())
+        }
+
+        /// 请求语种识别, 返回识别出的语言代码
+        pub async fn detect(&self, text: &str) -> Result<String, TranslateError> {
+            let mut this = self.clone();
+            this.update_salt();
+            let mut payload = JSONData::with_capacity(4);
+            payload.insert("appid".into(), this.id.into());
+            payload.insert("q".into(), text.into());
+            payload.insert("salt".into(), this.salt.clone().into());
+            payload.insert("sign".into(), this.get_sign(text).into());
+            let (result, _) = post_retry_with(&self.client, &self.headers, DETECT_URL, &payload)
+                .await?;
+            let (status, content_type) = response_meta(&result);
+            let body = result.bytes().await.map_err(TranslateError::Request)?;
+            let object = parse_body(status, content_type.as_deref(), &body)?;
+            detected_lang(&object).map_err(TranslateError::Response)
+        }
+
+        /// 请求翻译, 并对每行的 src 和 dst 进行单词级的对比
+        pub async fn translate_diff(
+            &self,
+            text: &str,
+        ) -> Result<TransDiff, TranslateError> {
+            let object = self.translate(text.into()).await?;
+            let result = TransResult::from_json(&object)?;
+            Ok(TransDiff::from_result(&result))
+        }
+
+        /// 翻译完整的 HTML 文档, 保留标签, 注释, 脚本与空白,
+        /// 所有文本节点, `<title>`, `alt` 与 `<meta name="description">`
+        /// 按行合并为尽量少的请求
+        /// # Examples
+        /// ```no_run
+        /// use baidu_fanyi::translater::Translater;
+        /// let translater = Translater::new("id", "key");
+        /// let out = tokio::runtime::Runtime::new().unwrap()
+        ///     .block_on(translater.translate_html_document("<p>Hello</p>"))
+        ///     .unwrap();
+        /// println!("{out}");
+        /// ```
+        pub async fn translate_html_document(
+            &self,
+            html: &str,
+        ) -> Result<String, TranslateError> {
+            let doc = Document::parse(html);
+            let texts = doc.texts();
+            if texts.is_empty() {
+                return Ok(html.into());
+            }
+            let joined = texts.join("\n");
+            let mut dsts = Vec::with_capacity(texts.len());
+            for block in split_text_blocks(&joined) {
+                let block = block.strip_suffix('\n').unwrap_or(block);
+                let object = self.translate(block.into()).await?;
+                let result = TransResult::from_json(&object)?;
+                dsts.extend(result.lines.into_iter().map(|line| line.dst));
+            }
+            if dsts.len() != texts.len() {
+                return Err(TranslateError::Response(format!(
+                    "expected {} translated lines, got {}",
+                    texts.len(),
+                    dsts.len(),
+                )));
+            }
+            Ok(doc.render(&dsts))
+        }
+
+        /// 构建 md5 签名, 官方示例组合方式为
+        /// appid + query + salt + appkey
+        /// salt 为一个 [32768,65536] 区间的整数字符串, 不进行定长
+        ///
+        /// 设置了 [`Translater::with_sign_callback`] 时由回调生成
+        pub fn get_sign(&self, message: &str) -> String {
+            if let Some(callback) = &self.sign_callback {
+                return callback(message, &self.salt);
+            }
+            let strs: [&str; 4] = [self.id, message, &self.salt, self.key];
+            format!("{:x}", make_md5(&strs.concat()))
+        }
+
+        /// 以 `f(query, salt)` 代替本地计算签名, 如由服务端持有 appkey 并生成签名,
+        /// `query` 与 `salt` 为签名公式中的值, 即发送的 `q` 与 `salt`
+        /// # Examples
+        /// ```
+        /// use std::sync::{Arc, Mutex};
+        /// use baidu_fanyi::translater::Translater;
+        ///
+        /// let signed = Arc::new(Mutex::new(vec![]));
+        /// let log = Arc::clone(&signed);
+        /// let mut translater = Translater::new("id", "")
+        ///     .with_sign_callback(move |query, salt| {
+        ///         log.lock().unwrap().push(format!("{query}:{salt}"));
+        ///         format!("{:x}", md5::compute(format!("id{query}{salt}server-key")))
+        ///     });
+        /// translater.update_salt();
+        /// let payload = translater.build_payload("hello".into());
+        /// let salt = payload["salt"].as_str().unwrap();
+        /// let sign = format!("{:x}", md5::compute(format!("idhello{salt}server-key")));
+        /// assert_eq!(payload["sign"], sign.as_str());
+        /// assert_eq!(*signed.lock().unwrap(), [format!("hello:{salt}")]);
+        ///
+        /// // 克隆共享回调
+        /// let cloned = translater.clone();
+        /// cloned.get_sign("world");
+        /// assert_eq!(signed.lock().unwrap()[1], format!("world:{salt}"));
+        /// ```
+        pub fn with_sign_callback<F>(mut self, f: F) -> Self
+            where F: Fn(&str, &str) -> String + Send + Sync + 'static,
+        {
+            self.sign_callback = Some(Arc::new(f));
+            self
+        }
+
+        /// 源语言, 被锁定时为锁定的语言
+        pub fn from_lang(&self) -> &str {
+            self.locked_from.get().map_or(self.from_lang, String::as_str)
+        }
+
+        /// 将之后所有请求的源语言锁定为 `lang`, 通常为首次请求检测出的语言,
+        /// 锁定对所有克隆生效, 已锁定时不做修改并返回 `false`,
+        /// 可用 [`Translater::unlock_from_lang`] 使克隆单独锁定
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::translater::Translater;
+        /// let translater = Translater::new("id", "key");
+        /// let cloned = translater.clone();
+        /// assert_eq!(translater.build_payload("a".into())["from"], "auto");
+        /// assert!(translater.lock_from_lang("zh"));
+        /// assert!(!translater.lock_from_lang("en"));
+        /// assert_eq!(cloned.from_lang(), "zh");
+        /// assert_eq!(cloned.build_payload("a".into())["from"], "zh");
+        /// ```
+        pub fn lock_from_lang(&self, lang: &str) -> bool {
+            self.locked_from.set(lang.into()).is_ok()
+        }
+
+        /// 使此翻译器不再共享之前的锁定, 之后可以单独锁定, 其它克隆不受影响
+        /// # Examples
+        /// ```
+        /// use baidu_fanyi::translater::Translater;
+        /// let translater = Translater::new("id", "key");
+        /// assert!(translater.lock_from_lang("zh"));
+        /// let mut other = translater.clone();
+        /// other.unlock_from_lang();
+        /// assert_eq!(other.from_lang(), "auto");
+        /// assert!(other.lock_from_lang("en"));
+        /// assert_eq!((translater.from_lang(), other.from_lang()), ("zh", "en"));
+        /// ```
+        pub fn unlock_from_lang(&mut self) {
+            self.locked_from = Arc::default();
+        }
+
+        pub fn to_lang(&self) -> &str {
+            self.to_lang
+        }
+    }
+}
@@ -1,297 +1,364 @@
 use std::{
     collections::HashMap,
-    env::{self, args},
-    ffi::OsString,
+    env::args,
     fs::{self, File},
-    io::{stdin, Read, BufRead, BufReader},
-    path::PathBuf,
+    io::{self, stdin, stdout, Read, BufReader, IsTerminal, Write},
+    path::{Path, PathBuf},
     process::exit,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+        Mutex,
+        OnceLock,
+    },
+    time::Instant,
 };
 
 use baidu_fanyi::{
+    DEFAULT_OUT_FORMAT,
+    read_null_terminated,
+    split_on_separator,
+    trim_lines,
+    count_input,
+    count_result,
+    lines_changed_ratio,
+    TrimMode,
+    ansi::strip_ansi,
+    config::{config_path, get_id_and_key_async},
+    dedup::Deduped,
+    diff,
+    encoding::{self, InputEncoding},
+    head::{read_head, read_head_bytes, Head},
+    i18n::{self, Lang, Msg},
+    log::{CallLog, LogLevel},
+    markdown::{self, Protected, Segment},
+    ndjson,
+    output::{error_marker, Formatter, DEFAULT_ERROR_MARKER},
+    newline::{self, Newline},
     mini_fmt::Fmtter,
-    traits::FilterOutLongEmpty
+    response::{self, FmtArgs, Session, TransResult},
+    side_by_side,
+    stats::{Stats, DEFAULT_PRICE_PER_MILLION},
+    stream::{pipeline, BlockGauge, BlockReader, PipelineError},
+    traits::FilterOutLongEmpty,
+    wrap::wrap_result,
+    translater::{
+        check_header,
+        check_langs,
+        default_to_lang_for,
+        split_text_blocks_by,
+        LimitUnit,
+        SaltSource,
+        TranslateError,
+        TranslateProgress,
+        Translater,
+        DEFAULT_FROM_LANG,
+        LANGS,
+    },
 };
 use either::Either::{Left, Right};
-use reqwest::{
-    header::HeaderMap,
-    Client,
-    Error as RError,
-    Response,
-};
-use serde_json::Value;
-use lazy_static::lazy_static;
-use md5::{
-    self,
-    Digest
-};
-use rand::random;
+use encoding_rs::{Encoding, UTF_8};
+use futures::{future, stream, StreamExt};
+use glob::{MatchOptions, Pattern};
+use tokio::sync::watch;
+use walkdir::WalkDir;
 
 
-macro_rules! literals {
-    ( $(
-            #define $name:ident = $value:expr;
-    )* ) => {
-        $(
-            macro_rules! $name {
-                () => {
-                    $value
-                }
-            }
-        )*
-    };
-}
-literals!{
-    #define API_END_POINT = "http://api.fanyi.baidu.com";
-    #define API_INTERFACE_PATH = "/api/trans/vip/translate";
-}
+const DST_ONLY_FORMAT: &str = "%s%n";
+const SRC_ONLY_FORMAT: &str = "%1s%n";
+/// `--fail-empty` 时输入为空的退出码
+const EMPTY_INPUT_CODE: i32 = 6;
+/// `--assert-translated` 检查失败的退出码, 与接口错误的 5 区分
+const UNTRANSLATED_CODE: i32 = 8;
 
+/// `--low-mem` 默认同时翻译的块数
+const DEFAULT_LOW_MEM_BLOCKS: usize = 4;
+/// `--max-input-bytes` 的默认值
+const DEFAULT_MAX_INPUT_BYTES: u64 = 10 << 20;
 
-pub const URL: &str = concat!(API_END_POINT!(), API_INTERFACE_PATH!());
-pub const DEFAULT_FROM_LANG: &str = "auto";
-pub const DEFAULT_TO_LANG: &str = "auto";
-pub const MAX_TIMEOUT_COUNT: u32 = 2;
-pub const MAX_ERROR_COUNT: u32 = 2;
-pub const MAX_REQUEST_BYTES: usize = 3000;
+/// 无法获取终端宽度时 `--side-by-side` 与 `--wrap 0` 使用的宽度
+const FALLBACK_WIDTH: usize = 80;
 
+/// 界面语言, 由 `--lang` 或环境变量决定
+static LANG: OnceLock<Lang> = OnceLock::new();
 
-lazy_static!{
-    static ref HEADERS: HeaderMap = {
-        let mut x = HeaderMap::new();
-        // 'Content-Type': 'application/x-www-form-urlencoded'
-        x.insert("Content-Type",
-                 "application/x-www-form-urlencoded".parse().unwrap());
-        x
-    };
+/// 当前的界面语言
+fn lang() -> Lang {
+    *LANG.get_or_init(Lang::from_env)
 }
 
-
-/// 传入累计大小
-/// 修改大小计数并且返回是否需要分配新的一个块
-pub fn split_blocks(sum: &mut usize, this: usize) -> Result<bool, ()> {
-    if this < MAX_REQUEST_BYTES {
-        let num = *sum + this;
-        Ok(if num < MAX_REQUEST_BYTES {
-            false
-        } else {
-            // 旧子块加上新子块超出了最大块大小
-            // 将统计大小赋值为新块大小并通知新建块
-            // 比较的子块将被放入新块
-            *sum = this;
-            true
-        })
-    } else {
-        // 大于最大请求大小
-        Err(())
-    }
+/// 以当前界面语言格式化消息, 参数依次为 `%0s`, `%1s`, ...
+macro_rules! msg {
+    ($key:ident $(, $arg:expr)* $(,)?) => {
+        Msg::$key.format(lang(), &[$(&$arg as &dyn std::fmt::Display),*])
+    };
 }
 
-
-type JSONData = HashMap<String, Value>;
-
-
-/// 构建 md5 值, 官方示例是 utf-8 编码, 而 rust 字符串为 utf-8, 因此不用转换
-fn make_md5(s: &str) -> Digest {
-    md5::compute(s.as_bytes())
+/// 输出错误信息
+fn error(text: impl std::fmt::Display) {
+    eprintln!("{}{text}", msg!(ErrorPrefix));
 }
 
-
-/// 获取盐值
-/// 官方要求盐值在 [32768,65536], 实在是阴间
-/// 我推测可能是 [32768,65536) 因此使用我推测的值
-fn get_salt() -> u16 {
-    const MASK: u16 = 32767;
-    const BASE: u16 = MASK + 1;
-    let rand_num: u16 = random();
-    let final_num = (rand_num & MASK) + BASE;
-    final_num
+/// 输出警告信息
+fn warning(text: impl std::fmt::Display) {
+    eprintln!("{}{text}", msg!(WarningPrefix));
 }
 
-
-async fn post(
-    url: &str,
-    headers: HeaderMap,
-    data: &JSONData
-    ) -> Result<Response, RError> {
-    let client = Client::new();
-    client.post(url)
-        .headers(headers)
-        .form(data)
-        .send().await
+struct Config {
+    from_lang: Option<String>,
+    to_lang: Option<String>,
+    /// 未指定 `-t`, 目标语言取自 locale
+    to_from_locale: bool,
+    /// 检测出的源语言与取自 locale 的目标语言相同时, 之后使用的目标语言
+    to_fallback: OnceLock<&'static str>,
+    /// 以首次请求检测出的语言作为之后请求的源语言
+    from_detect_only: bool,
+    format: Formatter,
+    /// `None` is no filter
+    long_empty_count: Option<usize>,
+    /// 过滤空白前去除每行首尾的空白
+    trim_mode: TrimMode,
+    /// 输入为以 NUL 分隔的记录, 每条记录作为一行
+    null_terminated: bool,
+    diff: bool,
+    /// 以颜色标出 src 与 dst 中不同的单词
+    color_diff: bool,
+    /// 仅输出行数与字符数
+    count: bool,
+    /// 仅统计输入, 不翻译
+    count_only: bool,
+    /// `--assert-translated` 要求译文不同的行的最低百分比,
+    /// 为 0 时只要求至少一行不同
+    assert_translated: Option<f64>,
+    /// 在每个输出的开头与结尾添加的文本
+    prepend: Option<Fmtter>,
+    append: Option<Fmtter>,
+    /// 额外的请求头
+    headers: Vec<(String, String)>,
+    user_agent: Option<String>,
+    /// 随每次请求发送的上下文
+    context: Option<String>,
+    /// 输入为 NDJSON 记录
+    ndjson: bool,
+    /// NDJSON 记录中待翻译文本的字段
+    text_field: String,
+    /// 块或 NDJSON 记录出错时中止
+    fail_fast: bool,
+    /// 相同的行只翻译一次
+    dedup: bool,
+    /// 输入为空时以 [`EMPTY_INPUT_CODE`] 退出
+    fail_empty: bool,
+    /// 失败块的标记, `<code>` 为错误码
+    error_marker: String,
+    /// 以两列输出, 值为总宽度
+    side_by_side: Option<usize>,
+    /// 为 `--side-by-side` 的 dst 列或 `--color-diff` 的差异着色
+    color: bool,
+    /// dst 的折行宽度
+    wrap: Option<usize>,
+    markdown: bool,
+    /// `-l` 或 `--head-bytes`
+    head: Option<Head>,
+    /// 未使用 `--stream` 时输入大小的上限, 0 为不限制
+    max_input_bytes: u64,
+    /// 逐块读取并翻译输入
+    stream: bool,
+    /// `--low-mem` 时同时翻译的块数, 同时设置 `stream`
+    low_mem: Option<usize>,
+    /// 单次请求大小的计数单位
+    limit_unit: LimitUnit,
+    /// 同时翻译的文件数
+    jobs: usize,
+    inputs: Vec<String>,
+    recursive: bool,
+    glob: Option<Pattern>,
+    out_dir: Option<PathBuf>,
+    force: bool,
+    dry_run: bool,
+    progress: bool,
+    /// 原地翻译, 值为备份文件后缀, 为空时不备份
+    in_place: Option<String>,
+    strip_ansi: StripAnsi,
+    verbose: bool,
+    newline: Newline,
+    /// `None` 为 UTF-8
+    encoding: Option<InputEncoding>,
+    /// 输出的编码, `None` 为 UTF-8
+    output_encoding: Option<&'static Encoding>,
+    /// 替换非法的 UTF-8 序列, 而非报错
+    lossy: bool,
+    /// 将 stdin 分割为多段分别翻译
+    separator: Option<String>,
+    stats: bool,
+    stats_json: bool,
+    price_per_million: f64,
+    /// 接口调用日志
+    log_file: Option<PathBuf>,
+    log_level: LogLevel,
+    /// 格式化 `inputs[0]` 中保存的接口响应, 不发送请求
+    from_json: bool,
+    #[cfg(feature = "watch")]
+    watch: Option<WatchConfig>,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            from_lang: None,
+            to_lang: None,
+            to_from_locale: false,
+            to_fallback: OnceLock::new(),
+            from_detect_only: false,
+            format: Formatter::default(),
+            long_empty_count: Some(2),
+            trim_mode: TrimMode::None,
+            null_terminated: false,
+            diff: false,
+            color_diff: false,
+            assert_translated: None,
+            count: false,
+            count_only: false,
+            prepend: None,
+            append: None,
+            headers: vec![],
+            user_agent: None,
+            context: None,
+            ndjson: false,
+            text_field: "text".into(),
+            fail_fast: false,
+            dedup: true,
+            fail_empty: false,
+            error_marker: DEFAULT_ERROR_MARKER.into(),
+            side_by_side: None,
+            color: false,
+            wrap: None,
+            markdown: false,
+            head: None,
+            max_input_bytes: DEFAULT_MAX_INPUT_BYTES,
+            stream: false,
+            low_mem: None,
+            limit_unit: LimitUnit::default(),
+            jobs: 1,
+            inputs: vec![],
+            recursive: false,
+            glob: None,
+            out_dir: None,
+            force: false,
+            dry_run: false,
+            progress: false,
+            in_place: None,
+            strip_ansi: StripAnsi::Auto,
+            verbose: false,
+            newline: Newline::Lf,
+            encoding: None,
+            output_encoding: None,
+            lossy: false,
+            separator: None,
+            stats: false,
+            stats_json: false,
+            price_per_million: DEFAULT_PRICE_PER_MILLION,
+            log_file: None,
+            from_json: false,
+            log_level: LogLevel::All,
+            #[cfg(feature = "watch")]
+            watch: None,
+        }
+    }
 }
 
+/// 子命令, 第一个参数不是子命令时为 `translate`
+const COMMANDS: [&str; 5] = ["translate", "detect", "langs", "init", "cache"];
 
-fn get_id_and_key() -> [String; 2] {
-    let path = config_path();
-    let file: String = fs::read_to_string(&path)
-        .unwrap_or_else(|e| {
-            eprintln!("read config file error. path: {:?}, msg: {:?}",
-                      &path, e.to_string());
-            panic!();
-        });
-    let mut lines = file.lines();
-    let msg: &str = "config lines < 2";
-    [lines.next().expect(msg).into(), lines.next().expect(msg).into()]
+enum Command {
+    Translate(Box<Config>),
+    Detect(Vec<String>),
+    Langs,
+    Init {
+        force: bool,
+        id_and_key: Option<[String; 2]>,
+    },
+    Cache(CacheAction),
 }
 
-fn config_path() -> OsString {
-    let mut path = PathBuf::new();
-    path.push(&env::var("HOME").expect("get home error")[..]);
-    path.push(".baidufanyi_key");
-    path.into()
+/// `cache` 子命令的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CacheAction {
+    Clear,
+    Stats,
 }
 
-
-#[derive(Clone, Copy)]
-struct Translater<'a> {
-    id: &'a str,
-    key: &'a str,
-    salt: u16,
-    from_lang: &'a str,
-    to_lang: &'a str,
+/// 何时去除输入中的 ANSI 转义序列
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StripAnsi {
+    /// 输入为管道时
+    Auto,
+    Always,
+    Never,
 }
-impl<'a> Translater<'a> {
-    pub fn new(id: &'a str, key: &'a str) -> Self {
-        Self {
-            id,
-            key,
-            salt: 0,
-            from_lang: DEFAULT_FROM_LANG,
-            to_lang: DEFAULT_TO_LANG,
-        }
-    }
-
-    pub fn set_from_lang(&mut self, from: &'a str) -> &Self {
-        self.from_lang = from;
-        self
-    }
-
-    pub fn set_to_lang(&mut self, to: &'a str) -> &Self {
-        self.to_lang = to;
-        self
-    }
-
-    /// 更新盐值
-    pub fn update_salt(&mut self) {
-        self.salt = get_salt()
-    }
-
-    /// 构建请求荷载
-    /// 'appid': appid,
-    /// 'q': query,
-    /// 'from': from_lang,
-    /// 'to': to_lang,
-    /// 'salt': salt,
-    /// 'sign': sign
-    pub fn build_payload(&self, message: String) -> JSONData {
-        const KEY_COUNT: usize = 6;
-        let sign = self.get_sign(&message); // 初始化签名
-        let mut data = JSONData::with_capacity(KEY_COUNT);
-        debug_assert!(data.capacity() >= KEY_COUNT); // 可能分配更多
-
-        data.insert("appid".to_string(), self.id.into());
-        data.insert("q".into(), message.into());
-        data.insert("from".into(), self.from_lang.into());
-        data.insert("to".into(), self.to_lang.into());
-        data.insert("salt".into(), self.salt.into());
-        data.insert("sign".into(), sign.into());
-        data
-    }
-
-    /// 请求翻译
-    /// 复制一份 Translater 进行配置获取
-    pub async fn translate(mut self, message: String) -> JSONData {
-        self.update_salt(); // 需要先初始化盐值
-        let payload: JSONData = self.build_payload(message);
-        let mut timeout_count: u32 = 0;
-        let mut error_count: u32 = 0;
-        let result = loop {
-            match post(URL, HEADERS.clone(), &payload).await {
-                Ok(val) => break val,
-                Err(e) => {
-                    if e.is_timeout() {
-                        timeout_count += 1
-                    } else {
-                        error_count += 1
-                    }
-                    if timeout_count >= MAX_TIMEOUT_COUNT {
-                        panic!("timeout count >= {}", MAX_TIMEOUT_COUNT)
-                    }
-                    if error_count >= MAX_ERROR_COUNT {
-                        panic!("error count >= {}", MAX_ERROR_COUNT)
-                    }
-                }
-            }
-        };
-        result.json::<JSONData>().await.expect("data to json error")
-    }
-
-    /// 构建 md5 签名, 官方示例组合方式为
-    /// appid + query + salt + appkey
-    /// salt 为一个 [32768,65536] 区间的整数字符串, 不进行定长
-    pub fn get_sign(&self, message: &str) -> String {
-        let strs: [&str; 4]
-            = [&self.id, message, &self.salt.to_string(), &self.key];
-        format!("{:x}", make_md5(&strs.concat()))
-    }
-
-    #[allow(unused)]
-    pub fn from_lang(&self) -> &str {
-        self.from_lang
-    }
 
-    #[allow(unused)]
-    pub fn to_lang(&self) -> &str {
-        self.to_lang
-    }
+/// 一次翻译的输入
+struct Input {
+    text: String,
+    /// `--markdown` 模式下被保护的代码与链接
+    protected: Option<Protected>,
 }
 
-
-const DEFAULT_OUT_FORMAT: &str = "%s\n%s\n";
-
-struct Config {
-    from_lang: Option<String>,
-    to_lang: Option<String>,
-    text: String,
-    format: Vec<Fmtter>,
-    long_empty_count: usize,
+/// `--watch` 的配置
+#[cfg(feature = "watch")]
+struct WatchConfig {
+    debounce: std::time::Duration,
+    /// 每次重新翻译前输出的文本, 为 `None` 时在终端上清屏
+    sep: Option<String>,
 }
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            from_lang: None,
-            to_lang: None,
-            text: String::new(),
-            format: vec![],
-            long_empty_count: 2,
-        }
-    }
+
+/// 一个输入文件及其输出位置, 输出为 `None` 时输出到 stdout
+struct Job {
+    input: PathBuf,
+    output: Option<PathBuf>,
 }
 
 /// out help info and exit
 #[inline]
-fn help(opts: &getopts::Options, code: i32) -> ! {
+fn help(cmd: &str, opts: &getopts::Options, code: i32) -> ! {
     macro_rules! concatn {
         ( $( $line:expr ),* $(,)? ) => {
             concat!( $( $line, "\n" ),* )
         };
     }
     let bin_name = env!("CARGO_BIN_NAME");
-    let biref = opts.short_usage(bin_name);
-    let option = opts.usage(&format!("{biref} <FILE>"));
-    let cfg = config_path();
-    eprint!(concatn!{
+    let usage = |cmd: &str, free: &str| {
+        let biref = opts.short_usage(&format!("{bin_name} {cmd}"));
+        let biref = biref.strip_prefix("Usage: ").unwrap_or(&biref);
+        opts.usage_with_format(|rows| format!(
+            "{}{biref} {free}\n\n{}\n{}\n",
+            msg!(HelpUsage),
+            msg!(HelpOptions),
+            rows.collect::<Vec<_>>().join("\n"),
+        ))
+    };
+    if cmd != "translate" {
+        let (free, note) = match cmd {
+            "detect" => ("<FILE>..", msg!(HelpDetect)),
+            "langs" => ("", msg!(HelpLangs)),
+            "init" => ("[<APPID> <APPKEY>]", msg!(HelpInit)),
+            "cache" => ("clear|stats", msg!(HelpCache)),
+            _ => ("", String::new()),
+        };
+        eprint!("{}\n{}\n{note}", usage(cmd, free), msg!(HelpNote));
+        exit(code);
+    }
+    let option = usage("[translate]", "<FILE>..");
+    let cfg = config_path()
+        .map_or_else(|e| e.to_string(), |path| path.display().to_string());
+    eprint!(concat!(concatn!{
         "{option}",
-        "NOTE:",
-        "    <FILE> is - use stdin",
-        "    config file in {cfg:?},",
-        "        line1: appid, line2: appkey",
-        "",
-        "Format:",
+        "{note_title}",
+        "{note}",
+        "{format}",
         "    |----|-------------|",
         "    | %s | Display     |",
         "    | %r | Debug       |",
         "    | %R | DebugExpand |",
+        "    | %% | Percent     |",
         "    | %n | LF          |",
         "    | %N | CR          |",
         "    | %t | Tab         |",
@@ -300,46 +367,226 @@ fn help(opts: &getopts::Options, code: i32) -> ! {
         "    | %x | ASCII       |",
         "    | %u | Unicode     |",
         "    | %U | Unicode+    |",
+        "    | %? | Condition   |",
         "    |----|-------------|",
-        "    `%[n]...` example: `%0s`, index 0 Display",
-    }, option=option, cfg=cfg);
+        "{format_note}",
+        "{format_args}",
+        "{format_args_note}",
+        "{commands}",
+    }, "{commands_note}"),
+        option=option,
+        note_title=msg!(HelpNote),
+        note=msg!(HelpTranslate, format!("{cfg:?}")),
+        format=msg!(HelpFormat),
+        format_note=msg!(HelpFormatNote),
+        format_args=msg!(HelpFormatArgs),
+        format_args_note=msg!(HelpFormatArgsNote),
+        commands=msg!(HelpCommands),
+        commands_note=msg!(HelpCommandsNote, bin_name),
+    );
     exit(code);
 }
 
-fn get_cfg() -> Config {
+/// 解析参数, 出错时输出子命令 `cmd` 的帮助并退出
+fn parse_args(
+    cmd: &str,
+    opts: &getopts::Options,
+    args: &[String],
+) -> getopts::Matches {
+    let parsed = match opts.parse(args) {
+        Ok(parsed) => parsed,
+        Err(getopts::Fail::ArgumentMissing(opt)) => {
+            error(msg!(ArgumentMissing, opt));
+            help(cmd, opts, 2);
+        },
+        Err(getopts::Fail::UnrecognizedOption(opt)) => {
+            error(msg!(InvalidOption, opt));
+            help(cmd, opts, 2);
+        },
+        Err(getopts::Fail::OptionMissing(opt)) => {
+            error(msg!(OptionMissing, opt));
+            help(cmd, opts, 2);
+        },
+        Err(getopts::Fail::OptionDuplicated(opt)) => {
+            // 重复的选项不按先后取值, 直接报错
+            let dash = if opt.chars().count() == 1 { "-" } else { "--" };
+            error(msg!(OptionDuplicated, format!("{dash}{opt}")));
+            help(cmd, opts, 2);
+        },
+        Err(getopts::Fail::UnexpectedArgument(opt)) => {
+            error(msg!(UnexpectedArgument, opt));
+            help(cmd, opts, 2);
+        },
+    };
+    if parsed.opt_present("help") { help(cmd, opts, 0) }
+    if let Some(value) = parsed.opt_str("lang").filter(|x| x.parse::<Lang>().is_err()) {
+        error(msg!(InvalidValueExpected, "--lang", value, msg!(LangNames)));
+        help(cmd, opts, 2)
+    }
+    parsed
+}
+
+/// 在解析参数前取得 `--lang` 的值, 使参数错误也以该语言输出,
+/// 值无效时在解析参数时报错
+/// getopts 中短选项的可选参数会取走下一个不以 `-` 开头的参数,
+/// 将没有紧跟参数的这类短选项改写为长选项,
+/// 使其参数只能以 `-i.bak` 或 `--in-place=.bak`, `-l3` 或 `--line=3` 的形式给出
+fn detach_optional_args(
+    opts: &getopts::Options,
+    args: &[String],
+    optional: &[(char, &str)],
+) -> Vec<String> {
+    let mut result = Vec::with_capacity(args.len());
+    let mut iter = args.iter();
+    for arg in iter.by_ref() {
+        if arg == "--" {
+            result.push(arg.clone());
+            break;
+        }
+        let group = arg.strip_prefix('-').filter(|group| !group.starts_with('-'));
+        let last = group.and_then(|group| group.chars().last());
+        let Some(&(short, long)) = optional.iter().find(|(c, _)| Some(*c) == last) else {
+            result.push(arg.clone());
+            continue;
+        };
+        // 末尾的字符可能是前面选项的参数, 单独解析确认
+        let alone = opts.parse([arg]).ok()
+            .filter(|m| m.opt_present(long) && m.opt_str(long).is_none());
+        match alone {
+            Some(_) if arg.len() > 2 => {
+                result.push(arg[..arg.len() - short.len_utf8()].into());
+                result.push(format!("--{long}"));
+            },
+            Some(_) => result.push(format!("--{long}")),
+            None => result.push(arg.clone()),
+        }
+    }
+    result.extend(iter.cloned());
+    result
+}
+
+fn init_lang(args: &[String]) {
+    let mut value = None;
+    let mut args = args.iter().skip(1).take_while(|&arg| arg != "--");
+    while let Some(arg) = args.next() {
+        if arg == "--lang" {
+            value = args.next().map(String::as_str);
+        } else if let Some(x) = arg.strip_prefix("--lang=") {
+            value = Some(x);
+        }
+    }
+    let lang = value.and_then(|x| x.parse().ok()).unwrap_or_else(Lang::from_env);
+    LANG.get_or_init(|| lang);
+}
+
+/// 解析子命令, 第一个参数不是子命令时视为 `translate`
+fn get_command() -> Command {
     let args = args().collect::<Vec<_>>();
+    init_lang(&args);
+    let (cmd, rest) = match args.get(1) {
+        Some(cmd) if COMMANDS.contains(&&**cmd) => (&**cmd, &args[2..]),
+        _ => ("translate", &args[1..]),
+    };
+    if cmd == "translate" {
+        return Command::Translate(Box::new(get_cfg(rest)));
+    }
+
+    let mut opts = getopts::Options::new();
+    opts.optflagmulti("h", "help", &msg!(OptHelp));
+    opts.optopt("", "lang", &msg!(OptLang), "<lang>");
+    if cmd == "init" {
+        opts.optflag("", "force", &msg!(OptInitForce));
+    }
+    let mut parsed = parse_args(cmd, &opts, rest);
+    let mut free = std::mem::take(&mut parsed.free);
+    match cmd {
+        "detect" => {
+            if !default_to_stdin(&mut free) {
+                error(msg!(NoInput));
+                help(cmd, &opts, 2);
+            }
+            Command::Detect(free)
+        },
+        "langs" => {
+            if !free.is_empty() {
+                error(msg!(UnexpectedArgument, free[0]));
+                help(cmd, &opts, 2);
+            }
+            Command::Langs
+        },
+        "init" => {
+            let id_and_key = match <[String; 2]>::try_from(free) {
+                Ok(id_and_key) => Some(id_and_key),
+                Err(free) if free.is_empty() => None,
+                Err(_) => {
+                    error(msg!(InitArgs));
+                    help(cmd, &opts, 2);
+                },
+            };
+            Command::Init { force: parsed.opt_present("force"), id_and_key }
+        },
+        "cache" => match free.as_slice() {
+            [action] if action == "clear" => Command::Cache(CacheAction::Clear),
+            [action] if action == "stats" => Command::Cache(CacheAction::Stats),
+            _ => {
+                error(msg!(CacheAction));
+                help(cmd, &opts, 2);
+            },
+        },
+        _ => unreachable!("unknown command {cmd}"),
+    }
+}
 
+fn get_cfg(args: &[String]) -> Config {
     let mut opts = getopts::Options::new();
 
     macro_rules! decl {
         (@str $t:literal) => ($t);
         (@str $t:tt) => (stringify!($t));
 
+        (@short _) => ("");
+        (@short $t:ident) => (stringify!($t));
+        (@short $t:literal) => (stringify!($t));
+
         (@arg $t:literal) => (concat!("<", $t, ">"));
         (@arg $t:tt) => (concat!("<", stringify!($t), ">"));
 
-        (-$short:ident --$long:tt * $desc:literal) => {
-            opts.optflagmulti(stringify!($short), decl!(@str $long), $desc);
+        // long option only
+        (--$long:tt $($rest:tt)*) => {
+            decl!(-_ --$long $($rest)*)
+        };
+
+        (-$short:tt --$long:tt * $desc:ident) => {
+            opts.optflagmulti(decl!(@short $short), decl!(@str $long), &msg!($desc));
         };
 
-        (-$short:ident --$long:tt $desc:literal) => {
-            opts.optflag(stringify!($short), decl!(@str $long), $desc);
+        (-$short:tt --$long:tt $desc:ident) => {
+            opts.optflag(decl!(@short $short), decl!(@str $long), &msg!($desc));
         };
 
-        (-$short:ident --$long:tt (*$hint:tt) $desc:literal) => {
+        (-$short:tt --$long:tt (*$hint:tt) $desc:ident) => {
             opts.optmulti(
-                stringify!($short),
+                decl!(@short $short),
+                decl!(@str $long),
+                &msg!($desc),
+                decl!(@arg $hint),
+            );
+        };
+
+        (-$short:tt --$long:tt [$hint:tt] $desc:ident) => {
+            opts.optflagopt(
+                decl!(@short $short),
                 decl!(@str $long),
-                $desc,
+                &msg!($desc),
                 decl!(@arg $hint),
             );
         };
 
-        (-$short:ident --$long:tt ($hint:tt) $desc:literal) => {
+        (-$short:tt --$long:tt ($hint:tt) $desc:ident) => {
             opts.optopt(
-                stringify!($short),
+                decl!(@short $short),
                 decl!(@str $long),
-                $desc,
+                &msg!($desc),
                 decl!(@arg $hint),
             );
         };
@@ -347,151 +594,1990 @@ fn get_cfg() -> Config {
 
     opts.parsing_style(getopts::ParsingStyle::StopAtFirstFree);
 
-    decl!(-f --from (lang)              "from lang");
-    decl!(-t --to (lang)                "to lang");
-    decl!(-l --line                     "read one line");
-    decl!(-m --fmt (*fstr)              "formatters (multiple)");
-    decl!(-o --"empty-count" (count)    "filter out empty count (default:2)");
-    decl!(-v --version*                 "show version");
-    decl!(-h --help*                    "show help");
+    decl!(-f --from (lang)              OptFrom);
+    decl!(-t --to (lang)                OptTo);
+    decl!(--"from-detect-only"          OptFromDetectOnly);
+    decl!(-l --line [count]             OptLine);
+    decl!(--"head-bytes" (n)            OptHeadBytes);
+    decl!(--"max-input-bytes" (n)       OptMaxInputBytes);
+    decl!(--stream                      OptStream);
+    decl!(--"low-mem" [blocks]          OptLowMem);
+    decl!(--"limit-unit" (unit)         OptLimitUnit);
+    decl!(--"stdin-separator" (pattern) OptStdinSeparator);
+    decl!(-0 --"read-null-terminated"   OptReadNullTerminated);
+    decl!(--"from-json" (path)          OptFromJson);
+    decl!(-m --fmt (*fstr)              OptFmt);
+    decl!(--"template-file" (path)      OptTemplateFile);
+    decl!(--"dst-only"                  OptDstOnly);
+    decl!(--"src-only"                  OptSrcOnly);
+    decl!(--prepend (text)              OptPrepend);
+    decl!(--append (text)               OptAppend);
+    decl!(-o --"empty-count" (count)    OptEmptyCount);
+    decl!(--"no-filter"                 OptNoFilter);
+    decl!(--trim                        OptTrim);
+    decl!(--"trim-start"                OptTrimStart);
+    decl!(--"trim-end"                  OptTrimEnd);
+    decl!(--markdown                    OptMarkdown);
+    decl!(--diff                        OptDiff);
+    decl!(--"color-diff"                OptColorDiff);
+    decl!(--count                       OptCount);
+    decl!(--"count-only"                OptCountOnly);
+    decl!(--ndjson                      OptNdjson);
+    decl!(--"text-field" (name)         OptTextField);
+    decl!(--"fail-fast"                 OptFailFast);
+    decl!(--"no-dedup"                  OptNoDedup);
+    decl!(--"fail-empty"                OptFailEmpty);
+    decl!(--"assert-translated"         OptAssertTranslated);
+    decl!(--"assert-translated-ratio" (n) OptAssertTranslatedRatio);
+    decl!(--"error-marker" (text)       OptErrorMarker);
+    decl!(--"side-by-side"              OptSideBySide);
+    decl!(--wrap (cols)                 OptWrap);
+    decl!(--color [when]                OptColor);
+    decl!(-r --recursive                OptRecursive);
+    decl!(-j --jobs (n)                 OptJobs);
+    decl!(--glob (pattern)              OptGlob);
+    decl!(--"out-dir" (dir)             OptOutDir);
+    decl!(--force                       OptForce);
+    decl!(--"dry-run"                   OptDryRun);
+    decl!(-i --"in-place" [SUFFIX]      OptInPlace);
+    decl!(--"strip-ansi" [when]         OptStripAnsi);
+    decl!(--encoding (name)             OptEncoding);
+    decl!(--"output-encoding" (name)    OptOutputEncoding);
+    decl!(--lossy                       OptLossy);
+    decl!(--newline (style)             OptNewline);
+    decl!(--verbose                     OptVerbose);
+    decl!(--stats                       OptStats);
+    decl!(--"stats-json"                OptStatsJson);
+    decl!(--"price-per-million" (yuan)  OptPricePerMillion);
+    decl!(--progress                    OptProgress);
+    decl!(--"log-file" (path)           OptLogFile);
+    decl!(--"log-level" (level)         OptLogLevel);
+    #[cfg(feature = "watch")]
+    decl!(--watch                       OptWatch);
+    #[cfg(feature = "watch")]
+    decl!(--"watch-debounce" (ms)       OptWatchDebounce);
+    #[cfg(feature = "watch")]
+    decl!(--"watch-sep" (text)          OptWatchSep);
+    decl!(--header (*"Name: value")     OptHeader);
+    decl!(--"user-agent" (ua)           OptUserAgent);
+    decl!(--context (text)              OptContext);
+    decl!(--lang (lang)                 OptLang);
+    decl!(-v --version*                 OptVersion);
+    decl!(-h --help*                    OptHelp);
 
-    let parsed = match opts.parse(&args[1..]) {
-        Ok(parsed) => parsed,
-        Err(getopts::Fail::ArgumentMissing(opt)) => {
-            eprintln!("Error: argument missing {opt}");
-            help(&opts, 2);
+    let args = detach_optional_args(&opts, args, &[('i', "in-place"), ('l', "line")]);
+    let parsed = parse_args("translate", &opts, &args);
+    if parsed.opt_present("version") {
+        println!("{}", env!("CARGO_PKG_VERSION"));
+        exit(0)
+    }
+
+    if parsed.opt_present("no-filter") && parsed.opt_present("o") {
+        error(msg!(Conflicts, "--no-filter", "-o"));
+        help("translate", &opts, 2)
+    }
+
+    let trims = [
+        ("trim", TrimMode::Both),
+        ("trim-start", TrimMode::Start),
+        ("trim-end", TrimMode::End),
+    ];
+    let mut trim_mode = TrimMode::None;
+    let mut trim_name = None;
+    for (name, mode) in trims {
+        if !parsed.opt_present(name) { continue }
+        if let Some(prev) = trim_name.replace(name) {
+            error(msg!(Conflicts, format!("--{prev}"), format!("--{name}")));
+            help("translate", &opts, 2)
+        }
+        trim_mode = mode;
+    }
+
+    if parsed.opt_present("read-null-terminated") {
+        let conflicts = ["l", "head-bytes", "stream", "low-mem", "ndjson", "stdin-separator"];
+        if let Some(name) = conflicts.iter().find(|&&x| parsed.opt_present(x)) {
+            let dash = if name.len() == 1 { "-" } else { "--" };
+            error(msg!(Conflicts, "-0", format!("{dash}{name}")));
+            help("translate", &opts, 2)
+        }
+    }
+
+    let mut cfg = Config {
+        trim_mode,
+        null_terminated: parsed.opt_present("read-null-terminated"),
+        long_empty_count: if parsed.opt_present("no-filter") {
+            None
+        } else {
+            Some(parsed.opt_str("o").map_or(Ok(2), |count| {
+                count.parse().map_err(|_| count)
+            }).unwrap_or_else(|count| {
+                error(msg!(InvalidValueExpected, "-o", count, msg!(NonNegativeInteger)));
+                help("translate", &opts, 2)
+            }))
         },
-        Err(getopts::Fail::UnrecognizedOption(opt)) => {
-            eprintln!("Error: invalid option {opt}");
-            help(&opts, 2);
+        from_lang: parsed.opt_str("from"),
+        to_lang: parsed.opt_str("to"),
+        from_detect_only: parsed.opt_present("from-detect-only"),
+        diff: parsed.opt_present("diff"),
+        color_diff: parsed.opt_present("color-diff"),
+        count: parsed.opt_present("count"),
+        ndjson: parsed.opt_present("ndjson"),
+        fail_fast: parsed.opt_present("fail-fast"),
+        dedup: !parsed.opt_present("no-dedup"),
+        fail_empty: parsed.opt_present("fail-empty"),
+        error_marker: parsed.opt_str("error-marker")
+            .unwrap_or_else(|| DEFAULT_ERROR_MARKER.into()),
+        count_only: parsed.opt_present("count-only"),
+        markdown: parsed.opt_present("markdown"),
+        recursive: parsed.opt_present("recursive"),
+        glob: parsed.opt_str("glob").map(|glob| {
+            Pattern::new(&glob).unwrap_or_else(|e| {
+                error(msg!(InvalidGlob, glob, e));
+                help("translate", &opts, 2)
+            })
+        }),
+        out_dir: parsed.opt_str("out-dir").map(Into::into),
+        force: parsed.opt_present("force"),
+        dry_run: parsed.opt_present("dry-run"),
+        progress: parsed.opt_present("progress"),
+        separator: parsed.opt_str("stdin-separator")
+            .map(|sep| sep.replace("\\n", "\n").replace("\\t", "\t")),
+        stats: parsed.opt_present("stats"),
+        stats_json: parsed.opt_present("stats-json"),
+        price_per_million: parsed
+            .opt_get_default("price-per-million", DEFAULT_PRICE_PER_MILLION)
+            .unwrap_or_else(|e| {
+                error(msg!(ParsePrice, e));
+                help("translate", &opts, 2)
+            }),
+        in_place: parsed.opt_default("in-place", ""),
+        strip_ansi: match parsed.opt_default("strip-ansi", "always").as_deref() {
+            None | Some("auto") => StripAnsi::Auto,
+            Some("always") => StripAnsi::Always,
+            Some("never") => StripAnsi::Never,
+            Some(when) => {
+                error(msg!(InvalidValue, "--strip-ansi", when));
+                help("translate", &opts, 2)
+            },
         },
-        Err(getopts::Fail::OptionMissing(opt)) => {
-            eprintln!("Error: missing required option {opt}");
-            help(&opts, 2);
+        verbose: parsed.opt_present("verbose"),
+        encoding: parsed.opt_str("encoding").map(|name| {
+            InputEncoding::from_name(&name).unwrap_or_else(|| {
+                error(msg!(UnknownEncoding, name));
+                help("translate", &opts, 2)
+            })
+        }),
+        newline: parsed.opt_get_default("newline", Newline::Lf)
+            .unwrap_or_else(|e| {
+                error(e);
+                help("translate", &opts, 2)
+            }),
+        output_encoding: parsed.opt_str("output-encoding").and_then(|name| {
+            let encoding = Encoding::for_label(name.as_bytes()).unwrap_or_else(|| {
+                error(msg!(UnknownOutputEncoding, name));
+                help("translate", &opts, 2)
+            });
+            (encoding != UTF_8).then_some(encoding)
+        }),
+        limit_unit: parsed.opt_get_default("limit-unit", LimitUnit::default())
+            .unwrap_or_else(|e| {
+                error(e);
+                help("translate", &opts, 2)
+            }),
+        lossy: parsed.opt_present("lossy"),
+        log_file: parsed.opt_str("log-file").map(Into::into),
+        log_level: parsed.opt_get_default("log-level", LogLevel::All)
+            .unwrap_or_else(|e| {
+                error(e);
+                help("translate", &opts, 2)
+            }),
+        ..Default::default()
+    };
+    if cfg.from_detect_only {
+        if cfg.from_lang.as_deref().is_some_and(|lang| lang != "auto") {
+            error(msg!(Requires, "--from-detect-only", "-f auto"));
+            help("translate", &opts, 2)
+        }
+        if cfg.ndjson {
+            error(msg!(Conflicts, "--from-detect-only", "--ndjson"));
+            help("translate", &opts, 2)
+        }
+    }
+    let from = cfg.from_lang.as_deref().unwrap_or(DEFAULT_FROM_LANG);
+    cfg.to_from_locale = cfg.to_lang.is_none();
+    let to = cfg.to_lang.get_or_insert_with(|| default_to_lang_for(from).into());
+    match check_langs(from, to) {
+        Ok(warnings) => for text in warnings {
+            warning(text);
         },
-        Err(getopts::Fail::OptionDuplicated(opt)) => {
-            eprintln!("Error: option duplicated {opt}");
-            help(&opts, 2);
+        Err(e) => {
+            error(e);
+            help("translate", &opts, 2)
         },
-        Err(getopts::Fail::UnexpectedArgument(opt)) => {
-            eprintln!("Error: unexpected argument {opt}");
-            help(&opts, 2);
+    }
+    if cfg.lossy && cfg.encoding.is_some() {
+        error(msg!(Conflicts, "--lossy", "--encoding"));
+        help("translate", &opts, 2)
+    }
+    if parsed.opt_present("log-level") && cfg.log_file.is_none() {
+        error(msg!(Requires, "--log-level", "--log-file"));
+        help("translate", &opts, 2)
+    }
+    for (name, slot) in [("prepend", &mut cfg.prepend), ("append", &mut cfg.append)] {
+        let Some(text) = parsed.opt_str(name) else { continue };
+        match Fmtter::build(&text) {
+            Ok(fmtter) if fmtter.is_const() => *slot = Some(fmtter),
+            Ok(_) => {
+                error(msg!(ConstArgument, format!("--{name}"), text));
+                help("translate", &opts, 2)
+            },
+            Err(e) => {
+                error(msg!(BuildFmtter, text, e));
+                help("translate", &opts, 2)
+            },
+        }
+    }
+
+    let mut free = parsed.free.clone();
+    cfg.head = match (parsed.opt_default("line", "1"), parsed.opt_str("head-bytes")) {
+        (Some(_), Some(_)) => {
+            error(msg!(Conflicts, "-l", "--head-bytes"));
+            help("translate", &opts, 2)
         },
+        (Some(count), None) => Some(Head::Lines(match count.parse() {
+            Ok(0) => {
+                error(msg!(LineCountZero));
+                help("translate", &opts, 2)
+            },
+            Ok(count) => count,
+            Err(e) => {
+                error(msg!(ParseInt, e));
+                help("translate", &opts, 2)
+            },
+        })),
+        (None, Some(max)) => Some(Head::Bytes(max.parse().unwrap_or_else(|e| {
+            error(msg!(ParseInt, e));
+            help("translate", &opts, 2)
+        }))),
+        (None, None) => None,
     };
 
-    if parsed.opt_present("help") { help(&opts, 0) }
-    if parsed.opt_present("version") {
-        println!("{}", env!("CARGO_PKG_VERSION"));
-        exit(0)
+    cfg.max_input_bytes = parsed
+        .opt_get_default("max-input-bytes", DEFAULT_MAX_INPUT_BYTES)
+        .unwrap_or_else(|e| {
+            error(msg!(ParseInt, e));
+            help("translate", &opts, 2)
+        });
+    cfg.stream = parsed.opt_present("stream");
+    if parsed.opt_present("low-mem") {
+        let blocks = parsed.opt_str("low-mem");
+        let depth = blocks.as_deref().map_or(Some(DEFAULT_LOW_MEM_BLOCKS), |n| {
+            n.parse().ok().filter(|&n| n != 0)
+        });
+        cfg.low_mem = Some(depth.unwrap_or_else(|| {
+            error(msg!(
+                InvalidValueExpected,
+                "--low-mem",
+                blocks.unwrap_or_default(),
+                msg!(PositiveInteger),
+            ));
+            help("translate", &opts, 2)
+        }));
+        cfg.stream = true;
+    }
+    let stream_name = if cfg.low_mem.is_some() { "low-mem" } else { "stream" };
+    if let Some(jobs) = parsed.opt_str("jobs") {
+        cfg.jobs = jobs.parse().ok().filter(|&n| n != 0).unwrap_or_else(|| {
+            error(msg!(InvalidValueExpected, "--jobs", jobs, msg!(PositiveInteger)));
+            help("translate", &opts, 2)
+        });
+        if cfg.stream {
+            error(msg!(Conflicts, format!("--{stream_name}"), "--jobs"));
+            help("translate", &opts, 2)
+        }
+    }
+    if cfg.stream {
+        let conflicts = [
+            "l", "head-bytes", "stdin-separator", "markdown", "diff", "color-diff",
+            "count", "count-only", "ndjson", "encoding", "in-place", "out-dir",
+            "output-encoding",
+        ];
+        if let Some(name) = conflicts.iter().find(|&&x| parsed.opt_present(x)) {
+            let dash = if name.len() == 1 { "-" } else { "--" };
+            error(msg!(Conflicts, format!("--{stream_name}"), format!("{dash}{name}")));
+            help("translate", &opts, 2)
+        }
+        if cfg.newline == Newline::Crlf {
+            error(msg!(Conflicts, "--stream", "--newline crlf"));
+            help("translate", &opts, 2)
+        }
     }
 
-    let mut cfg = Config::default();
+    if cfg.in_place.is_some() && cfg.out_dir.is_some() {
+        error(msg!(Conflicts, "--in-place", "--out-dir"));
+        help("translate", &opts, 2)
+    }
 
-    cfg.long_empty_count = parsed.opt_get_default("o", 2)
-        .unwrap_or_else(|e| {
-            eprintln!("Error: parse to int error `{}`", e);
-            help(&opts, 2)
+    for header in parsed.opt_strs("header") {
+        let Some((name, value)) = header.split_once(':') else {
+            error(msg!(InvalidHeader, header));
+            help("translate", &opts, 2)
+        };
+        let (name, value) = (name.trim(), value.trim());
+        if let Err(e) = check_header(name, value) {
+            error(e);
+            help("translate", &opts, 2)
+        }
+        cfg.headers.push((name.into(), value.into()));
+    }
+    cfg.user_agent = parsed.opt_str("user-agent");
+    cfg.context = parsed.opt_str("context");
+    if let Some(Err(e)) = cfg.user_agent.as_ref().map(|ua| {
+        check_header("User-Agent", ua)
+    }) {
+        error(e);
+        help("translate", &opts, 2)
+    }
+
+    if cfg.ndjson {
+        let conflicts = [
+            "m", "template-file", "dst-only", "src-only", "diff", "color-diff", "markdown",
+            "count", "count-only", "side-by-side", "stdin-separator",
+        ];
+        if let Some(name) = conflicts.iter().find(|&&x| parsed.opt_present(x)) {
+            let dash = if name.len() == 1 { "-" } else { "--" };
+            error(msg!(Conflicts, "--ndjson", format!("{dash}{name}")));
+            help("translate", &opts, 2)
+        }
+        if let Some(field) = parsed.opt_str("text-field") {
+            cfg.text_field = field;
+        }
+    } else {
+        if parsed.opt_present("text-field") {
+            error(msg!(Requires, "--text-field", "--ndjson"));
+            help("translate", &opts, 2)
+        }
+    }
+
+    if cfg.count || cfg.count_only {
+        let conflicts = ["diff", "color-diff", "side-by-side", "in-place", "out-dir"];
+        if let Some(name) = conflicts.iter().find(|&&x| parsed.opt_present(x)) {
+            error(msg!(Conflicts, "--count", format!("--{name}")));
+            help("translate", &opts, 2)
+        }
+    }
+
+    if let Some(n) = parsed.opt_str("assert-translated-ratio") {
+        let ratio = n.parse().ok().filter(|n| (0.0..=100.0).contains(n));
+        cfg.assert_translated = Some(ratio.unwrap_or_else(|| {
+            error(msg!(
+                InvalidValueExpected,
+                "--assert-translated-ratio",
+                n,
+                msg!(Percentage),
+            ));
+            help("translate", &opts, 2)
+        }));
+    } else if parsed.opt_present("assert-translated") {
+        cfg.assert_translated = Some(0.0);
+    }
+    if cfg.assert_translated.is_some() {
+        let conflicts = ["stream", "low-mem", "ndjson", "diff", "count-only"];
+        if let Some(name) = conflicts.iter().find(|&&x| parsed.opt_present(x)) {
+            error(msg!(Conflicts, "--assert-translated", format!("--{name}")));
+            help("translate", &opts, 2)
+        }
+    }
+
+    if parsed.opt_present("side-by-side") {
+        let conflicts = [
+            "m", "template-file", "dst-only", "src-only", "diff", "color-diff",
+            "in-place", "out-dir",
+        ];
+        if let Some(name) = conflicts.iter().find(|&&x| parsed.opt_present(x)) {
+            let dash = if name.len() == 1 { "-" } else { "--" };
+            error(msg!(Conflicts, "--side-by-side", format!("{dash}{name}")));
+            help("translate", &opts, 2)
+        }
+    }
+    if cfg.diff && cfg.color_diff {
+        error(msg!(Conflicts, "--diff", "--color-diff"));
+        help("translate", &opts, 2)
+    }
+    if cfg.diff || cfg.color_diff {
+        let diff_name = if cfg.diff { "--diff" } else { "--color-diff" };
+        if let Some(name) = ["in-place", "out-dir"].iter().find(|&&x| parsed.opt_present(x)) {
+            error(msg!(Conflicts, diff_name, format!("--{name}")));
+            help("translate", &opts, 2)
+        }
+    }
+
+    if parsed.opt_present("side-by-side") || cfg.color_diff {
+        let width = terminal_size::terminal_size()
+            .map(|(terminal_size::Width(w), _)| usize::from(w));
+        if parsed.opt_present("side-by-side") {
+            cfg.side_by_side = Some(width.unwrap_or(FALLBACK_WIDTH));
+        }
+        cfg.color = match parsed.opt_default("color", "always").as_deref() {
+            None | Some("auto") => width.is_some()
+                && stdout().is_terminal()
+                && std::env::var_os("NO_COLOR").is_none(),
+            Some("always") => true,
+            Some("never") => false,
+            Some(when) => {
+                error(msg!(InvalidValue, "--color", when));
+                help("translate", &opts, 2)
+            },
+        };
+    } else if parsed.opt_present("color") {
+        error(msg!(RequiresEither, "--color", "--side-by-side", "--color-diff"));
+        help("translate", &opts, 2)
+    }
+
+    if let Some(cols) = parsed.opt_str("wrap") {
+        let conflicts = [
+            "side-by-side", "diff", "color-diff", "count", "count-only", "ndjson",
+        ];
+        if let Some(name) = conflicts.iter().find(|&&x| parsed.opt_present(x)) {
+            error(msg!(Conflicts, "--wrap", format!("--{name}")));
+            help("translate", &opts, 2)
+        }
+        let cols: usize = cols.parse().unwrap_or_else(|e| {
+            error(msg!(ParseInt, e));
+            help("translate", &opts, 2)
+        });
+        cfg.wrap = Some(if cols == 0 {
+            terminal_size::terminal_size()
+                .map_or(FALLBACK_WIDTH, |(terminal_size::Width(w), _)| w.into())
+        } else {
+            cols
         });
-    cfg.from_lang = parsed.opt_str("from");
-    cfg.to_lang = parsed.opt_str("to");
+    }
 
     let mut fmtters = parsed.opt_strs("m");
-    if fmtters.is_empty() { fmtters.push(DEFAULT_OUT_FORMAT.to_owned()) }
+    if let Some(path) = parsed.opt_str("template-file") {
+        if !fmtters.is_empty() {
+            error(msg!(Conflicts, "--template-file", "-m"));
+            help("translate", &opts, 2)
+        }
+        fmtters.push(fs::read_to_string(&path).unwrap_or_else(|e| {
+            error(msg!(ReadTemplate, path, e));
+            exit(3)
+        }));
+    }
+    if parsed.opt_present("dst-only") && parsed.opt_present("src-only") {
+        error(msg!(Conflicts, "--dst-only", "--src-only"));
+        help("translate", &opts, 2)
+    }
+    let shortcuts = [("dst-only", DST_ONLY_FORMAT), ("src-only", SRC_ONLY_FORMAT)];
+    for (name, format) in shortcuts {
+        if !parsed.opt_present(name) { continue }
+        if !fmtters.is_empty() {
+            error(msg!(ConflictsFormats, format!("--{name}")));
+            help("translate", &opts, 2)
+        }
+        fmtters.push(format.to_owned());
+    }
+    if fmtters.is_empty() {
+        let to_file = cfg.in_place.is_some() || cfg.out_dir.is_some();
+        let is_tty = stdout().is_terminal();
+        fmtters.push(default_format(to_file, is_tty).to_owned())
+    }
+    if fmtters.iter().any(|fmt| fmt.trim().is_empty()) {
+        error(msg!(EmptyFormat));
+        help("translate", &opts, 2)
+    }
+    let mut formats = vec![];
     for formatter in fmtters {
-        match formatter.parse() {
-            Ok(format) => cfg.format.push(format),
+        match formatter.parse::<Fmtter>() {
+            Ok(format) => {
+                let unknown = format.named_slots()
+                    .find(|name| !FmtArgs::NAMES.contains(name))
+                    .map(str::to_owned);
+                if let Some(name) = unknown {
+                    error(msg!(UnknownPlaceholder, formatter, name));
+                    help("translate", &opts, 2)
+                }
+                let available = FmtArgs::NAMES.len();
+                if format.arg_count() > available {
+                    error(msg!(TooManyArguments, formatter, format.arg_count(), available));
+                    help("translate", &opts, 2)
+                }
+                formats.push(format)
+            },
             Err(e) => {
-                eprintln!("Error: on `{formatter}` build fmtter error: {e}");
-                help(&opts, 2)
+                error(msg!(BuildFmtter, formatter, e));
+                help("translate", &opts, 2)
             },
         }
     }
+    let no_value = |fmt: &Fmtter| {
+        fmt.value_slots().next().is_none() && fmt.named_slots().next().is_none()
+    };
+    if formats.iter().all(no_value) {
+        warning(msg!(NoValueFormat));
+    }
+    cfg.format = Formatter::new(formats);
+    if cfg.stream && cfg.format.fmtters().len() > 1 {
+        error(msg!(OneFormat, "--stream"));
+        help("translate", &opts, 2)
+    }
 
-    let filename = match &parsed.free[..] {
-        [name] => name,
-        [] => {
-            eprintln!("Error: free argument missing");
-            help(&opts, 2);
+    if let Some(path) = parsed.opt_str("from-json") {
+        if let Some(arg) = free.first() {
+            error(msg!(UnexpectedArgument, arg));
+            help("translate", &opts, 2)
         }
-        [_, args @ ..] => {
-            eprintln!("Error: unexpected free arguments {args:?}");
-            help(&opts, 2);
+        let conflicts = [
+            "l", "head-bytes", "stream", "low-mem", "stdin-separator", "markdown",
+            "diff", "color-diff", "count-only", "ndjson", "in-place", "out-dir",
+            "recursive", "glob", "jobs", "dry-run", "progress", "stats", "stats-json",
+            "log-file",
+        ];
+        if let Some(name) = conflicts.iter().find(|&&x| parsed.opt_present(x)) {
+            let dash = if name.len() == 1 { "-" } else { "--" };
+            error(msg!(Conflicts, "--from-json", format!("{dash}{name}")));
+            help("translate", &opts, 2)
         }
-    };
+        cfg.from_json = true;
+        free.push(path);
+    }
+    if !default_to_stdin(&mut free) {
+        error(msg!(NoInput));
+        help("translate", &opts, 2);
+    }
+    if cfg.in_place.is_some() && free.iter().any(|x| x == "-") {
+        error(msg!(InPlaceStdin));
+        help("translate", &opts, 2);
+    }
+    #[cfg(feature = "watch")]
+    if parsed.opt_present("watch") {
+        if free.len() != 1 || free[0] == "-" || Path::new(&free[0]).is_dir() {
+            error(msg!(WatchOneFile));
+            help("translate", &opts, 2)
+        }
+        let conflicts = [
+            "in-place", "dry-run", "count-only", "recursive", "stream", "low-mem",
+            "jobs", "from-json",
+        ];
+        if let Some(name) = conflicts.iter().find(|&&x| parsed.opt_present(x)) {
+            error(msg!(Conflicts, "--watch", format!("--{name}")));
+            help("translate", &opts, 2)
+        }
+        let debounce = parsed.opt_get_default("watch-debounce", 300)
+            .unwrap_or_else(|e| {
+                error(msg!(ParseInt, e));
+                help("translate", &opts, 2)
+            });
+        cfg.watch = Some(WatchConfig {
+            debounce: std::time::Duration::from_millis(debounce),
+            sep: parsed.opt_str("watch-sep"),
+        });
+    } else if let Some(name) = ["watch-debounce", "watch-sep"].iter()
+        .find(|&&x| parsed.opt_present(x))
+    {
+        error(msg!(Requires, format!("--{name}"), "--watch"));
+        help("translate", &opts, 2)
+    }
+    cfg.inputs = free;
 
-    let mut reader = match &**filename {
-        "-" => Left(stdin().lock()),
-        path => {
-            Right(BufReader::new(File::open(path).unwrap_or_else(|e| {
-                eprintln!("Error: open file error `{e}`");
-                exit(3)
-            })))
-        },
-    };
+    cfg
+}
 
-    cfg.text.clear();
-    let err = if parsed.opt_present("line") {
-        reader.read_line(&mut cfg.text)
-    } else {
-        reader.read_to_string(&mut cfg.text)
-    };
-    if let Err(e) = err {
-        eprintln!("Error: read text error `{e}`");
-        exit(3)
-    };
-    cfg.text = (&*cfg.text).filter_out_long_empty(cfg.long_empty_count);
 
-    cfg
+/// 没有输入文件且 stdin 不是终端时读取 stdin, 返回是否有输入
+fn default_to_stdin(free: &mut Vec<String>) -> bool {
+    if free.is_empty() && !stdin().is_terminal() {
+        free.push("-".into());
+    }
+    !free.is_empty()
 }
 
+/// 未指定格式时的默认格式, 输出到文件或非终端时仅输出译文
+fn default_format(to_file: bool, is_tty: bool) -> &'static str {
+    if to_file || !is_tty {
+        DST_ONLY_FORMAT
+    } else {
+        DEFAULT_OUT_FORMAT
+    }
+}
 
-/// 格式化返回的 json 数据
-#[inline]
-fn format_out(fmtters: &Vec<Fmtter>, object: JSONData) -> Result<Vec<String>, String> {
-    if let Some(lines) = object.get("trans_result") {
-        let lines = lines.as_array().unwrap();
-        let mut strs: Vec<[&str; 2]> = Vec::with_capacity(lines.len());
-        for line in lines {
-            let line = line.as_object().unwrap();
-            strs.push([
-                      line.get("dst").unwrap().as_str().unwrap(),
-                      line.get("src").unwrap().as_str().unwrap()
-            ]);
-        }
-        // formats
-        let mut res_lines: Vec<String>
-            = Vec::with_capacity(strs.len() * fmtters.len());
-        for fmtter in fmtters.iter() {
-            for item in strs.iter() {
-                res_lines.push(fmtter.fmt_str(item))
+/// 列出所有输入文件及其输出位置, 两个输入写入同一个文件时报错
+fn plan_jobs(cfg: &Config) -> Result<Vec<Job>, String> {
+    let mut jobs = vec![];
+    for input in &cfg.inputs {
+        let path = Path::new(input);
+        if !path.is_dir() {
+            if cfg.glob.is_some() {
+                return Err(msg!(GlobNotDirectory, input));
             }
+            let output = if cfg.in_place.is_some() {
+                Some(path.into())
+            } else {
+                cfg.out_dir.as_ref()
+                    .filter(|_| input != "-")
+                    .map(|dir| dir.join(path.file_name().unwrap_or_default()))
+            };
+            jobs.push(Job { input: path.into(), output });
+            continue;
+        }
+        if !cfg.recursive {
+            return Err(msg!(IsDirectory, input));
+        }
+        if cfg.out_dir.is_none() && cfg.in_place.is_none() {
+            return Err(msg!(RequiresEither, "--recursive", "--out-dir", "--in-place"));
+        }
+        let options = MatchOptions::default();
+        for entry in WalkDir::new(path).sort_by_file_name() {
+            let entry = entry.map_err(|e| e.to_string())?;
+            if !entry.file_type().is_file() { continue }
+            let relative = entry.path().strip_prefix(path)
+                .expect("walk entry outside of root");
+            // 跳过之前 `--in-place` 生成的备份
+            if cfg.in_place.as_deref().is_some_and(|suffix| {
+                !suffix.is_empty() && entry.file_name().to_string_lossy().ends_with(suffix)
+            }) {
+                continue;
+            }
+            if cfg.glob.as_ref().is_some_and(|glob| {
+                !glob.matches_path_with(relative, options)
+            }) {
+                continue;
+            }
+            let output = match &cfg.out_dir {
+                Some(out_dir) => out_dir.join(relative),
+                None => entry.path().into(),
+            };
+            jobs.push(Job {
+                input: entry.path().into(),
+                output: Some(output),
+            });
         }
-        Ok(res_lines)
-    } else {
-        Err(format!("result data error: {:#?}", object))
     }
+    let mut inputs = HashMap::new();
+    for job in &jobs {
+        let Some(output) = &job.output else { continue };
+        if let Some(other) = inputs.insert(output, &job.input) {
+            let [a, b, c] = [other, &job.input, output].map(|path| path.display());
+            return Err(msg!(SameOutput, a, b, c));
+        }
+    }
+    Ok(jobs)
 }
 
-
-#[tokio::main]
-async fn main() {
-    let cfg = get_cfg();
-    let [id, key] = get_id_and_key();
-    let mut translater = Translater::new(&id, &key);
-    if let Some(x) = &cfg.from_lang {
-        translater.set_from_lang(x);
+/// 输出是否比输入更新
+fn is_up_to_date(input: &Path, output: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path)?.modified();
+    match (modified(input), modified(output)) {
+        (Ok(input), Ok(output)) => output > input,
+        _ => false,
     }
-    if let Some(x) = &cfg.to_lang {
-        translater.set_to_lang(x);
+}
+
+/// 读取输入文件, `-` 为 stdin
+fn read_text(cfg: &Config, path: &Path) -> io::Result<String> {
+    let is_stdin = path == Path::new("-");
+    if !is_stdin && cfg.head.is_none() && cfg.max_input_bytes != 0 {
+        let len = fs::metadata(path)?.len();
+        if len > cfg.max_input_bytes {
+            return Err(input_too_large(cfg.max_input_bytes, Some(len)));
+        }
     }
-    let result: JSONData
-        = translater.translate(cfg.text).await;
-    match format_out(&cfg.format, result) {
-        Ok(msg) => {
-            for line in msg {
-                print!("{}", line)
-            }
+    let mut reader = if is_stdin {
+        Left(stdin().lock())
+    } else {
+        Right(BufReader::new(File::open(path)?))
+    };
+    let mut text = if let Some(encoding) = cfg.encoding {
+        let limit = if cfg.head.is_some() { 0 } else { cfg.max_input_bytes };
+        let bytes = read_all(&mut reader, limit)?;
+        let text = encoding::decode(&bytes, encoding).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, msg!(TryEncoding, e))
+        })?;
+        match cfg.head {
+            Some(head) => read_head(&mut text.as_bytes(), head)?,
+            None => text,
+        }
+    } else {
+        let bytes = if let Some(head) = cfg.head {
+            read_head_bytes(&mut reader, head)?
+        } else {
+            read_all(&mut reader, cfg.max_input_bytes)?
+        };
+        if cfg.lossy {
+            let (text, count) = encoding::decode_lossy(&bytes);
+            if count != 0 {
+                warning(msg!(ReplacedInvalid, path.display(), count));
+            }
+            text
+        } else {
+            encoding::decode_utf8(bytes).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, msg!(TryLossy, e))
+            })?
+        }
+    };
+    let bom = text.len() - newline::strip_bom(&text).len();
+    text.drain(..bom);
+    if cfg.newline != Newline::Keep {
+        text = newline::to_lf(&text);
+    }
+    if should_strip_ansi(cfg, is_stdin) {
+        let stripped = strip_ansi(&text);
+        if cfg.verbose {
+            eprintln!("{}", msg!(StrippedAnsi, text.len() - stripped.len()));
+        }
+        text = stripped;
+    }
+    Ok(text)
+}
+
+/// 读取全部输入, 超过 `limit` 字节时报错, `limit` 为 0 时不限制
+fn read_all(reader: &mut impl Read, limit: u64) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    if limit == 0 {
+        reader.read_to_end(&mut bytes)?;
+    } else if reader.take(limit + 1).read_to_end(&mut bytes)? as u64 > limit {
+        return Err(input_too_large(limit, None));
+    }
+    Ok(bytes)
+}
+
+/// 输入超过 `--max-input-bytes` 的错误, `len` 为已知的输入大小
+fn input_too_large(limit: u64, len: Option<u64>) -> io::Error {
+    let msg = match len {
+        Some(len) => msg!(InputTooLargeLen, len, limit),
+        None => msg!(InputTooLarge, limit),
+    };
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// 是否去除输入中的 ANSI 转义序列
+fn should_strip_ansi(cfg: &Config, is_stdin: bool) -> bool {
+    match cfg.strip_ansi {
+        StripAnsi::Auto => is_stdin && !stdin().is_terminal(),
+        StripAnsi::Always => true,
+        StripAnsi::Never => false,
+    }
+}
+
+/// 预处理待翻译的文本
+fn prepare_input(cfg: &Config, mut text: String) -> Input {
+    let protected = cfg.markdown.then(|| {
+        let protected = markdown::protect(&text);
+        text.clone_from(&protected.text);
+        protected
+    });
+    filter_text(cfg, &mut text);
+    Input { text, protected }
+}
+
+
+/// 按照配置去除每行首尾的空白, 并过滤输入文本中多余的空白符
+fn filter_text(cfg: &Config, text: &mut String) {
+    if cfg.trim_mode != TrimMode::None {
+        *text = trim_lines(text, cfg.trim_mode);
+    }
+    if let Some(count) = cfg.long_empty_count {
+        *text = (&**text).filter_out_long_empty(count);
+    }
+}
+
+
+/// 还原 Markdown 占位符,
+/// 对占位符被翻译损坏的行, 以占位符分割后单独翻译各个文本片段
+async fn restore_markdown(
+    translater: &Translater<'_>,
+    protected: &Protected,
+    result: &mut TransResult,
+) -> Result<(), TranslateError> {
+    let mut broken = vec![];
+    for (i, line) in result.lines.iter_mut().enumerate() {
+        match protected.restore_line(&line.src, &line.dst) {
+            Some(dst) => line.dst = dst,
+            None => broken.push(i),
+        }
+    }
+    if !broken.is_empty() {
+        let pieces = broken.iter()
+            .flat_map(|&i| protected.segments(&result.lines[i].src))
+            .filter_map(|seg| match seg {
+                Segment::Text(text) if !text.trim().is_empty() => {
+                    Some(text.trim())
+                },
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let object = translater.translate(pieces).await?;
+        let pieces = TransResult::from_json(&object)?;
+        let mut pieces = pieces.lines.into_iter().map(|line| line.dst);
+        for &i in &broken {
+            let dst = protected.segments(&result.lines[i].src).into_iter()
+                .map(|seg| match seg {
+                    Segment::Text(text) if text.trim().is_empty() => {
+                        text.to_owned()
+                    },
+                    Segment::Text(text) => {
+                        let trimmed = text.trim_start();
+                        let start = &text[..text.len()-trimmed.len()];
+                        let end = &trimmed[trimmed.trim_end().len()..];
+                        let dst = pieces.next().unwrap_or_default();
+                        format!("{start}{dst}{end}")
+                    },
+                    Segment::Span(id) => protected.spans[id].clone(),
+                })
+                .collect();
+            result.lines[i].dst = dst;
+        }
+    }
+    for line in &mut result.lines {
+        line.src = protected.restore(&line.src);
+    }
+    Ok(())
+}
+
+
+/// 按块翻译并格式化输入, 返回块数与失败的块的下标,
+/// 所有块都失败或 `--fail-fast` 时返回错误
+///
+/// 未指定 `--no-dedup` 时相同的行只发送一次, 块下标为去重后的块
+async fn translate_input(
+    translater: &Translater<'_>,
+    cfg: &Config,
+    input: Input,
+    out: &mut impl Write,
+) -> Result<Translated, TranslateError> {
+    if cfg.diff {
+        let diff = translater.translate_diff(&input.text).await?;
+        write!(out, "{diff}").map_err(TranslateError::Io)?;
+        return Ok(Translated::whole(None));
+    }
+    let deduped = cfg.dedup.then(|| Deduped::new(&input.text));
+    let text = deduped.as_ref().map_or(input.text.as_str(), |deduped| &deduped.text);
+    let mut blocks = split_text_blocks(cfg, text);
+    let protected = input.protected.as_ref();
+    let (mut session, mut failed) = request_blocks(translater, cfg, &blocks, protected).await?;
+    let mut requested = session.blocks.len();
+    let mut dedup_chars = 0;
+    if let Some(deduped) = &deduped {
+        match deduped.expand(&blocks, &session) {
+            Some(expanded) => {
+                session = expanded;
+                dedup_chars = deduped.saved_chars;
+            },
+            None => {
+                // 译文行数与原文不符, 无法对应重复的行, 改为不去重重新翻译
+                warning(msg!(DedupMismatch));
+                blocks = split_text_blocks(cfg, &input.text);
+                (session, failed) = request_blocks(translater, cfg, &blocks, protected).await?;
+                requested = session.blocks.len();
+            },
+        }
+    }
+    if cfg.count {
+        let (lines, src, dst) = count_result(&session.merged());
+        writeln!(out, "lines: {lines}, src_chars: {src}, dst_chars: {dst}")
+            .map_err(TranslateError::Io)?;
+    } else {
+        write_session(cfg, &session, out)?;
+    }
+    let changed_ratio = Some(lines_changed_ratio(&session.merged()));
+    Ok(Translated { blocks: requested, failed, changed_ratio, dedup_chars })
+}
+
+/// 按 `--limit-unit` 分块, 去掉空白的块
+fn split_text_blocks<'a>(cfg: &Config, text: &'a str) -> Vec<&'a str> {
+    let mut blocks = split_text_blocks_by(text, cfg.limit_unit);
+    blocks.retain(|block| !block.trim().is_empty());
+    blocks
+}
+
+/// 逐块请求翻译, 返回各块的结果与失败或只有部分结果的块的下标,
+/// 所有块都失败或 `--fail-fast` 时返回错误
+async fn request_blocks(
+    translater: &Translater<'_>,
+    cfg: &Config,
+    blocks: &[&str],
+    protected: Option<&Protected>,
+) -> Result<(Session, Vec<usize>), TranslateError> {
+    let mut session = Session::default();
+    let mut first_err = None;
+    // 接口报错但仍返回了部分结果的块
+    let mut partial = vec![];
+    for &block in blocks {
+        let result = translate_block(translater, cfg, block, protected).await;
+        if let (Err(TranslateError::ApiError(_, body)), true) = (&result, cfg.verbose) {
+            eprintln!("{}", msg!(Response, serde_json::to_string(body).unwrap_or_default()));
+        }
+        match result {
+            Ok(result) => session.blocks.push(Ok(result)),
+            Err(e) if cfg.fail_fast => return Err(e),
+            Err(e) => match e.partial() {
+                Some(mut result) => {
+                    error(i18n::translate_error(lang(), &e));
+                    if finish_result(translater, cfg, protected, &mut result).await.is_ok() {
+                        partial.push(session.blocks.len());
+                        session.blocks.push(Ok(result));
+                    } else {
+                        session.blocks.push(Err(e.code()));
+                        first_err.get_or_insert(e);
+                    }
+                },
+                None => {
+                    session.blocks.push(Err(e.code()));
+                    first_err.get_or_insert(e);
+                },
+            },
+        }
+    }
+    let mut failed = session.failed();
+    if let Some(e) = first_err.filter(|_| failed.len() == session.blocks.len()) {
+        return Err(e);
+    }
+    failed.extend(partial);
+    failed.sort_unstable();
+    Ok((session, failed))
+}
+
+/// [`translate_input`] 的结果
+struct Translated {
+    blocks: usize,
+    /// 失败或只有部分结果的块的下标
+    failed: Vec<usize>,
+    /// 译文与原文不同的行的比例, `--diff` 时为 `None`
+    changed_ratio: Option<f64>,
+    /// 去重后未发送的字符数
+    dedup_chars: usize,
+}
+impl Translated {
+    /// 整个输入作为一个块成功翻译
+    fn whole(changed_ratio: Option<f64>) -> Self {
+        Self { blocks: 1, failed: vec![], changed_ratio, dedup_chars: 0 }
+    }
+}
+
+/// `--assert-translated` 的检查
+fn check_translated(cfg: &Config, changed_ratio: f64) -> Result<(), (i32, String)> {
+    let Some(min) = cfg.assert_translated else { return Ok(()) };
+    if changed_ratio == 0.0 {
+        return Err((UNTRANSLATED_CODE, msg!(SameAsInput)));
+    }
+    let percent = changed_ratio * 100.0;
+    if percent < min {
+        let reason = msg!(TooFewTranslated, format!("{percent:.1}"), min);
+        return Err((UNTRANSLATED_CODE, reason));
+    }
+    Ok(())
+}
+
+/// 按 `--side-by-side`, `--color-diff` 或格式输出各块的结果, 失败的块输出错误标记
+fn write_session(
+    cfg: &Config,
+    session: &Session,
+    out: &mut impl Write,
+) -> Result<(), TranslateError> {
+    if cfg.side_by_side.is_some() || cfg.color_diff {
+        for block in &session.blocks {
+            let rendered = match (block, cfg.side_by_side) {
+                (Ok(result), Some(width)) => side_by_side::render(result, width, cfg.color),
+                (Ok(result), None) => diff::render_result(result, cfg.color),
+                (Err(code), _) => error_marker(&cfg.error_marker, code),
+            };
+            out.write_all(rendered.as_bytes()).map_err(TranslateError::Io)?;
+        }
+        Ok(())
+    } else {
+        cfg.format.write_session(session, &cfg.error_marker, out)
+    }
+}
+
+/// 翻译一个块, 并还原 Markdown 占位符
+async fn translate_block(
+    translater: &Translater<'_>,
+    cfg: &Config,
+    block: &str,
+    protected: Option<&Protected>,
+) -> Result<TransResult, TranslateError> {
+    // 如英文 locale 下翻译英文时, 改用其它目标语言
+    let with_to = |to| {
+        let mut translater = translater.clone();
+        translater.set_to_lang(to);
+        translater
+    };
+    let mut fallback = cfg.to_fallback.get().map(|&to| with_to(to));
+    let mut result = request_block(fallback.as_ref().unwrap_or(translater), cfg, block).await?;
+    if fallback.is_none() && cfg.to_from_locale && result.from == translater.to_lang() {
+        let to = *cfg.to_fallback.get_or_init(|| default_to_lang_for(&result.from));
+        result = request_block(fallback.insert(with_to(to)), cfg, block).await?;
+    }
+    let translater = fallback.as_ref().unwrap_or(translater);
+    if cfg.from_detect_only && translater.lock_from_lang(&result.from) {
+        eprintln!("{}", msg!(DetectedLang, result.from));
+    }
+    finish_result(translater, cfg, protected, &mut result).await?;
+    Ok(result)
+}
+
+/// 发送一个块的请求, `--progress` 时输出进度
+async fn request_block(
+    translater: &Translater<'_>,
+    cfg: &Config,
+    block: &str,
+) -> Result<TransResult, TranslateError> {
+    let object = if cfg.progress {
+        let (tx, mut rx) = watch::channel(TranslateProgress::Connecting);
+        let printer = tokio::spawn(async move {
+            while rx.changed().await.is_ok() {
+                eprintln!("{}", msg!(Progress, *rx.borrow_and_update()));
+            }
+        });
+        let object = translater.translate_with_watch(block.into(), tx).await;
+        printer.await.expect("progress printer panicked");
+        object?
+    } else {
+        translater.translate(block.into()).await?
+    };
+    Ok(TransResult::from_json(&object)?)
+}
+
+/// 还原 Markdown 占位符并按 `--wrap` 折行, 出错块的部分结果同样需要
+async fn finish_result(
+    translater: &Translater<'_>,
+    cfg: &Config,
+    protected: Option<&Protected>,
+    result: &mut TransResult,
+) -> Result<(), TranslateError> {
+    if let Some(protected) = protected {
+        restore_markdown(translater, protected, result).await?;
+    }
+    if let Some(width) = cfg.wrap {
+        wrap_result(result, width);
+    }
+    Ok(())
+}
+
+/// 翻译错误对应的退出码
+fn error_code(e: &TranslateError) -> i32 {
+    match e {
+        TranslateError::Io(_) => 3,
+        TranslateError::ApiError(..) => 5,
+        _ => 4,
+    }
+}
+
+/// 翻译错误的退出码与以界面语言描述的错误信息
+fn job_error(e: &TranslateError) -> (i32, String) {
+    (error_code(e), i18n::translate_error(lang(), e))
+}
+
+/// 先写入同目录下的临时文件, 再重命名覆盖目标文件,
+/// 若 `suffix` 不为空则先将原文件备份为 `文件名+suffix`
+fn write_in_place(path: &Path, data: &[u8], suffix: &str) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = path.with_file_name(format!(
+        ".{name}.{}.{}.tmp",
+        env!("CARGO_PKG_NAME"),
+        std::process::id(),
+    ));
+    let write = || {
+        fs::write(&tmp, data)?;
+        fs::set_permissions(&tmp, fs::metadata(path)?.permissions())?;
+        if !suffix.is_empty() {
+            fs::copy(path, path.with_file_name(format!("{name}{suffix}")))?;
+        }
+        fs::rename(&tmp, path)
+    };
+    write().inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// 翻译一个文件, 本应输出到 stdout 的内容写入 `out`,
+/// 失败时返回退出码与错误信息
+async fn run_job(
+    translater: &Translater<'_>,
+    cfg: &Config,
+    job: &Job,
+    stats: Option<&Mutex<Stats>>,
+    out: &mut impl Write,
+) -> Result<(), (i32, String)> {
+    // 每个输入分别检测并锁定源语言
+    let unlocked;
+    let translater = if cfg.from_detect_only {
+        let mut cloned = translater.clone();
+        cloned.unlock_from_lang();
+        unlocked = cloned;
+        &unlocked
+    } else {
+        translater
+    };
+    if cfg.stream {
+        return run_stream(translater, cfg, job, stats, out).await;
+    }
+    let text = match read_text(cfg, &job.input) {
+        // `-l` 未读到非空行
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            return skip_empty(cfg, job, &e.to_string());
+        },
+        Err(e) => return Err((3, msg!(ReadText, e))),
+        Ok(text) if text.trim().is_empty() => {
+            return skip_empty(cfg, job, &msg!(InputEmpty));
+        },
+        Ok(text) => text,
+    };
+    let failed = if job.output.is_none()
+        && cfg.newline != Newline::Crlf
+        && !cfg.ndjson
+        && cfg.output_encoding.is_none()
+    {
+        // 直接输出到 stdout, 不缓存整个结果
+        translate_text(translater, cfg, job, &text, stats, out).await?
+    } else {
+        // 输出到 stdout 时首尾由 [`Frame`] 在所有输入前后输出一次
+        let framed = job.output.is_some();
+        let mut buf = Vec::new();
+        if framed {
+            write_const(&mut buf, cfg.prepend.as_ref())?;
+        }
+        let failed = if cfg.ndjson {
+            let out = translate_ndjson(translater, cfg, &text, stats).await?;
+            buf.extend_from_slice(out.as_bytes());
+            vec![]
+        } else {
+            translate_text(translater, cfg, job, &text, stats, &mut buf).await?
+        };
+        if framed {
+            write_const(&mut buf, cfg.append.as_ref())?;
+        }
+        let out_text = String::from_utf8(buf).expect("formatted output is utf-8");
+        write_output(cfg, job, out_text, out)?;
+        failed
+    };
+    check_failed(&failed)
+}
+
+/// `--from-json` 时按输出选项格式化保存的接口响应, 不发送请求
+fn run_from_json(
+    cfg: &Config,
+    job: &Job,
+    out: &mut impl Write,
+) -> Result<(), (i32, String)> {
+    let text = read_text(cfg, &job.input)
+        .map_err(|e| (3, msg!(ReadText, e)))?;
+    let mut session = response::parse_saved(&text)
+        .map_err(|(i, e)| (4, msg!(SavedResponse, i, e)))?;
+    if session.blocks.is_empty() {
+        return skip_empty(cfg, job, &msg!(InputEmpty));
+    }
+    if let Some(width) = cfg.wrap {
+        for result in session.blocks.iter_mut().flatten() {
+            wrap_result(result, width);
+        }
+    }
+    let mut buf = Vec::new();
+    write_const(&mut buf, cfg.prepend.as_ref())?;
+    if cfg.count {
+        let (lines, src, dst) = count_result(&session.merged());
+        writeln!(buf, "lines: {lines}, src_chars: {src}, dst_chars: {dst}")
+            .map_err(|e| (3, msg!(Write, e)))?;
+    } else {
+        write_session(cfg, &session, &mut buf)
+            .map_err(|e| job_error(&e))?;
+    }
+    write_const(&mut buf, cfg.append.as_ref())?;
+    let out_text = String::from_utf8(buf).expect("formatted output is utf-8");
+    write_output(cfg, job, out_text, out)?;
+    check_translated(cfg, lines_changed_ratio(&session.merged()))?;
+    check_failed(&session.failed())
+}
+
+/// 有失败的块时返回错误
+fn check_failed(failed: &[usize]) -> Result<(), (i32, String)> {
+    if !failed.is_empty() {
+        let blocks = failed.iter()
+            .map(|i| format!("#{}", i + 1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err((7, msg!(BlocksFailed, blocks)));
+    }
+    Ok(())
+}
+
+/// `--stream` 与 `--low-mem` 中逐块输出的状态
+#[derive(Default)]
+struct StreamOutput {
+    blocks: usize,
+    failed: Vec<usize>,
+    first_err: Option<TranslateError>,
+    /// 没有返回部分结果的失败块数
+    hard_failed: usize,
+}
+impl StreamOutput {
+    /// 输出一个块的结果, `--fail-fast` 时返回块的错误
+    fn write(
+        &mut self,
+        cfg: &Config,
+        result: Result<TransResult, TranslateError>,
+        out: &mut impl Write,
+    ) -> Result<(), TranslateError> {
+        if let (Err(TranslateError::ApiError(_, body)), true) = (&result, cfg.verbose) {
+            eprintln!("{}", msg!(Response, serde_json::to_string(body).unwrap_or_default()));
+        }
+        let block = match result {
+            Ok(result) => Ok(result),
+            Err(e) if cfg.fail_fast => return Err(e),
+            Err(e) => {
+                self.failed.push(self.blocks);
+                match e.partial() {
+                    Some(mut result) => {
+                        error(i18n::translate_error(lang(), &e));
+                        // 流式翻译不支持 `--markdown`, 只需折行
+                        if let Some(width) = cfg.wrap {
+                            wrap_result(&mut result, width);
+                        }
+                        Ok(result)
+                    },
+                    None => {
+                        let code = e.code();
+                        self.hard_failed += 1;
+                        self.first_err.get_or_insert(e);
+                        Err(code)
+                    },
+                }
+            },
+        };
+        let session = Session { blocks: vec![block] };
+        write_session(cfg, &session, out)?;
+        self.blocks += 1;
+        Ok(())
+    }
+
+    /// 所有块输出后调用
+    fn finish(self, cfg: &Config, job: &Job) -> Result<(), (i32, String)> {
+        if self.blocks == 0 {
+            return skip_empty(cfg, job, &msg!(InputEmpty));
+        }
+        if let Some(e) = self.first_err.filter(|_| self.hard_failed == self.blocks) {
+            return Err(job_error(&e));
+        }
+        check_failed(&self.failed)
+    }
+}
+
+/// 按行读取输入的 [`BlockReader`], 过滤空白符之前统一换行符,
+/// 按需去除 ANSI 转义与每行首尾的空白, 顺序与 [`read_text`] 和 [`filter_text`] 相同
+fn block_reader<R: io::BufRead>(cfg: &Config, is_stdin: bool, reader: R) -> BlockReader<R> {
+    let to_lf = cfg.newline != Newline::Keep;
+    let strip = should_strip_ansi(cfg, is_stdin);
+    let trim_mode = cfg.trim_mode;
+    BlockReader::new(reader)
+        .map_lines(move |mut line| {
+            if to_lf {
+                line = newline::to_lf(&line);
+            }
+            if strip {
+                line = strip_ansi(&line);
+            }
+            if trim_mode != TrimMode::None {
+                line = trim_lines(&line, trim_mode);
+            }
+            line
+        })
+        .filter(cfg.long_empty_count)
+        .lossy(cfg.lossy)
+        .unit(cfg.limit_unit)
+}
+
+/// `--stream` 时逐块读取并翻译输入, 每块翻译后立即输出,
+/// 只在内存中保留一个块
+async fn run_stream(
+    translater: &Translater<'_>,
+    cfg: &Config,
+    job: &Job,
+    stats: Option<&Mutex<Stats>>,
+    out: &mut impl Write,
+) -> Result<(), (i32, String)> {
+    if let Some(depth) = cfg.low_mem {
+        return run_low_mem(translater, cfg, job, stats, out, depth).await;
+    }
+    let read_error = |e| (3, msg!(ReadText, e));
+    let is_stdin = job.input == Path::new("-");
+    let reader = if is_stdin {
+        Left(stdin().lock())
+    } else {
+        Right(BufReader::new(File::open(&job.input).map_err(read_error)?))
+    };
+    let mut reader = block_reader(cfg, is_stdin, reader);
+    let mut output = StreamOutput::default();
+    for block in reader.by_ref() {
+        let block = block.map_err(read_error)?;
+        if block.trim().is_empty() { continue }
+        if let Some(stats) = stats {
+            stats.lock().unwrap().record_input(&block);
+        }
+        let result = translate_block(translater, cfg, &block, None).await;
+        output.write(cfg, result, out)
+            .map_err(|e| job_error(&e))?;
+    }
+    if reader.replaced() != 0 {
+        warning(msg!(ReplacedInvalid, job.input.display(), reader.replaced()));
+    }
+    output.finish(cfg, job)
+}
+
+/// `--low-mem` 时读取, 翻译与输出同时进行, 至多 `depth` 个块同时翻译,
+/// 内存中的块数不超过 `2 * depth + 1`
+async fn run_low_mem(
+    translater: &Translater<'_>,
+    cfg: &Config,
+    job: &Job,
+    stats: Option<&Mutex<Stats>>,
+    out: &mut impl Write,
+    depth: usize,
+) -> Result<(), (i32, String)> {
+    let is_stdin = job.input == Path::new("-");
+    let reader = if is_stdin {
+        Left(BufReader::new(stdin()))
+    } else {
+        let file = File::open(&job.input)
+            .map_err(|e| (3, msg!(ReadText, e)))?;
+        Right(BufReader::new(file))
+    };
+    let reader = block_reader(cfg, is_stdin, reader);
+    let gauge = Arc::new(BlockGauge::default());
+    let mut output = StreamOutput::default();
+    let translate = |block: String| async move {
+        if block.trim().is_empty() { return None }
+        if let Some(stats) = stats {
+            stats.lock().unwrap().record_input(&block);
+        }
+        Some(translate_block(translater, cfg, &block, None).await)
+    };
+    let write = |result: Option<_>| match result {
+        Some(result) => output.write(cfg, result, out),
+        None => Ok(()),
+    };
+    let reader = pipeline(reader, depth, gauge.clone(), translate, write).await
+        .map_err(|e| match e {
+            PipelineError::Read(e) => (3, msg!(ReadText, e)),
+            PipelineError::Write(e) => job_error(&e),
+        })?;
+    if reader.replaced() != 0 {
+        warning(msg!(ReplacedInvalid, job.input.display(), reader.replaced()));
+    }
+    if cfg.verbose {
+        eprintln!("{}", msg!(BlocksInMemory, job.input.display(), gauge.peak()));
+    }
+    output.finish(cfg, job)
+}
+
+/// 跳过空的输入, 不发送请求
+fn skip_empty(cfg: &Config, job: &Job, reason: &str) -> Result<(), (i32, String)> {
+    if cfg.fail_empty {
+        return Err((EMPTY_INPUT_CODE, reason.into()));
+    }
+    if cfg.verbose {
+        eprintln!("{}", msg!(Skipped, job.input.display(), reason));
+    }
+    Ok(())
+}
+
+/// 输出 `--prepend` 或 `--append` 的文本
+fn write_const(
+    out: &mut impl Write,
+    fmtter: Option<&Fmtter>,
+) -> Result<(), (i32, String)> {
+    let Some(fmtter) = fmtter else { return Ok(()) };
+    out.write_all(fmtter.fmt_str::<&str>(&[]).as_bytes())
+        .map_err(|e| (3, msg!(Write, e)))
+}
+
+/// 按 `--newline` 与 `--output-encoding` 转换输出的文本
+fn encode_text(cfg: &Config, mut out: String) -> Vec<u8> {
+    if cfg.newline == Newline::Crlf {
+        out = newline::to_crlf(&out);
+    }
+    match cfg.output_encoding {
+        Some(encoding) => encoding::encode_output(&out, encoding),
+        None => out.into_bytes(),
+    }
+}
+
+/// 输出到 stdout 的所有输入共用一组 `--prepend` 与 `--append`,
+/// 第一次写入前输出开头, [`Frame::finish`] 时若有过输出则输出结尾
+struct Frame {
+    prepend: Vec<u8>,
+    append: Vec<u8>,
+    started: AtomicBool,
+}
+impl Frame {
+    fn new(cfg: &Config) -> Self {
+        let text = |fmtter: Option<&Fmtter>| {
+            let text = fmtter.map(|fmtter| fmtter.fmt_str::<&str>(&[]));
+            encode_text(cfg, text.unwrap_or_default())
+        };
+        Self {
+            prepend: text(cfg.prepend.as_ref()),
+            append: text(cfg.append.as_ref()),
+            started: AtomicBool::new(false),
+        }
+    }
+
+    /// 在 `out` 的第一次写入前输出开头
+    fn writer<W: Write>(&self, out: W) -> Framed<'_, W> {
+        Framed { frame: self, out }
+    }
+
+    fn finish(&self, out: &mut impl Write) -> Result<(), (i32, String)> {
+        if self.started.load(Ordering::Relaxed) {
+            out.write_all(&self.append).map_err(|e| (3, msg!(Write, e)))?;
+        }
+        Ok(())
+    }
+}
+
+/// 见 [`Frame::writer`]
+struct Framed<'a, W> {
+    frame: &'a Frame,
+    out: W,
+}
+impl<W: Write> Write for Framed<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() && !self.frame.started.swap(true, Ordering::Relaxed) {
+            self.out.write_all(&self.frame.prepend)?;
+        }
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// 将结果写入 `job` 的输出位置, 没有输出文件时写入 `stdout`
+fn write_output(
+    cfg: &Config,
+    job: &Job,
+    out: String,
+    stdout: &mut impl Write,
+) -> Result<(), (i32, String)> {
+    let out = encode_text(cfg, out);
+    match &job.output {
+        Some(path) if cfg.in_place.is_some() => {
+            let suffix = cfg.in_place.as_deref().unwrap_or_default();
+            write_in_place(path, &out, suffix)
+                .map_err(|e| (3, msg!(WriteInPlace, e)))?;
+        },
+        Some(path) => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)
+                    .map_err(|e| (3, msg!(CreateDir, e)))?;
+            }
+            fs::write(path, out)
+                .map_err(|e| (3, msg!(WriteFile, e)))?;
+        },
+        None => stdout.write_all(&out)
+            .map_err(|e| (3, msg!(Write, e)))?,
+    }
+    Ok(())
+}
+
+/// 翻译一个文件的文本, 按 `--stdin-separator` 分段,
+/// `-0` 时每条记录单独翻译, 输出的每条记录以 NUL 结尾
+async fn translate_text(
+    translater: &Translater<'_>,
+    cfg: &Config,
+    job: &Job,
+    text: &str,
+    stats: Option<&Mutex<Stats>>,
+    out: &mut impl Write,
+) -> Result<Vec<usize>, (i32, String)> {
+    let separator = cfg.separator.as_deref()
+        .filter(|_| job.input == Path::new("-"));
+    let segments = match separator {
+        // 空记录同样输出, 使输出的记录与输入一一对应
+        _ if cfg.null_terminated => read_null_terminated(text.as_bytes())
+            .map_err(|e| (3, msg!(ReadText, e)))?,
+        Some(sep) => split_on_separator(text, sep).into_iter().map(str::to_owned).collect(),
+        None => vec![text.to_owned()],
+    };
+    let (mut blocks, mut failed) = (0, vec![]);
+    // 所有段输出后再报告第一个未通过 `--assert-translated` 的段
+    let mut asserted = Ok(());
+    let write_error = |e| (3, msg!(Write, e));
+    for (i, segment) in segments.into_iter().enumerate() {
+        if let (Some(sep), true) = (separator, i != 0) {
+            out.write_all(sep.as_bytes()).map_err(write_error)?;
+        }
+        let input = prepare_input(cfg, segment);
+        if let Some(stats) = stats {
+            stats.lock().unwrap().record_input(&input.text);
+        }
+        let translated = translate_input(translater, cfg, input, out)
+            .await
+            .map_err(|e| job_error(&e))?;
+        failed.extend(translated.failed.into_iter().map(|i| blocks + i));
+        blocks += translated.blocks;
+        if let Some(stats) = stats {
+            stats.lock().unwrap().dedup_chars += translated.dedup_chars;
+        }
+        if let Some(ratio) = translated.changed_ratio {
+            asserted = asserted.and(check_translated(cfg, ratio));
+        }
+        if cfg.null_terminated {
+            out.write_all(b"\0").map_err(write_error)?;
+        }
+    }
+    asserted?;
+    Ok(failed)
+}
+
+/// 翻译 NDJSON 记录, 按输入顺序输出加入译文的记录,
+/// 出错的记录输出为错误对象
+async fn translate_ndjson(
+    translater: &Translater<'_>,
+    cfg: &Config,
+    text: &str,
+    stats: Option<&Mutex<Stats>>,
+) -> Result<String, (i32, String)> {
+    let mut outs = vec![];
+    let mut records = vec![];
+    // 每条记录在输出中的位置
+    let mut slots = vec![];
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() { continue }
+        match ndjson::parse_record(i + 1, line, &cfg.text_field) {
+            Ok(record) => {
+                slots.push(outs.len());
+                records.push(record);
+                outs.push(None);
+            },
+            Err(err) if cfg.fail_fast => {
+                let msg = err["error"].as_str().unwrap_or_default();
+                return Err((2, msg!(RecordLine, i + 1, msg)))
+            },
+            Err(err) => outs.push(Some(err)),
+        }
+    }
+    let (from, to) = (translater.from_lang(), translater.to_lang());
+    for batch in ndjson::batches(&records, from, to, cfg.limit_unit) {
+        let mut translater = translater.clone();
+        translater.set_from_lang(&batch.from).set_to_lang(&batch.to);
+        let texts = batch.indexes.iter()
+            .map(|&i| &*records[i].text)
+            .collect::<Vec<_>>();
+        if let Some(stats) = stats {
+            stats.lock().unwrap().record_input(&texts.join("\n"));
+        }
+        match translate_lines(&translater, &texts).await {
+            Ok(results) => {
+                for (&i, (dst, from)) in batch.indexes.iter().zip(results) {
+                    let record = records[i].clone();
+                    outs[slots[i]] = Some(record.finish(&dst, &from));
+                }
+            },
+            Err(e) if cfg.fail_fast => {
+                let line = records[batch.indexes[0]].line;
+                return Err((error_code(&e), msg!(RecordLine, line, job_error(&e).1)))
+            },
+            Err(e) => {
+                for &i in &batch.indexes {
+                    let record = &records[i];
+                    let err = ndjson::error_object(
+                        record.id(),
+                        record.line,
+                        &e.to_string(),
+                    );
+                    outs[slots[i]] = Some(err);
+                }
+            },
+        }
+    }
+    Ok(outs.into_iter()
+        .map(|out| format!("{}\n", out.unwrap_or_default()))
+        .collect())
+}
+
+/// 在一次请求中翻译多条文本, 返回每条的译文与检测出的源语言,
+/// 结果行数与文本数不一致时逐条翻译
+async fn translate_lines(
+    translater: &Translater<'_>,
+    texts: &[&str],
+) -> Result<Vec<(String, String)>, TranslateError> {
+    let object = translater.translate(texts.join("\n")).await?;
+    let result = TransResult::from_json(&object)?;
+    if texts.len() == 1 {
+        let dst = result.lines.iter()
+            .map(|line| &*line.dst)
+            .collect::<Vec<_>>()
+            .join("\n");
+        return Ok(vec![(dst, result.from)]);
+    }
+    if result.lines.len() == texts.len() {
+        return Ok(result.lines.into_iter()
+            .map(|line| (line.dst, result.from.clone()))
+            .collect());
+    }
+    let mut res = Vec::with_capacity(texts.len());
+    for text in texts {
+        res.extend(Box::pin(translate_lines(translater, &[text])).await?);
+    }
+    Ok(res)
+}
+
+
+#[tokio::main]
+async fn main() {
+    let start = Instant::now();
+    match get_command() {
+        Command::Translate(cfg) => run_translate(start, &cfg).await,
+        Command::Detect(inputs) => run_detect(&inputs).await,
+        Command::Langs => {
+            for (code, name) in LANGS {
+                println!("{code:<5} {name}");
+            }
+        },
+        Command::Init { force, id_and_key } => run_init(force, id_and_key),
+        // 还没有翻译缓存, 总是为空
+        Command::Cache(CacheAction::Clear) => println!("{}", msg!(CacheNothingToClear)),
+        Command::Cache(CacheAction::Stats) => println!("{}", msg!(CacheEmpty)),
+    }
+}
+
+/// 读取 id 与 key, 失败时退出
+async fn load_id_and_key() -> [String; 2] {
+    get_id_and_key_async().await.unwrap_or_else(|e| {
+        error(e);
+        exit(3)
+    })
+}
+
+/// 输出每个输入的语言
+async fn run_detect(inputs: &[String]) {
+    let [id, key] = load_id_and_key().await;
+    let mut translater = Translater::new(&id, &key);
+    translater.set_salt_source(SaltSource::from_env());
+    let cfg = Config::default();
+    let mut code = 0;
+    for input in inputs {
+        let lang = match read_text(&cfg, Path::new(input)) {
+            Ok(text) => translater.detect(text.trim()).await
+                .map_err(|e| job_error(&e)),
+            Err(e) => Err((3, msg!(ReadText, e))),
+        };
+        match lang {
+            Ok(lang) if inputs.len() > 1 => println!("{input}: {lang}"),
+            Ok(lang) => println!("{lang}"),
+            Err((err_code, e)) => {
+                error(format!("{input}: {e}"));
+                code = err_code;
+            },
+        }
+    }
+    exit(code)
+}
+
+/// 写入配置文件, 未给出 id 与 key 时从 stdin 读取
+fn run_init(force: bool, id_and_key: Option<[String; 2]>) {
+    let path = config_path().unwrap_or_else(|e| {
+        error(e);
+        exit(3)
+    });
+    // 提前检查以免询问后才失败, 写入时仍以 create_new 防止覆盖
+    if !force && path.exists() {
+        error(msg!(ConfigExists, format!("{path:?}")));
+        exit(3)
+    }
+    let [id, key] = id_and_key.unwrap_or_else(|| {
+        let read = |prompt| {
+            eprint!("{prompt}: ");
+            let mut line = String::new();
+            if let Err(e) = stdin().read_line(&mut line) {
+                error(msg!(ReadPrompt, prompt, e));
+                exit(3)
+            }
+            line.trim().to_owned()
+        };
+        [read("appid"), read("appkey")]
+    });
+    if id.is_empty() || key.is_empty() {
+        error(msg!(EmptyIdOrKey));
+        exit(2)
+    }
+    let write = || {
+        if force {
+            match fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => (),
+            }
+        }
+        // 以 0600 创建, 密钥不会有短暂可读的时刻
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&path)?.write_all(format!("{id}\n{key}\n").as_bytes())
+    };
+    match write() {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            error(msg!(ConfigExists, format!("{path:?}")));
+            exit(3)
+        },
+        Err(e) => {
+            error(msg!(WriteConfig, format!("{path:?}"), e));
+            exit(3)
+        },
+    }
+    eprintln!("{}", msg!(ConfigWritten, format!("{path:?}")));
+}
+
+/// 在输入文件改变时重新翻译, 直到 Ctrl-C
+///
+/// 单次翻译的错误只会输出, 不会中止
+#[cfg(feature = "watch")]
+async fn run_watch(
+    translater: &Translater<'_>,
+    cfg: &Config,
+    watch: &WatchConfig,
+    job: &Job,
+    stats: Option<&Mutex<Stats>>,
+) {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+    use notify::{RecursiveMode, Watcher};
+
+    let path = fs::canonicalize(&job.input).unwrap_or_else(|e| {
+        error(format!("{}: {e}", job.input.display()));
+        exit(3)
+    });
+    // 编辑器常以替换文件的方式保存, 所以监视所在目录
+    let dir = path.parent().unwrap_or(Path::new("/")).to_owned();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(
+        move |event: notify::Result<notify::Event>| {
+            if event.is_ok_and(|event| event.paths.contains(&path)) {
+                let _ = tx.send(());
+            }
+        },
+    );
+    let _watcher = watcher
+        .and_then(|mut watcher| {
+            watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+            Ok(watcher)
+        })
+        .unwrap_or_else(|e| {
+            error(msg!(WatchFailed, dir.display(), e));
+            exit(3)
+        });
+
+    let mut last_hash = None;
+    loop {
+        let hash = read_text(cfg, &job.input).map(|text| {
+            let mut hasher = DefaultHasher::new();
+            prepare_input(cfg, text).text.hash(&mut hasher);
+            hasher.finish()
+        });
+        match hash {
+            Ok(hash) if last_hash == Some(hash) => (),
+            Ok(hash) => {
+                match &watch.sep {
+                    Some(sep) if last_hash.is_some() => println!("{sep}"),
+                    Some(_) => (),
+                    None if stdout().is_terminal() => print!("\x1b[2J\x1b[H"),
+                    None => (),
+                }
+                last_hash = Some(hash);
+                let frame = Frame::new(cfg);
+                let result = run_job(translater, cfg, job, stats, &mut frame.writer(stdout()))
+                    .await
+                    .and(frame.finish(&mut stdout()));
+                if let Err((_, e)) = result {
+                    error(format!("{}: {e}", job.input.display()));
+                }
+            },
+            Err(e) => {
+                error(format!("{}: {}", job.input.display(), msg!(ReadText, e)));
+            },
+        }
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            event = rx.recv() => if event.is_none() { break },
+        }
+        // 合并 debounce 时间内的后续事件
+        while let Ok(Some(())) = tokio::time::timeout(watch.debounce, rx.recv()).await {}
+    }
+}
+
+/// 翻译所有输入
+/// `--jobs` 大于 1 时, 第一次 Ctrl-C 后不再开始新的文件,
+/// 等待进行中的文件完成, 第二次 Ctrl-C 立即退出
+fn drain_on_ctrl_c() -> Arc<AtomicBool> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = interrupted.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() { return }
+        flag.store(true, Ordering::Relaxed);
+        eprintln!("{}", msg!(Interrupted));
+        if tokio::signal::ctrl_c().await.is_ok() {
+            exit(130)
+        }
+    });
+    interrupted
+}
+
+async fn run_translate(start: Instant, cfg: &Config) {
+    let jobs = plan_jobs(cfg).unwrap_or_else(|e| {
+        error(e);
+        exit(2)
+    });
+    if cfg.dry_run {
+        eprintln!("{}", msg!(Salt, SaltSource::from_env()));
+        for job in &jobs {
+            let output = job.output.as_deref().unwrap_or(Path::new("-"));
+            println!("{} -> {}", job.input.display(), output.display());
+        }
+        return;
+    }
+    if cfg.count_only {
+        let mut code = 0;
+        for job in &jobs {
+            match read_text(cfg, &job.input) {
+                Ok(text) => {
+                    let input = prepare_input(cfg, text);
+                    let (lines, chars) = count_input(&input.text);
+                    if jobs.len() > 1 {
+                        print!("{}: ", job.input.display());
+                    }
+                    println!("lines: {lines}, src_chars: {chars}");
+                },
+                Err(e) => {
+                    error(format!("{}: {}", job.input.display(), msg!(ReadText, e)));
+                    code = 3;
+                },
+            }
+        }
+        exit(code)
+    }
+    if cfg.from_json {
+        let job = &jobs[0];
+        if let Err((code, e)) = run_from_json(cfg, job, &mut stdout()) {
+            error(format!("{}: {e}", job.input.display()));
+            exit(code)
+        }
+        return;
+    }
+    let [id, key] = load_id_and_key().await;
+    let build = || {
+        let mut builder = Translater::builder(&id, &key);
+        for (name, value) in &cfg.headers {
+            builder.header(name, value)?;
+        }
+        if let Some(ua) = &cfg.user_agent {
+            builder.user_agent(ua)?;
+        }
+        if let Some(context) = &cfg.context {
+            builder.with_context(context.clone());
+        }
+        builder.build()
+    };
+    let mut translater = build().unwrap_or_else(|e| {
+        error(e);
+        exit(2)
+    });
+    translater.set_salt_source(SaltSource::from_env());
+    if cfg.verbose {
+        translater = translater.with_salt_callback(|salt| eprintln!("{}", msg!(Salt, salt)));
+    }
+    if let Some(x) = &cfg.from_lang {
+        translater.set_from_lang(x);
+    }
+    if let Some(x) = &cfg.to_lang {
+        translater.set_to_lang(x);
+    }
+    let stats = (cfg.stats || cfg.stats_json).then(|| {
+        let stats = Arc::new(Mutex::new(Stats::default()));
+        translater.set_stats(stats.clone());
+        stats
+    });
+    if let Some(path) = &cfg.log_file {
+        let log = CallLog::open(path, cfg.log_level).unwrap_or_else(|e| {
+            error(msg!(OpenLog, format!("{path:?}"), e));
+            exit(3)
+        });
+        translater.set_log(Arc::new(log));
+    }
+    #[cfg(feature = "watch")]
+    if let Some(watch) = &cfg.watch {
+        run_watch(&translater, cfg, watch, &jobs[0], stats.as_deref()).await;
+        return;
+    }
+    let (mut translated, mut skipped, mut failed) = (0, 0, 0);
+    let mut code = 0;
+    let mut pending = vec![];
+    for job in &jobs {
+        if let Some(output) = job.output.as_ref().filter(|_| {
+            cfg.in_place.is_none()
+        }) {
+            if !cfg.force && is_up_to_date(&job.input, output) {
+                skipped += 1;
+                continue;
+            }
+        }
+        pending.push(job);
+    }
+    let total = pending.len();
+    let interrupted = if cfg.jobs > 1 { drain_on_ctrl_c() } else { Arc::default() };
+    let (translater, stats) = (&translater, stats.as_deref());
+    let frame = &Frame::new(cfg);
+    let mut results = stream::iter(pending)
+        .take_while(|_| future::ready(!interrupted.load(Ordering::Relaxed)))
+        .map(|job| async move {
+            // 并行时先缓存输出到 stdout 的内容, 按输入顺序写出
+            let mut buf = Vec::new();
+            let result = if cfg.jobs > 1 {
+                run_job(translater, cfg, job, stats, &mut buf).await
+            } else {
+                run_job(translater, cfg, job, stats, &mut frame.writer(stdout())).await
+            };
+            (job, result, buf)
+        })
+        .buffered(cfg.jobs);
+    let mut done = 0;
+    while let Some((job, result, buf)) = results.next().await {
+        done += 1;
+        // 出错的文件已输出的部分同样写出, 与不并行时一致
+        let written = frame.writer(stdout()).write_all(&buf).map_err(|e| (3, msg!(Write, e)));
+        let result = result.and(written);
+        match result {
+            Ok(()) => translated += 1,
+            Err((err_code, e)) => {
+                error(format!("{}: {e}", job.input.display()));
+                failed += 1;
+                code = err_code;
+            },
+        }
+    }
+    drop(results);
+    if let Err((err_code, e)) = frame.finish(&mut stdout()) {
+        error(e);
+        code = err_code;
+    }
+    if done != total {
+        error(msg!(NotStarted, total - done));
+        code = 130;
+    }
+    if cfg.out_dir.is_some() || cfg.in_place.is_some() {
+        eprintln!("{}", msg!(FilesSummary, translated, skipped, failed));
+    }
+    if let Some(stats) = stats {
+        let mut stats = stats.lock().unwrap();
+        stats.elapsed = start.elapsed();
+        if cfg.stats {
+            eprint!("{}", stats.display(cfg.price_per_million));
+        }
+        if cfg.stats_json {
+            println!("{}", stats.to_json(cfg.price_per_million));
         }
-        Err(e) => panic!("{}", e),
     }
+    exit(code)
 }
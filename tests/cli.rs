@@ -0,0 +1,120 @@
+//! 命令行参数的检查, 出错时以 2 退出且不发送请求
+
+mod common;
+
+use common::{command, run, run_args, stdout, Mock};
+
+fn assert_usage_error(args: &[&str]) {
+    let output = run_args(args, "");
+    assert_eq!(output.status.code(), Some(2), "{args:?}: {output:?}");
+    assert!(output.stdout.is_empty(), "{args:?}: {output:?}");
+}
+
+#[test]
+fn output_modes_conflict_with_files() {
+    for mode in ["--count", "--diff", "--color-diff", "--side-by-side"] {
+        for files in [&["--out-dir", "/tmp/outx"][..], &["--in-place"]] {
+            let args = [&["--dry-run", mode][..], files, &["file"]].concat();
+            assert_usage_error(&args);
+        }
+    }
+}
+
+#[test]
+fn line_count_must_be_attached() {
+    let sent = |args: &[&str]| {
+        let mock = Mock::uppercase();
+        let output = run(command(&mock.proxy()).args(args), "one\ntwo\nthree\n");
+        assert!(output.status.success(), "{args:?}: {output:?}");
+        mock.finish().iter().map(|request| request.q()).collect::<Vec<_>>()
+    };
+    assert_eq!(sent(&["-l", "-"]), ["one\n"]);
+    assert_eq!(sent(&["-l2", "-"]), ["one\ntwo\n"]);
+    assert_eq!(sent(&["--line=2"]), ["one\ntwo\n"]);
+    assert_usage_error(&["-lx", "-"]);
+    assert_usage_error(&["-l0", "-"]);
+}
+
+#[test]
+fn format_arguments_out_of_range() {
+    assert_usage_error(&["-m", "%5s%n", "-"]);
+    assert_usage_error(&["-m", "%s%s%s%s%s", "-"]);
+    let output = run_args(&["--dry-run", "-m", "%s%s%s%3s%n", "-"], "");
+    assert_eq!(output.status.code(), Some(0), "{output:?}");
+}
+
+#[test]
+fn null_terminated_records() {
+    let mock = Mock::uppercase();
+    let output = run(command(&mock.proxy()).args(["-0", "-"]), "one\ntwo\0\0three\0");
+    let mut sent = mock.finish().iter().map(|request| request.q()).collect::<Vec<_>>();
+    sent.sort();
+    assert!(output.status.success(), "{output:?}");
+    // 记录内的换行保留, 空记录不发送但仍输出
+    assert_eq!(sent, ["one\ntwo\n", "three\n"]);
+    assert_eq!(stdout(&output), "ONE\nTWO\n\0\0THREE\n\0");
+    for conflict in ["-l", "--stream", "--ndjson", "--stdin-separator=,"] {
+        assert_usage_error(&["-0", conflict, "-"]);
+    }
+}
+
+#[test]
+fn verbose_prints_sent_salt() {
+    let mock = Mock::uppercase();
+    let output = run(command(&mock.proxy()).args(["--verbose", "-"]), "hello\n");
+    assert!(output.status.success(), "{output:?}");
+    let salt = mock.finish().remove(0).form("salt").unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.matches("salt: ").count(), 1, "{stderr}");
+    assert!(stderr.contains(&format!("salt: {salt}\n")), "{stderr}");
+}
+
+#[test]
+fn locale_target_falls_back_to_zh() {
+    let targets = |args: &[&str]| {
+        let mock = Mock::uppercase();
+        let mut command = command(&mock.proxy());
+        let output = run(command.env("LANG", "en_US.UTF-8").args(args), "hello\n");
+        assert!(output.status.success(), "{args:?}: {output:?}");
+        mock.finish().iter().map(|request| request.form("to").unwrap()).collect::<Vec<_>>()
+    };
+    // 检测出的源语言与目标语言相同, 改为 zh 重新翻译
+    assert_eq!(targets(&["-"]), ["en", "zh"]);
+    assert_eq!(targets(&["-f", "en", "-"]), ["zh"]);
+    assert_eq!(targets(&["-f", "jp", "-"]), ["en"]);
+    assert_eq!(targets(&["-t", "en", "-"]), ["en"]);
+}
+
+#[test]
+fn repeated_options_are_errors() {
+    for args in [
+        &["-t", "en", "-t", "zh"][..],
+        &["-t", "en", "--to", "zh"],
+        &["-f", "en", "-f", "zh"],
+        &["-o", "1", "-o", "2"],
+        &["--error-marker", "a", "--error-marker", "b"],
+    ] {
+        let output = run_args(&[args, &["-"]].concat(), "");
+        assert_eq!(output.status.code(), Some(2), "{args:?}: {output:?}");
+        // 错误信息指出重复的选项
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(args[0]), "{args:?}: {stderr}");
+    }
+}
+
+#[test]
+fn stdin_when_no_file() {
+    let sent = |args: &[&str]| {
+        let mock = Mock::uppercase();
+        let dir = common::temp_home();
+        std::fs::write(dir.join("file.txt"), "file\n").unwrap();
+        let output = run(command(&mock.proxy()).current_dir(dir).args(args), "stdin\n");
+        assert!(output.status.success(), "{args:?}: {output:?}");
+        mock.finish().iter().map(|request| request.q()).collect::<Vec<_>>()
+    };
+    assert_eq!(sent(&[]), ["stdin\n"]);
+    assert_eq!(sent(&["-t", "zh"]), ["stdin\n"]);
+    assert_eq!(sent(&["-"]), ["stdin\n"]);
+    // 给出文件时不读取标准输入
+    assert_eq!(sent(&["file.txt"]), ["file\n"]);
+}
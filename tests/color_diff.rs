@@ -0,0 +1,38 @@
+//! `--color-diff` 与普通输出一样分块翻译并还原 Markdown
+
+mod common;
+
+use common::{command, run, stdout, Mock};
+
+fn color_diff(input: &str, args: &[&str]) -> (Vec<String>, String) {
+    let mock = Mock::uppercase();
+    let output = run(
+        command(&mock.proxy()).args(["--color-diff", "--color=never"]).args(args),
+        input,
+    );
+    assert!(output.status.success(), "{output:?}");
+    let sent = mock.finish().iter().map(|request| request.q()).collect();
+    (sent, stdout(&output))
+}
+
+#[test]
+fn marks_changed_words() {
+    let (sent, out) = color_diff("keep this\n", &[]);
+    assert_eq!(sent, ["keep this\n"]);
+    assert_eq!(out, "[-keep-]{+KEEP+} [-this-]{+THIS+}\n");
+}
+
+#[test]
+fn splits_blocks() {
+    let input: String = (0..5).map(|i| format!("{i}{}\n", "a".repeat(1998))).collect();
+    let (sent, out) = color_diff(&input, &[]);
+    assert_eq!(sent.len(), 3);
+    assert_eq!(out.lines().count(), 5);
+}
+
+#[test]
+fn restores_markdown() {
+    let (sent, out) = color_diff("use `foo` here\n", &["--markdown"]);
+    assert!(!sent[0].contains("foo"), "{sent:?}");
+    assert_eq!(out, "[-use-]{+USE+} `foo` [-here-]{+HERE+}\n");
+}
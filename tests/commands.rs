@@ -0,0 +1,119 @@
+//! `detect`, `langs`, `init` 与 `cache` 子命令
+
+mod common;
+
+use std::{fs, path::PathBuf};
+
+use common::{command, run, run_args, stdout, temp_home, Mock, Reply};
+
+/// 没有密钥文件的临时 HOME
+fn empty_home() -> PathBuf {
+    let home = temp_home();
+    fs::remove_file(home.join(".baidufanyi_key")).unwrap();
+    home
+}
+
+fn init(home: &PathBuf, args: &[&str], input: &str) -> std::process::Output {
+    run(command("http://127.0.0.1:9").env("HOME", home).arg("init").args(args), input)
+}
+
+#[test]
+fn init_writes_key_file() {
+    let home = empty_home();
+    let output = init(&home, &["id", "key"], "");
+    assert!(output.status.success(), "{output:?}");
+    let path = home.join(".baidufanyi_key");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "id\nkey\n");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn init_reads_stdin() {
+    let home = empty_home();
+    let output = init(&home, &[], " id \nkey\n");
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(fs::read_to_string(home.join(".baidufanyi_key")).unwrap(), "id\nkey\n");
+
+    let home = empty_home();
+    let output = init(&home, &[], "id\n\n");
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    assert!(!home.join(".baidufanyi_key").exists());
+}
+
+#[test]
+fn init_keeps_existing_file() {
+    let home = temp_home();
+    let path = home.join(".baidufanyi_key");
+    let output = init(&home, &["new", "secret"], "");
+    assert_eq!(output.status.code(), Some(3), "{output:?}");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "id\nkey\n");
+
+    let output = init(&home, &["--force", "new", "secret"], "");
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "new\nsecret\n");
+}
+
+#[test]
+fn subcommand_usage_errors() {
+    let cases = [
+        &["init", "id"][..],
+        &["init", "a", "b", "c"],
+        &["langs", "x"],
+        &["detect", "--bad"],
+        &["cache"],
+        &["cache", "drop"],
+        &["cache", "clear", "stats"],
+    ];
+    for args in cases {
+        let output = run_args(args, "");
+        assert_eq!(output.status.code(), Some(2), "{args:?}: {output:?}");
+        assert!(output.stdout.is_empty(), "{args:?}: {output:?}");
+    }
+}
+
+#[test]
+fn langs_lists_codes() {
+    let output = run_args(&["langs"], "");
+    assert!(output.status.success(), "{output:?}");
+    let out = stdout(&output);
+    assert!(out.lines().any(|line| line.split_whitespace().next() == Some("zh")), "{out}");
+    assert!(out.lines().any(|line| line.split_whitespace().next() == Some("en")), "{out}");
+}
+
+#[test]
+fn cache_is_empty() {
+    let output = run_args(&["cache", "stats"], "");
+    assert!(output.status.success(), "{output:?}");
+    assert!(stdout(&output).starts_with("entries: 0"), "{output:?}");
+    let output = run_args(&["cache", "clear"], "");
+    assert!(output.status.success(), "{output:?}");
+    assert!(stdout(&output).starts_with("nothing to clear"), "{output:?}");
+    let output = run_args(&["cache", "-h"], "");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("cache [-h].. [--lang <lang>] clear|stats"), "{stderr}");
+}
+
+#[test]
+fn detect_prints_language() {
+    let mock = Mock::start(|_| Reply::json(r#"{"error_code":0,"data":{"src":"en"}}"#));
+    let output = run(command(&mock.proxy()).args(["detect", "-"]), "hello\n");
+    let requests = mock.finish();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(stdout(&output), "en\n");
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].head.contains("/api/trans/vip/language"), "{}", requests[0].head);
+    assert_eq!(requests[0].q(), "hello");
+}
+
+#[test]
+fn legacy_form_is_translate() {
+    let mock = Mock::uppercase();
+    let output = run(command(&mock.proxy()).args(["-t", "zh", "-"]), "hi\n");
+    assert_eq!(mock.finish().len(), 1);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(stdout(&output), "HI\n");
+}
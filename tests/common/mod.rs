@@ -0,0 +1,253 @@
+//! 集成测试共用的 HTTP 模拟服务器与命令行辅助函数
+#![allow(dead_code)]
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+/// 收到的请求
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// 请求行与请求头, 已转为小写
+    pub head: String,
+    pub body: String,
+}
+impl Request {
+    /// 请求头的值
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key == name).then(|| value.trim())
+        })
+    }
+
+    /// 表单中的值, 已解码
+    pub fn form(&self, key: &str) -> Option<String> {
+        self.body.split('&').find_map(|pair| {
+            let (k, v) = pair.split_once('=')?;
+            (k == key).then(|| form_decode(v))
+        })
+    }
+
+    /// 表单中要翻译的文本
+    pub fn q(&self) -> String {
+        self.form("q").unwrap_or_default()
+    }
+}
+
+/// 解码 `application/x-www-form-urlencoded` 中的值
+pub fn form_decode(value: &str) -> String {
+    let mut bytes = vec![];
+    let mut iter = value.bytes();
+    while let Some(b) = iter.next() {
+        match b {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [iter.next().unwrap(), iter.next().unwrap()];
+                let hex = std::str::from_utf8(&hex).unwrap();
+                bytes.push(u8::from_str_radix(hex, 16).unwrap());
+            },
+            b => bytes.push(b),
+        }
+    }
+    String::from_utf8(bytes).unwrap()
+}
+
+/// 模拟的响应
+#[derive(Debug, Clone)]
+pub struct Reply {
+    /// 状态行, 如 `200 OK`
+    pub status: &'static str,
+    /// 额外的响应头, 每个以 `\r\n` 结尾
+    pub headers: String,
+    pub body: String,
+}
+impl Reply {
+    pub fn json(body: impl Into<String>) -> Self {
+        Self {
+            status: "200 OK",
+            headers: "content-type: application/json\r\n".into(),
+            body: body.into(),
+        }
+    }
+
+    pub fn empty(status: &'static str) -> Self {
+        Self { status, headers: String::new(), body: String::new() }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push_str(&format!("{name}: {value}\r\n"));
+        self
+    }
+}
+
+/// 将 `q` 的每行译为大写, 源语言为表单中的 `from`, 自动检测时为 `en`
+pub fn uppercase(request: &Request) -> Reply {
+    let q = request.q();
+    let lines = q.lines()
+        .map(|src| serde_json::json!({"src": src, "dst": src.to_uppercase()}))
+        .collect::<Vec<_>>();
+    let from = request.form("from").filter(|from| from != "auto");
+    let body = serde_json::json!({
+        "from": from.as_deref().unwrap_or("en"),
+        "to": request.form("to").unwrap_or_default(),
+        "trans_result": lines,
+    });
+    Reply::json(body.to_string())
+}
+
+type Respond = dyn Fn(&Request) -> Reply + Send + Sync;
+
+/// 在本地端口上运行的服务器, 每个连接在单独的线程中处理,
+/// 可作为 HTTP 代理或直接请求其 [`Mock::url`]
+pub struct Mock {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    requests: Arc<Mutex<Vec<Request>>>,
+    server: thread::JoinHandle<()>,
+}
+impl Mock {
+    pub fn start(respond: impl Fn(&Request) -> Reply + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let requests = Arc::new(Mutex::new(vec![]));
+        let respond: Arc<Respond> = Arc::new(respond);
+        let server = thread::spawn({
+            let (stop, requests) = (stop.clone(), requests.clone());
+            move || {
+                let mut handlers = vec![];
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) { break }
+                    let (requests, respond) = (requests.clone(), respond.clone());
+                    handlers.push(thread::spawn(move || {
+                        serve(stream.unwrap(), &*respond, &requests);
+                    }));
+                }
+                for handler in handlers {
+                    handler.join().unwrap();
+                }
+            }
+        });
+        Self { addr, stop, requests, server }
+    }
+
+    /// 使用 [`uppercase`] 响应
+    pub fn uppercase() -> Self {
+        Self::start(uppercase)
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.addr)
+    }
+
+    /// 作为 `http_proxy` 的地址
+    pub fn proxy(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// 通过此服务器代理的 [`reqwest::Client`]
+    pub fn client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .proxy(reqwest::Proxy::http(self.proxy()).unwrap())
+            .build()
+            .unwrap()
+    }
+
+    /// 取出目前收到的请求, 请求在响应前记录
+    pub fn take(&self) -> Vec<Request> {
+        std::mem::take(&mut *self.requests.lock().unwrap())
+    }
+
+    /// 停止服务器, 返回按收到顺序排列的请求
+    pub fn finish(self) -> Vec<Request> {
+        self.stop.store(true, Ordering::SeqCst);
+        drop(TcpStream::connect(self.addr));
+        self.server.join().unwrap();
+        std::mem::take(&mut *self.requests.lock().unwrap())
+    }
+}
+
+/// 读取一个请求, 记录后响应, 忽略未发送请求就关闭的连接
+fn serve(stream: TcpStream, respond: &Respond, requests: &Mutex<Vec<Request>>) {
+    let mut reader = BufReader::new(stream);
+    let (mut head, mut length) = (String::new(), 0);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_or(true, |n| n == 0) { return }
+        if line == "\r\n" { break }
+        let line = line.to_lowercase();
+        if let Some(n) = line.strip_prefix("content-length:") {
+            length = n.trim().parse().unwrap();
+        }
+        head.push_str(&line);
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    let request = Request { head, body: String::from_utf8(body).unwrap() };
+    let reply = respond(&request);
+    requests.lock().unwrap().push(request);
+    write!(
+        reader.get_mut(),
+        "HTTP/1.1 {}\r\n{}content-length: {}\r\nconnection: close\r\n\r\n{}",
+        reply.status, reply.headers, reply.body.len(), reply.body,
+    ).unwrap();
+}
+
+/// 带有密钥文件的临时 HOME 目录, 每次调用都不同
+pub fn temp_home() -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let home = std::env::temp_dir().join(format!(
+        "baidu_fanyi-test-{}-{}",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::SeqCst),
+    ));
+    std::fs::create_dir_all(&home).unwrap();
+    std::fs::write(home.join(".baidufanyi_key"), "id\nkey\n").unwrap();
+    home
+}
+
+/// 使用临时 HOME 运行的命令行, 请求经由 `proxy` 发送
+pub fn command(proxy: &str) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_baidu_fanyi"));
+    command
+        .env("HOME", temp_home())
+        .env("http_proxy", proxy)
+        .env("LANG", "C")
+        .env_remove("LC_ALL")
+        .env_remove("LC_MESSAGES")
+        .env_remove("no_proxy")
+        .env_remove("NO_PROXY")
+        .env_remove("BAIDU_FANYI_SALT")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    command
+}
+
+/// 将 `input` 写入标准输入并等待命令结束
+pub fn run(command: &mut Command, input: &str) -> Output {
+    let mut child = command.spawn().unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    // 命令可能在读取输入前因参数错误退出
+    let _ = stdin.write_all(input.as_bytes());
+    drop(stdin);
+    child.wait_with_output().unwrap()
+}
+
+/// 以 `args` 运行命令行, 不发送请求时使用
+pub fn run_args(args: &[&str], input: &str) -> Output {
+    run(command("http://127.0.0.1:9").args(args), input)
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
@@ -0,0 +1,37 @@
+//! 默认按行去重, 重复的行只发送一次
+
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use common::{command, run, stdout, uppercase, Mock, Reply};
+
+#[test]
+fn sends_unique_lines() {
+    let mock = Mock::uppercase();
+    let output = run(command(&mock.proxy()).arg("-"), "a\nb\na\n\nb\nc\n");
+    assert!(output.status.success(), "{output:?}");
+    let sent = mock.finish().iter().map(|request| request.q()).collect::<Vec<_>>();
+    assert_eq!(sent, ["a\nb\nc\n"]);
+    assert_eq!(stdout(&output), "A\nB\nA\nB\nC\n");
+}
+
+#[test]
+fn mismatched_lines_fall_back() {
+    // 第一次请求的译文合并为一行
+    let merged = r#"{"from":"en","to":"zh","trans_result":[{"src":"a b","dst":"A B"}]}"#;
+    let mock = Mock::start({
+        let count = AtomicUsize::new(0);
+        move |request| match count.fetch_add(1, Ordering::SeqCst) {
+            0 => Reply::json(merged),
+            _ => uppercase(request),
+        }
+    });
+    let output = run(command(&mock.proxy()).arg("-"), "a\nb\na\n");
+    assert!(output.status.success(), "{output:?}");
+    let sent = mock.finish().iter().map(|request| request.q()).collect::<Vec<_>>();
+    assert_eq!(sent, ["a\nb\n", "a\nb\na\n"]);
+    assert_eq!(stdout(&output), "A\nB\nA\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("without deduplication"), "{stderr}");
+}
@@ -0,0 +1,37 @@
+//! `--from-detect-only` 对每个输入分别检测并锁定源语言
+
+mod common;
+
+use std::fs;
+
+use common::{command, run, temp_home, uppercase, Mock, Reply, Request};
+
+/// 自动检测时按文本判断源语言, 否则同 [`uppercase`]
+fn respond(request: &Request) -> Reply {
+    let detected = if request.q().starts_with("bonjour") { "fr" } else { "de" };
+    let reply = uppercase(request);
+    match request.form("from").as_deref() {
+        Some("auto") => {
+            let body = reply.body.replace("\"en\"", &format!("{detected:?}"));
+            Reply { body, ..reply }
+        },
+        _ => reply,
+    }
+}
+
+#[test]
+fn locked_per_input() {
+    let dir = temp_home();
+    fs::write(dir.join("fr.txt"), "bonjour\n").unwrap();
+    fs::write(dir.join("de.txt"), "hallo\n").unwrap();
+    let mock = Mock::start(respond);
+    let args = ["--from-detect-only", "-t", "zh", "fr.txt", "de.txt"];
+    let output = run(command(&mock.proxy()).current_dir(&dir).args(args), "");
+    assert!(output.status.success(), "{output:?}");
+    // 第二个输入不沿用第一个输入检测出的语言
+    for request in mock.finish() {
+        assert_eq!(request.form("from").unwrap(), "auto", "{}", request.body);
+    }
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("fr") && stderr.contains("de"), "{stderr}");
+}
@@ -0,0 +1,84 @@
+//! [`myers_diff`] 的编辑序列与最长公共子序列的比较
+
+use baidu_fanyi::diff::{myers_diff, DiffOp, DiffPair, TransDiff};
+use proptest::prelude::*;
+
+/// 平方复杂度的最长公共子序列长度, 作为参照
+fn lcs_len(a: &[u8], b: &[u8]) -> usize {
+    let mut row = vec![0; b.len() + 1];
+    for x in a {
+        let mut diag = 0;
+        for (j, y) in b.iter().enumerate() {
+            let up = row[j + 1];
+            row[j + 1] = if x == y { diag + 1 } else { up.max(row[j]) };
+            diag = up;
+        }
+    }
+    row[b.len()]
+}
+
+/// 由编辑序列还原 `(a, b)`
+fn sides(ops: &[DiffOp<u8>]) -> (Vec<u8>, Vec<u8>) {
+    let (mut a, mut b) = (vec![], vec![]);
+    for op in ops {
+        match *op {
+            DiffOp::Equal(x) => { a.push(x); b.push(x) },
+            DiffOp::Delete(x) => a.push(x),
+            DiffOp::Insert(x) => b.push(x),
+        }
+    }
+    (a, b)
+}
+
+proptest! {
+    #[test]
+    fn myers_is_shortest(
+        a in prop::collection::vec(0u8..4, 0..64),
+        b in prop::collection::vec(0u8..4, 0..64),
+    ) {
+        let ops = myers_diff(&a, &b);
+        prop_assert_eq!(sides(&ops), (a.clone(), b.clone()));
+        let equal = ops.iter().filter(|op| matches!(op, DiffOp::Equal(_))).count();
+        prop_assert_eq!(equal, lcs_len(&a, &b));
+    }
+
+    #[test]
+    fn deletes_before_inserts(
+        a in prop::collection::vec(0u8..4, 0..32),
+        b in prop::collection::vec(0u8..4, 0..32),
+    ) {
+        let ops = myers_diff(&a, &b);
+        for pair in ops.windows(2) {
+            let insert_then_delete = matches!(pair, [DiffOp::Insert(_), DiffOp::Delete(_)]);
+            prop_assert!(!insert_then_delete, "{:?}", ops);
+        }
+    }
+}
+
+#[test]
+fn long_inputs() {
+    // 保存每步状态的实现在此需要约 600 MiB
+    let a: Vec<u32> = (0..3000).collect();
+    let b: Vec<u32> = (3000..6000).collect();
+    let ops = myers_diff(&a, &b);
+    assert_eq!(ops.len(), 6000);
+    assert!(ops[..3000].iter().all(|op| matches!(op, DiffOp::Delete(_))));
+
+    let b: Vec<u32> = (0..3000).map(|x| if x % 300 == 0 { x + 1 } else { x }).collect();
+    let changed = myers_diff(&a, &b).iter()
+        .filter(|op| !matches!(op, DiffOp::Equal(_)))
+        .count();
+    assert_eq!(changed, 20);
+}
+
+#[test]
+fn trans_diff_ignores_case() {
+    let diff = TransDiff::between("Hello RUST world", "hello Rust 世界");
+    assert_eq!(diff.pairs, [
+        DiffPair::Common("Hello".into(), "hello".into()),
+        DiffPair::Common("RUST".into(), "Rust".into()),
+        DiffPair::Removed("world".into()),
+        DiffPair::Added("世".into()),
+        DiffPair::Added("界".into()),
+    ]);
+}
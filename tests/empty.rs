@@ -0,0 +1,37 @@
+//! 空白的输入不发送请求
+
+mod common;
+
+use std::{fs, process::Output};
+
+use common::{command, run, temp_home, Mock};
+
+/// 以 `args` 翻译 `input`, 检查没有发送请求
+fn no_request(input: &str, args: &[&str]) -> Output {
+    let mock = Mock::uppercase();
+    let output = run(command(&mock.proxy()).args(args), input);
+    assert!(mock.finish().is_empty(), "{args:?}");
+    assert!(output.stdout.is_empty(), "{args:?}: {output:?}");
+    output
+}
+
+#[test]
+fn skipped_without_request() {
+    let dir = temp_home();
+    let empty = dir.join("empty.txt");
+    fs::write(&empty, "").unwrap();
+    let empty = empty.to_str().unwrap();
+    let cases = [
+        ("", &[empty][..]),
+        (" \n\t\n\n", &["-"]),
+        ("", &["-l", "-"]),
+        ("\n  \n", &["-l3", "-"]),
+    ];
+    for (input, args) in cases {
+        let output = no_request(input, args);
+        assert_eq!(output.status.code(), Some(0), "{args:?}: {output:?}");
+        let args = [&["--fail-empty"], args].concat();
+        let output = no_request(input, &args);
+        assert_eq!(output.status.code(), Some(6), "{args:?}: {output:?}");
+    }
+}
@@ -0,0 +1,56 @@
+//! 失败的块在输出中以 `--error-marker` 标记, `--fail-fast` 时立即退出
+
+mod common;
+
+use common::{command, run, stdout, uppercase, Mock, Reply};
+
+/// 含有 `fail` 的块总是失败
+fn fail_block() -> Mock {
+    Mock::start(|request| match request.q().contains("fail") {
+        true => Reply::json(r#"{"error_code":"54003","error_msg":"Invalid Access Limit"}"#),
+        false => uppercase(request),
+    })
+}
+
+/// 按字节计数时每块 29 行, 第二块以 `fail` 开头
+fn input() -> String {
+    (0..87)
+        .map(|i| match i {
+            29 => format!("fail{}\n", "-".repeat(95)),
+            _ => format!("line {i:02}{}\n", ".".repeat(92)),
+        })
+        .collect()
+}
+
+#[test]
+fn marks_failed_block() {
+    let mock = fail_block();
+    let output = run(command(&mock.proxy()).args(["--limit-unit", "bytes", "-"]), &input());
+    assert_eq!(mock.finish().len(), 3);
+    assert_eq!(output.status.code(), Some(7), "{output:?}");
+    let text = stdout(&output);
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 29 + 1 + 29, "{text}");
+    assert!(lines[28].starts_with("LINE 28"), "{text}");
+    assert_eq!(lines[29], "[[translation failed: 54003]]");
+    assert!(lines[30].starts_with("LINE 58"), "{text}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("translation failed for blocks #2"), "{stderr}");
+
+    let mock = fail_block();
+    let args = ["--limit-unit", "bytes", "--error-marker", "<!-- <code> -->", "-"];
+    let output = run(command(&mock.proxy()).args(args), &input());
+    mock.finish();
+    assert_eq!(stdout(&output).lines().nth(29), Some("<!-- 54003 -->"));
+}
+
+#[test]
+fn fail_fast() {
+    let mock = fail_block();
+    let args = ["--limit-unit", "bytes", "--fail-fast", "-"];
+    let output = run(command(&mock.proxy()).args(args), &input());
+    // 第三个块不再请求
+    assert_eq!(mock.finish().len(), 2);
+    assert_eq!(output.status.code(), Some(5), "{output:?}");
+    assert!(!stdout(&output).contains("LINE 58"), "{output:?}");
+}
@@ -0,0 +1,35 @@
+//! 空白符的过滤, `-o` 与 `--no-filter`
+
+mod common;
+
+use common::{command, run, run_args, Mock};
+
+/// 以 `args` 翻译 `input`, 返回发送的文本
+fn sent(input: &str, args: &[&str]) -> String {
+    let mock = Mock::uppercase();
+    let output = run(command(&mock.proxy()).args(args).arg("-"), input);
+    assert!(output.status.success(), "{args:?}: {output:?}");
+    let requests = mock.finish();
+    assert_eq!(requests.len(), 1, "{args:?}");
+    requests[0].q()
+}
+
+#[test]
+fn no_filter_passes_whitespace() {
+    let input = "a:\n\t- b  c\n\n\n\n    d\t\te\n";
+    assert_eq!(sent(input, &["--no-filter", "--no-dedup"]), input);
+    // 超过两个的连续空白符被去掉
+    assert_eq!(sent(input, &["--no-dedup"]), "a:\n\t- b  c\n\nd\t\te\n");
+}
+
+#[test]
+fn empty_count_errors() {
+    for args in [&["--no-filter", "-o", "3"][..], &["-o", "-1"], &["-o", "x"]] {
+        let output = run_args(&[args, &["-"]].concat(), "a\n");
+        assert_eq!(output.status.code(), Some(2), "{args:?}: {output:?}");
+    }
+    // 错误信息中带有给出的值
+    let output = run_args(&["-o", "-1", "-"], "a\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("invalid -o value `-1`"), "{stderr}");
+}
@@ -0,0 +1,74 @@
+//! 输出格式的选项: `-m`, `--template-file`, `--dst-only` 与 `--src-only`
+
+mod common;
+
+use std::fs;
+
+use common::{command, run, run_args, stdout, temp_home, Mock};
+
+/// 以 `args` 翻译 `input`
+fn translate(input: &str, args: &[&str]) -> std::process::Output {
+    let mock = Mock::uppercase();
+    let output = run(command(&mock.proxy()).args(args).arg("-"), input);
+    mock.finish();
+    output
+}
+
+fn stderr(output: &std::process::Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn template_file() {
+    let dir = temp_home();
+    let path = dir.join("template");
+    // 文件中的换行原样输出, 不需要 `%n`
+    fs::write(&path, "[%s]\n<%1s>\n").unwrap();
+    let path = path.to_str().unwrap();
+    let output = translate("hello\n", &["--template-file", path]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(stdout(&output), "[HELLO]\n<hello>\n");
+
+    let missing = dir.join("missing");
+    let output = run_args(&["--template-file", missing.to_str().unwrap(), "-"], "a\n");
+    assert_eq!(output.status.code(), Some(3), "{output:?}");
+    fs::write(dir.join("invalid"), "%?x{").unwrap();
+    let invalid = dir.join("invalid");
+    let output = run_args(&["--template-file", invalid.to_str().unwrap(), "-"], "a\n");
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    let output = run_args(&["--template-file", path, "-m", "%s", "-"], "a\n");
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+}
+
+#[test]
+fn dst_and_src_only() {
+    assert_eq!(stdout(&translate("hello\n", &["--dst-only"])), "HELLO\n");
+    assert_eq!(stdout(&translate("hello\n", &["--src-only"])), "hello\n");
+    // 输出不是终端时默认只输出译文
+    assert_eq!(stdout(&translate("hello\n", &[])), "HELLO\n");
+    let conflicts = [
+        &["--dst-only", "--src-only"][..],
+        &["--dst-only", "-m", "%s"],
+        &["--src-only", "-m", "%s"],
+    ];
+    for args in conflicts {
+        let output = run_args(&[args, &["-"]].concat(), "a\n");
+        assert_eq!(output.status.code(), Some(2), "{args:?}: {output:?}");
+    }
+}
+
+#[test]
+fn empty_and_valueless_formats() {
+    for format in ["", "  "] {
+        let output = run_args(&["-m", format, "-"], "a\n");
+        assert_eq!(output.status.code(), Some(2), "{format:?}: {output:?}");
+    }
+    let output = translate("hello\n", &["-m", "no placeholders%n"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(stdout(&output), "no placeholders\n");
+    assert!(stderr(&output).contains("no format contains a value"), "{output:?}");
+    // 另一个格式输出了译文时不警告
+    let output = translate("hello\n", &["-m", "no placeholders%n", "-m", "%s%n"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(!stderr(&output).contains("no format contains a value"), "{output:?}");
+}
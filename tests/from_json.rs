@@ -0,0 +1,88 @@
+//! `--from-json` 格式化保存的接口响应, 不需要配置文件与网络
+
+use std::process::{Command, Output};
+
+/// 以空的 HOME 运行, 没有配置文件时请求会失败
+fn run(args: &[&str]) -> Output {
+    let home = std::env::temp_dir().join("baidu_fanyi-from-json-home");
+    std::fs::create_dir_all(&home).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_baidu_fanyi"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("HOME", &home)
+        .env("LANG", "C")
+        .env_remove("LC_ALL")
+        .env_remove("LC_MESSAGES")
+        .output()
+        .unwrap()
+}
+
+fn stdout(args: &[&str]) -> String {
+    let output = run(args);
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+const RESPONSE: &str = "tests/fixtures/response.json";
+const RESPONSES: &str = "tests/fixtures/responses.ndjson";
+
+#[test]
+fn default_format() {
+    // stdout 不是终端, 默认仅输出译文
+    assert_eq!(stdout(&["--from-json", RESPONSE]), "你好世界\n早上好\n");
+}
+
+#[test]
+fn custom_formats() {
+    let out = stdout(&[
+        "--from-json", RESPONSE,
+        "-m", "%{src}s -> %{dst}s%n",
+        "-m", "[%2s->%3s]%n",
+    ]);
+    assert_eq!(out, concat!(
+        "Hello world -> 你好世界\n",
+        "Good morning -> 早上好\n",
+        "[en->zh]\n",
+        "[en->zh]\n",
+    ));
+    assert_eq!(stdout(&["--from-json", RESPONSE, "--src-only"]), "Hello world\nGood morning\n");
+}
+
+#[test]
+fn wrap() {
+    let out = stdout(&["--from-json", RESPONSE, "--dst-only", "--wrap", "4"]);
+    assert_eq!(out, "你好\n世界\n早上\n好\n");
+}
+
+#[test]
+fn side_by_side() {
+    let out = stdout(&["--from-json", RESPONSE, "--side-by-side", "--color=never"]);
+    assert_eq!(out, concat!(
+        "Hello world                            │ 你好世界\n",
+        "Good morning                           │ 早上好\n",
+    ));
+}
+
+#[test]
+fn ndjson_with_failed_block() {
+    let output = run(&["--from-json", RESPONSES, "--dst-only"]);
+    assert_eq!(output.status.code(), Some(7));
+    assert_eq!(output.stdout, "一\n[[translation failed: 54003]]\n二\n".as_bytes());
+}
+
+#[test]
+fn malformed_response() {
+    let output = run(&["--from-json", "tests/fixtures/malformed.ndjson"]);
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("response #2"), "{stderr}");
+    assert!(stderr.contains("`trans_result[1].dst`"), "{stderr}");
+}
+
+#[test]
+fn conflicts() {
+    let output = run(&["--from-json", RESPONSE, "--stream"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = run(&["--from-json", RESPONSE, "input.txt"]);
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,41 @@
+//! `-l` 与 `--head-bytes` 只翻译输入的开头部分
+
+mod common;
+
+use common::{command, run, run_args, Mock};
+
+/// 以 `args` 翻译 `input`, 返回发送的文本
+fn sent(input: &str, args: &[&str]) -> Vec<String> {
+    let mock = Mock::uppercase();
+    let output = run(command(&mock.proxy()).args(args).arg("-"), input);
+    assert!(output.status.success(), "{args:?}: {output:?}");
+    mock.finish().iter().map(|request| request.q()).collect()
+}
+
+#[test]
+fn count_larger_than_input() {
+    assert_eq!(sent("one\ntwo\n", &["-l5"]), ["one\ntwo\n"]);
+    assert_eq!(sent("one\ntwo", &["--line=5"]), ["one\ntwo\n"]);
+}
+
+#[test]
+fn head_bytes() {
+    let input = "one\ntwo\nthree\n";
+    assert_eq!(sent(input, &["--head-bytes", "8"]), ["one\ntwo\n"]);
+    assert_eq!(sent(input, &["--head-bytes", "7"]), ["one\n"]);
+    assert_eq!(sent(input, &["--head-bytes", "100"]), ["one\ntwo\nthree\n"]);
+}
+
+#[test]
+fn head_errors() {
+    for args in [
+        &["-l2", "--head-bytes", "9"][..],
+        &["--head-bytes", "x"],
+        &["--head-bytes", "-1"],
+        &["-l2", "--stream"],
+    ] {
+        let output = run_args(&[args, &["-"]].concat(), "one\n");
+        assert_eq!(output.status.code(), Some(2), "{args:?}: {output:?}");
+        assert!(output.stdout.is_empty(), "{args:?}: {output:?}");
+    }
+}
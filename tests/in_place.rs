@@ -0,0 +1,82 @@
+//! `--in-place` 的备份与失败时保留原文件
+
+mod common;
+
+use std::{fs, path::Path};
+
+use common::{command, run, temp_home, Mock, Reply};
+
+/// 在 `dir` 中运行, 返回退出码
+fn in_place(mock: &Mock, dir: &Path, args: &[&str]) -> Option<i32> {
+    let output = run(command(&mock.proxy()).current_dir(dir).args(args), "");
+    output.status.code()
+}
+
+fn file_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<_> = fs::read_dir(dir).unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn separate_argument_is_a_file() {
+    let dir = temp_home();
+    fs::write(dir.join("a.txt"), "hello\n").unwrap();
+    let mock = Mock::uppercase();
+    assert_eq!(in_place(&mock, &dir, &["-i", "a.txt"]), Some(0));
+    assert_eq!(mock.finish().len(), 1);
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "HELLO\n");
+    assert!(!dir.join("a.txt.bak").exists());
+}
+
+#[test]
+fn attached_suffix_creates_backup() {
+    for args in [&["-i.bak", "a.txt"][..], &["--in-place=.bak", "a.txt"]] {
+        let dir = temp_home();
+        fs::write(dir.join("a.txt"), "hello\n").unwrap();
+        let mock = Mock::uppercase();
+        assert_eq!(in_place(&mock, &dir, args), Some(0), "{args:?}");
+        mock.finish();
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "HELLO\n");
+        assert_eq!(fs::read_to_string(dir.join("a.txt.bak")).unwrap(), "hello\n");
+    }
+}
+
+#[test]
+fn recursive_skips_backups() {
+    let dir = temp_home();
+    fs::create_dir(dir.join("docs")).unwrap();
+    fs::write(dir.join("docs/a.txt"), "hello\n").unwrap();
+    let mock = Mock::uppercase();
+    assert_eq!(in_place(&mock, &dir, &["-r", "-i.bak", "docs"]), Some(0));
+    assert_eq!(in_place(&mock, &dir, &["-ri.bak", "docs"]), Some(0));
+    // 第二次只翻译 a.txt, 不会翻译 a.txt.bak
+    assert_eq!(mock.finish().len(), 2);
+    assert_eq!(file_names(&dir.join("docs")), ["a.txt", "a.txt.bak"]);
+    assert_eq!(fs::read_to_string(dir.join("docs/a.txt.bak")).unwrap(), "HELLO\n");
+}
+
+#[test]
+fn failed_translation_keeps_file() {
+    let dir = temp_home();
+    fs::write(dir.join("a.txt"), "hello\n").unwrap();
+    let mock = Mock::start(|_| Reply::json(r#"{"error_code":"54001","error_msg":"Invalid Sign"}"#));
+    assert_eq!(in_place(&mock, &dir, &["-i.bak", "a.txt"]), Some(5));
+    mock.finish();
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "hello\n");
+    assert_eq!(file_names(&dir), [".baidufanyi_key", "a.txt"]);
+}
+
+#[test]
+fn failed_write_keeps_file() {
+    let dir = temp_home();
+    fs::write(dir.join("a.txt"), "hello\n").unwrap();
+    let mock = Mock::uppercase();
+    // 备份路径 `a.txt/x` 无法创建, 临时文件不会重命名
+    assert_eq!(in_place(&mock, &dir, &["-i/x", "a.txt"]), Some(3));
+    mock.finish();
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "hello\n");
+    assert_eq!(file_names(&dir), [".baidufanyi_key", "a.txt"]);
+}
@@ -0,0 +1,80 @@
+//! `--jobs` 同时翻译多个文件
+
+mod common;
+
+use std::{
+    fs,
+    path::Path,
+    process::Output,
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::{command, run, stdout, temp_home, uppercase, Mock, Reply};
+
+/// 在 `dir` 中以 `-j jobs` 运行
+fn run_jobs(mock: &Mock, dir: &Path, jobs: &str, args: &[&str]) -> Output {
+    let args = [&["-j", jobs][..], args].concat();
+    run(command(&mock.proxy()).current_dir(dir).args(args), "")
+}
+
+#[test]
+fn faster_than_sequential() {
+    let dir = temp_home();
+    let files = ["a.txt", "b.txt", "c.txt", "d.txt"];
+    for file in files {
+        fs::write(dir.join(file), format!("{file}\n")).unwrap();
+    }
+    let elapsed = |jobs| {
+        let mock = Mock::start(|request| {
+            thread::sleep(Duration::from_millis(300));
+            uppercase(request)
+        });
+        let out = format!("out{jobs}");
+        let start = Instant::now();
+        let output = run_jobs(&mock, &dir, jobs, &[&["--out-dir", &out][..], &files].concat());
+        let elapsed = start.elapsed();
+        assert!(output.status.success(), "{output:?}");
+        assert_eq!(mock.finish().len(), files.len());
+        for file in files {
+            let translated = fs::read_to_string(dir.join(&out).join(file)).unwrap();
+            assert_eq!(translated, format!("{}\n", file.to_uppercase()));
+        }
+        elapsed
+    };
+    let (sequential, parallel) = (elapsed("1"), elapsed("4"));
+    assert!(parallel * 2 < sequential, "{parallel:?} {sequential:?}");
+}
+
+#[test]
+fn partial_output_independent_of_jobs() {
+    let dir = temp_home();
+    // 两个块, 第二个块失败
+    let mut text: String = (0..100).map(|i| format!("line {i} {}\n", "a".repeat(90))).collect();
+    text.push_str("fail\n");
+    fs::write(dir.join("a.txt"), text).unwrap();
+    fs::write(dir.join("b.txt"), "b\n").unwrap();
+    let stdouts = ["1", "2"].map(|jobs| {
+        let mock = Mock::start(|request| match request.q().contains("fail") {
+            true => Reply::json(r#"{"error_code":"54003","error_msg":"Invalid Access Limit"}"#),
+            false => uppercase(request),
+        });
+        let output = run_jobs(&mock, &dir, jobs, &["a.txt", "b.txt"]);
+        assert_eq!(mock.finish().len(), 3);
+        assert_eq!(output.status.code(), Some(7), "{output:?}");
+        stdout(&output)
+    });
+    assert!(stdouts[0].starts_with("LINE 0 "), "{}", stdouts[0]);
+    assert!(stdouts[0].ends_with("B\n"), "{}", stdouts[0]);
+    assert_eq!(stdouts[0], stdouts[1]);
+}
+
+#[test]
+fn conflicts_with_stream() {
+    for jobs in ["1", "2"] {
+        for mode in ["--stream", "--low-mem"] {
+            let output = common::run_args(&["-j", jobs, mode, "-"], "");
+            assert_eq!(output.status.code(), Some(2), "{jobs} {mode}: {output:?}");
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! `--ndjson` 记录的合并请求与出错记录
+
+mod common;
+
+use common::{command, run, stdout, Mock, Reply, Request};
+use serde_json::Value;
+
+/// 源语言自动检测时, 含非 ASCII 字符的请求检测为 `zh`, 否则为 `en`
+fn detect(request: &Request) -> Reply {
+    let q = request.q();
+    let from = match request.form("from").as_deref() {
+        Some("auto") if q.is_ascii() => "en".to_owned(),
+        Some("auto") => "zh".to_owned(),
+        from => from.unwrap_or_default().to_owned(),
+    };
+    let lines = q.lines()
+        .map(|src| serde_json::json!({"src": src, "dst": src.to_uppercase()}))
+        .collect::<Vec<_>>();
+    let body = serde_json::json!({"from": from, "to": "en", "trans_result": lines});
+    Reply::json(body.to_string())
+}
+
+fn records(out: &str) -> Vec<Value> {
+    out.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+}
+
+#[test]
+fn auto_records_are_detected_separately() {
+    let mock = Mock::start(detect);
+    let input = concat!(
+        r#"{"id":1,"text":"hello"}"#, "\n",
+        r#"{"id":2,"text":"你好"}"#, "\n",
+        r#"{"id":3,"text":"cat","from":"en"}"#, "\n",
+        r#"{"id":4,"text":"dog","from":"en"}"#, "\n",
+    );
+    let output = run(command(&mock.proxy()).args(["--ndjson", "-t", "en", "-"]), input);
+    let mut sent = mock.finish().iter().map(|request| request.q()).collect::<Vec<_>>();
+    sent.sort();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(sent, ["cat\ndog", "hello", "你好"]);
+    let out = records(&stdout(&output));
+    let detected = out.iter()
+        .map(|record| (record["id"].as_i64().unwrap(), record["detected_from"].as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(detected, [(1, Some("en")), (2, Some("zh")), (3, Some("en")), (4, Some("en"))]);
+    assert_eq!(out[1]["dst"], "你好");
+    assert_eq!(out[2]["dst"], "CAT");
+}
+
+#[test]
+fn malformed_lines_become_error_objects() {
+    let mock = Mock::start(detect);
+    let input = concat!(
+        r#"{"id":"a","text":"one","from":"en"}"#, "\n",
+        r#"{"id":"b","#, "\n",
+        "\n",
+        r#"{"id":"c","text":3}"#, "\n",
+        "[1]\n",
+        r#"{"id":"d","text":"two","from":"en"}"#, "\n",
+    );
+    let output = run(command(&mock.proxy()).args(["--ndjson", "-"]), input);
+    assert_eq!(mock.finish().len(), 1);
+    let out = records(&stdout(&output));
+    assert_eq!(out.len(), 5, "{output:?}");
+    assert_eq!((&out[0]["id"], &out[0]["dst"]), (&"a".into(), &"ONE".into()));
+    assert_eq!((&out[1]["line"], out[1]["id"].is_null()), (&2.into(), true));
+    assert_eq!((&out[2]["id"], &out[2]["line"]), (&"c".into(), &4.into()));
+    assert_eq!(out[3]["line"], 5);
+    assert_eq!((&out[4]["id"], &out[4]["dst"]), (&"d".into(), &"TWO".into()));
+    for err in &out[1..4] {
+        assert!(err["error"].is_string(), "{err}");
+    }
+}
+
+#[test]
+fn fail_fast_stops_at_malformed_line() {
+    let mock = Mock::start(detect);
+    let input = concat!(r#"{"text":"one"}"#, "\n", "{\n", r#"{"text":"two"}"#, "\n");
+    let output = run(
+        command(&mock.proxy()).args(["--ndjson", "--fail-fast", "-"]),
+        input,
+    );
+    assert!(mock.finish().is_empty());
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+    assert!(output.stdout.is_empty(), "{output:?}");
+}
@@ -0,0 +1,72 @@
+//! `--out-dir` 与 `--recursive` 的输出位置
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{command, run, stdout, temp_home, Mock};
+
+/// 在 `dir` 中运行
+fn run_in(mock: &Mock, dir: &Path, args: &[&str]) -> Output {
+    run(command(&mock.proxy()).current_dir(dir).args(args), "")
+}
+
+/// 在 `dir` 中创建文件及其目录
+fn create(dir: &Path, files: &[&str]) {
+    for file in files {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("{file}\n")).unwrap();
+    }
+}
+
+#[test]
+fn mirrors_relative_paths() {
+    let dir = temp_home();
+    create(&dir, &["docs/a.md", "docs/sub/b.md", "docs/sub/c.txt"]);
+    let mock = Mock::uppercase();
+    let args = ["-r", "--glob", "**/*.md", "--out-dir", "out", "docs"];
+    let output = run_in(&mock, &dir, &args);
+    assert_eq!(mock.finish().len(), 2);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(fs::read_to_string(dir.join("out/a.md")).unwrap(), "DOCS/A.MD\n");
+    assert_eq!(fs::read_to_string(dir.join("out/sub/b.md")).unwrap(), "DOCS/SUB/B.MD\n");
+    assert!(!dir.join("out/sub/c.txt").exists());
+}
+
+#[test]
+fn same_output_is_an_error() {
+    let dir = temp_home();
+    create(&dir, &["a/x.txt", "b/x.txt", "c/x.txt"]);
+    let cases = [
+        &["--out-dir", "out", "a/x.txt", "b/x.txt"][..],
+        &["-r", "--out-dir", "out", "a", "b"],
+        &["-r", "--out-dir", "out", "c", "a/x.txt"],
+        &["-i", "a/x.txt", "a/x.txt"],
+    ];
+    for args in cases {
+        let mock = Mock::uppercase();
+        let output = run_in(&mock, &dir, args);
+        assert!(mock.finish().is_empty(), "{args:?}");
+        assert_eq!(output.status.code(), Some(2), "{args:?}: {output:?}");
+        assert!(!dir.join("out").exists(), "{args:?}");
+        assert_eq!(fs::read_to_string(dir.join("a/x.txt")).unwrap(), "a/x.txt\n");
+    }
+}
+
+#[test]
+fn glob_needs_directories() {
+    let dir = temp_home();
+    create(&dir, &["docs/a.md", "b.txt"]);
+    let mock = Mock::uppercase();
+    let args = ["-r", "--glob", "*.md", "--out-dir", "out", "docs", "b.txt"];
+    let output = run_in(&mock, &dir, &args);
+    assert!(mock.finish().is_empty());
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+
+    let mock = Mock::uppercase();
+    let output = run_in(&mock, &dir, &["--dry-run", "--out-dir", "out", "docs/a.md", "b.txt"]);
+    assert!(mock.finish().is_empty());
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(stdout(&output), "docs/a.md -> out/a.md\nb.txt -> out/b.txt\n");
+}
@@ -0,0 +1,84 @@
+//! [`Formatter`] 的输出与命令行相同格式的输出逐字节一致
+
+use baidu_fanyi::{
+    mini_fmt::Fmtter,
+    output::{format_out, format_out_stream, Formatter},
+    response::{Session, TransLine, TransResult},
+    translater::TranslateError,
+};
+
+fn result() -> TransResult {
+    TransResult {
+        from: "en".into(),
+        to: "zh".into(),
+        lines: vec![
+            TransLine { src: "hello".into(), dst: "HELLO".into() },
+            TransLine { src: "world \"q\"".into(), dst: "WORLD \"Q\"".into() },
+        ],
+    }
+}
+
+fn formatter() -> Formatter {
+    ["%s%n", "%1r -> %0r [%2s->%3s]%n", "%?eq(WORLD \"Q\"){W}:{%R}%t%n"]
+        .map(|s| Fmtter::build(s).unwrap())
+        .to_vec()
+        .into()
+}
+
+/// `printf 'hello\nworld "q"\n' | baidu_fanyi -m .. -m .. -m ..` 的输出
+const EXPECTED: &str = concat!(
+    "HELLO\n",
+    "WORLD \"Q\"\n",
+    "\"hello\" -> \"HELLO\" [en->zh]\n",
+    "\"world \\\"q\\\"\" -> \"WORLD \\\"Q\\\"\" [en->zh]\n",
+    "\"HELLO\"\t\n",
+    "W\t\n",
+);
+
+#[test]
+fn format_result_matches_binary() {
+    let formatter = formatter();
+    assert_eq!(formatter.format_result(&result()).unwrap().concat(), EXPECTED);
+    assert_eq!(format_out(formatter.fmtters(), &result()).unwrap().concat(), EXPECTED);
+}
+
+#[test]
+fn write_result_matches_format_result() {
+    let formatter = formatter();
+    let mut out = vec![];
+    formatter.write_result(&result(), &mut out).unwrap();
+    assert_eq!(out, EXPECTED.as_bytes());
+    let mut out = vec![];
+    format_out_stream(formatter.fmtters(), &result(), &mut out).unwrap();
+    assert_eq!(out, EXPECTED.as_bytes());
+}
+
+#[test]
+fn write_session_in_format_order() {
+    let session = Session { blocks: vec![Ok(result()), Err("54003".into())] };
+    let formatter: Formatter = vec![Fmtter::build("%{dst}s%n").unwrap()].into();
+    let mut out = vec![];
+    formatter.write_session(&session, "<code>", &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "HELLO\nWORLD \"Q\"\n54003\n");
+}
+
+#[test]
+fn format_line_applies_every_format() {
+    let result = result();
+    let lines: String = result.lines.iter()
+        .map(|line| formatter().format_line(line, ("en", "zh")).unwrap())
+        .collect();
+    // 按行而非按格式排列
+    assert!(lines.starts_with("HELLO\n\"hello\" -> \"HELLO\" [en->zh]\n\"HELLO\"\t\n"));
+    assert_eq!(lines.len(), EXPECTED.len());
+}
+
+#[test]
+fn missing_arguments_are_errors() {
+    let formatter: Formatter = vec![Fmtter::build("%4s").unwrap()].into();
+    assert!(formatter.format_result(&result()).is_err());
+    let error = format_out(formatter.fmtters(), &result()).unwrap_err();
+    assert_eq!(error.path, "trans_result[0]");
+    let error = formatter.write_result(&result(), &mut vec![]).unwrap_err();
+    assert!(matches!(error, TranslateError::Response(_)), "{error:?}");
+}
@@ -0,0 +1,17 @@
+//! 接口出错但带有 `trans_result` 时输出的部分结果
+
+mod common;
+
+use common::{command, run, stdout, Mock, Reply};
+
+#[test]
+fn partial_result_is_wrapped() {
+    let mock = Mock::start(|_| Reply::json(concat!(
+        r#"{"error_code":"54001","error_msg":"Invalid Sign","from":"en","to":"zh","#,
+        r#""trans_result":[{"src":"aaa bbb ccc","dst":"AAA BBB CCC"}]}"#,
+    )));
+    let output = run(command(&mock.proxy()).args(["--wrap", "7", "-"]), "aaa bbb ccc\n");
+    mock.finish();
+    assert_eq!(output.status.code(), Some(7), "{output:?}");
+    assert_eq!(stdout(&output), "AAA BBB\nCCC\n");
+}
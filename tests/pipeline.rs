@@ -0,0 +1,116 @@
+//! [`run_pipeline`] 以模拟的翻译任务运行
+
+use std::{
+    io::Cursor,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use baidu_fanyi::{
+    mini_fmt::Fmtter,
+    output::Formatter,
+    response::{TransLine, TransResult},
+    stream::{run_pipeline, PipelineConfig},
+    translater::{LimitUnit, TranslateError, MAX_REQUEST_BYTES},
+};
+
+/// 译文为原文的大写, 记录每批的行
+fn mock(
+    batches: Arc<Mutex<Vec<Vec<String>>>>,
+) -> impl FnMut(String) -> std::future::Ready<Result<TransResult, TranslateError>> {
+    move |batch: String| {
+        let lines: Vec<String> = batch.lines().map(str::to_owned).collect();
+        batches.lock().unwrap().push(lines.clone());
+        let lines = lines.into_iter()
+            .map(|src| TransLine { dst: src.to_uppercase(), src })
+            .collect();
+        std::future::ready(Ok(TransResult { from: "en".into(), to: "zh".into(), lines }))
+    }
+}
+
+fn format() -> Formatter {
+    vec![Fmtter::build("%1s=%0s%n").unwrap()].into()
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Runtime::new().unwrap()
+}
+
+#[test]
+fn lines_arrive_in_order() {
+    let text: String = (0..2000).map(|i| format!("line {i}\n")).collect();
+    let mut count = 0;
+    let translate = |batch: String| {
+        // 使各批的耗时不同
+        count += 1;
+        let delay = Duration::from_micros(300 * (count % 4));
+        async move {
+            tokio::time::sleep(delay).await;
+            let lines = batch.lines()
+                .map(|src| TransLine { src: src.into(), dst: src.to_uppercase() })
+                .collect();
+            Ok(TransResult { from: "en".into(), to: "zh".into(), lines })
+        }
+    };
+    let mut out = Vec::new();
+    let input = Cursor::new(text.clone());
+    let mut config = PipelineConfig::new(input, translate, format(), &mut out);
+    config.capacity = 16;
+    runtime().block_on(run_pipeline(config)).unwrap();
+    let expected: String = text.lines().map(|l| format!("{l}={}\n", l.to_uppercase())).collect();
+    assert_eq!(String::from_utf8(out).unwrap(), expected);
+}
+
+#[test]
+fn batches_within_limit() {
+    let line = "a".repeat(999);
+    let text = format!("{line}\n").repeat(20);
+    let batches = Arc::default();
+    let mut out = Vec::new();
+    let translate = mock(Arc::clone(&batches));
+    let mut config = PipelineConfig::new(Cursor::new(text), translate, format(), &mut out);
+    config.limit_unit = LimitUnit::Bytes;
+    runtime().block_on(run_pipeline(config)).unwrap();
+    let batches = batches.lock().unwrap();
+    assert_eq!(batches.iter().map(Vec::len).sum::<usize>(), 20);
+    for batch in batches.iter() {
+        let size: usize = batch.iter().map(|line| line.len() + 1).sum();
+        assert!(size < MAX_REQUEST_BYTES, "{size}");
+    }
+}
+
+#[test]
+fn blank_batches_skipped() {
+    let batches = Arc::default();
+    let mut out = Vec::new();
+    let translate = mock(Arc::clone(&batches));
+    let config = PipelineConfig::new(Cursor::new("\n  \n"), translate, format(), &mut out);
+    runtime().block_on(run_pipeline(config)).unwrap();
+    assert!(batches.lock().unwrap().is_empty());
+    assert!(out.is_empty());
+}
+
+#[test]
+fn translate_error_stops_pipeline() {
+    let text = "ok\n".repeat(10_000);
+    let mut count = 0;
+    let translate = |batch: String| {
+        count += 1;
+        let failed = count == 2;
+        async move {
+            if failed {
+                return Err(TranslateError::Response("mock".into()));
+            }
+            let lines = batch.lines()
+                .map(|src| TransLine { src: src.into(), dst: src.into() })
+                .collect();
+            Ok(TransResult { from: "en".into(), to: "zh".into(), lines })
+        }
+    };
+    let mut out = Vec::new();
+    let mut config = PipelineConfig::new(Cursor::new(text), translate, format(), &mut out);
+    config.capacity = 8;
+    let e = runtime().block_on(run_pipeline(config)).unwrap_err();
+    assert!(matches!(e, TranslateError::Response(_)), "{e:?}");
+    assert!(out.len() < 10_000 * "ok=ok\n".len());
+}
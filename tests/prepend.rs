@@ -0,0 +1,63 @@
+//! `--prepend` 与 `--append` 在 stdout 上只输出一次, 每个输出文件各一次
+
+mod common;
+
+use std::{fs, path::Path, process::Output};
+
+use common::{command, run, stdout, temp_home, Mock};
+
+const FRAME: [&str; 4] = ["--prepend", "<%n", "--append", ">%n"];
+
+fn run_in(dir: &Path, args: &[&str], input: &str) -> Output {
+    let mock = Mock::uppercase();
+    let output = run(
+        command(&mock.proxy()).current_dir(dir).args(FRAME).args(["--dst-only"]).args(args),
+        input,
+    );
+    mock.finish();
+    output
+}
+
+fn files(dir: &Path) -> [&'static str; 2] {
+    fs::write(dir.join("a.txt"), "a\n").unwrap();
+    fs::write(dir.join("b.txt"), "b\n").unwrap();
+    ["a.txt", "b.txt"]
+}
+
+#[test]
+fn once_around_stdout() {
+    let dir = temp_home();
+    let output = run_in(&dir, &[], "hello\n");
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(stdout(&output), "<\nHELLO\n>\n");
+
+    for jobs in ["1", "2"] {
+        let args = [&["-j", jobs][..], &files(&dir)].concat();
+        let output = run_in(&dir, &args, "");
+        assert!(output.status.success(), "{output:?}");
+        assert_eq!(stdout(&output), "<\nA\nB\n>\n", "-j {jobs}");
+    }
+    let output = run_in(&dir, &["--stream", "a.txt", "b.txt"], "");
+    assert_eq!(stdout(&output), "<\nA\nB\n>\n");
+    let output = run_in(&dir, &["--newline", "crlf", "a.txt", "b.txt"], "");
+    assert_eq!(stdout(&output), "<\r\nA\r\nB\r\n>\r\n");
+}
+
+#[test]
+fn per_output_file() {
+    let dir = temp_home();
+    let args = [&["--out-dir", "out"][..], &files(&dir)].concat();
+    let output = run_in(&dir, &args, "");
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stdout.is_empty());
+    assert_eq!(fs::read_to_string(dir.join("out/a.txt")).unwrap(), "<\nA\n>\n");
+    assert_eq!(fs::read_to_string(dir.join("out/b.txt")).unwrap(), "<\nB\n>\n");
+}
+
+#[test]
+fn nothing_for_empty_input() {
+    let dir = temp_home();
+    let output = run_in(&dir, &[], "\n");
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stdout.is_empty(), "{output:?}");
+}
@@ -0,0 +1,83 @@
+//! 格式解析与空白符过滤的性质测试, 输入与 `fuzz/` 中的目标相同
+
+use baidu_fanyi::{
+    mini_fmt::{Fmtter, FmtterCached},
+    traits::{FilterOutLongEmpty, LongEmptyFilter},
+};
+use proptest::prelude::*;
+
+/// 偏向格式语法的字符, 比任意字符串更容易走到解析的各个分支
+fn format_str() -> impl Strategy<Value = String> {
+    prop_oneof![
+        any::<String>(),
+        "([%?:{}()snrRNtezxuU0-9a-fA-F]|empty|nonempty|eq|date|timestamp|ab){0,32}",
+    ]
+}
+
+fn longest_whitespace_run(s: &str) -> usize {
+    s.chars()
+        .fold((0, 0), |(run, max), c| {
+            let run = if c.is_whitespace() { run + 1 } else { 0 };
+            (run, max.max(run))
+        })
+        .1
+}
+
+proptest! {
+    #[test]
+    fn build_never_panics(format in format_str()) {
+        let _ = Fmtter::build(&format);
+    }
+
+    #[test]
+    fn build_display_build_is_idempotent(format in format_str()) {
+        if let Ok(fmtter) = Fmtter::build(&format) {
+            let displayed = fmtter.to_string();
+            prop_assert_eq!(Fmtter::build(&displayed), Ok(fmtter));
+        }
+    }
+
+    #[test]
+    fn try_fmt_str_never_panics(
+        format in format_str(),
+        args in prop::collection::vec(any::<String>(), 0..4),
+    ) {
+        if let Ok(fmtter) = Fmtter::build(&format) {
+            let _ = fmtter.try_fmt_str(&args);
+        }
+    }
+
+    #[test]
+    fn cached_matches_fmt_str(
+        format in format_str(),
+        calls in prop::collection::vec(prop::collection::vec("[a\"%]{0,2}", 4), 1..16),
+    ) {
+        if let Ok(fmtter) = Fmtter::build(&format) {
+            let cached = FmtterCached::new(fmtter.clone());
+            for args in &calls {
+                if let Ok(expected) = fmtter.try_fmt_str(args) {
+                    prop_assert_eq!(&*cached.fmt_str_cached(args), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn filter_limits_whitespace_runs(text in any::<String>(), count in 0usize..8) {
+        let filtered = text.as_str().filter_out_long_empty(count);
+        prop_assert!(longest_whitespace_run(&filtered) <= count);
+        let kept = |s: &str| s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+        prop_assert_eq!(kept(&filtered), kept(&text));
+    }
+
+    #[test]
+    fn filter_by_chunks_matches_whole(text in any::<String>(), count in 0usize..8, at in any::<usize>()) {
+        let mut at = at % (text.len() + 1);
+        while !text.is_char_boundary(at) {
+            at -= 1;
+        }
+        let mut filter = LongEmptyFilter::new(count);
+        let chunked = filter.push(&text[..at]) + &filter.push(&text[at..]);
+        prop_assert_eq!(chunked, text.as_str().filter_out_long_empty(count));
+    }
+}
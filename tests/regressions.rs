@@ -0,0 +1,58 @@
+//! 模糊测试找到的输入, 已最小化
+
+use baidu_fanyi::mini_fmt::{Fmtter, MAX_NESTING};
+
+/// 深度嵌套的条件曾导致递归解析时栈溢出
+#[test]
+fn deeply_nested_condition() {
+    let nested = |depth| format!("{}a{}", "%?empty{".repeat(depth), "}".repeat(depth));
+    assert!(Fmtter::build(&nested(MAX_NESTING)).is_ok());
+    assert!(Fmtter::build(&nested(MAX_NESTING + 1)).is_err());
+    assert!(Fmtter::build(&nested(100_000)).is_err());
+}
+
+/// 参数不足时 `fmt_str` 会越界 panic
+#[test]
+fn missing_arguments() {
+    for format in ["%s", "%1s", "%?empty{%s}:{%2s}", "%s%?nonempty{%3r}"] {
+        let fmtter = Fmtter::build(format).unwrap();
+        assert!(fmtter.try_fmt_str::<&str>(&[]).is_err(), "{format}");
+    }
+    let args = std::collections::HashMap::from([("a", "x")]);
+    assert!(Fmtter::build("%s%s").unwrap().fmt_with_named(&args).is_err());
+}
+
+/// 以 `:` 开头的常量跟在条件之后, 显示后会被解析为 else 分支
+#[test]
+fn colon_after_condition() {
+    let fmtter = Fmtter::build("%?empty{a}%x3a{b}").unwrap();
+    assert_eq!(fmtter.to_string(), "%?empty{a}%x3a{b}");
+    assert_eq!(Fmtter::build(&fmtter.to_string()).unwrap(), fmtter);
+}
+
+/// 分支中由十六进制转义得到的括号, 原样显示后分支的括号不再配对
+#[test]
+fn brace_in_branch() {
+    for format in ["%?empty{%x7d}", "%?empty{%x7b}:{%u007d}"] {
+        let fmtter = Fmtter::build(format).unwrap();
+        assert_eq!(Fmtter::build(&fmtter.to_string()).unwrap(), fmtter, "{format}");
+    }
+}
+
+/// 截断的转义序列
+#[test]
+fn truncated_sequences() {
+    let formats = ["%", "%x", "%x1", "%u12", "%U10fff", "%0", "%{date}", "%?", "%?empty{"];
+    for format in formats {
+        assert!(Fmtter::build(format).is_err(), "{format}");
+    }
+    assert!(Fmtter::build("%xzz").is_err());
+}
+
+/// `from_str_radix` 接受的 `+` 号曾被当作十六进制数字
+#[test]
+fn sign_in_hex() {
+    for format in ["%x+1", "%u+041", "%U+00041"] {
+        assert!(Fmtter::build(format).is_err(), "{format}");
+    }
+}
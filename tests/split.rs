@@ -0,0 +1,24 @@
+//! [`split_cjk_sentences`] 在中日文句末标点处分块
+
+use baidu_fanyi::translater::split_cjk_sentences;
+
+#[test]
+fn sentences_under_limit_stay_together() {
+    let text = "今天天气很好。我们去公园吧！";
+    assert_eq!(split_cjk_sentences(text, text.len()), [text]);
+}
+
+#[test]
+fn sentences_over_limit_split_at_boundary() {
+    let text = "今天天气很好。我们去公园吧！你来吗？";
+    let first = "今天天气很好。".len();
+    assert_eq!(split_cjk_sentences(text, first + 3), [
+        "今天天气很好。",
+        "我们去公园吧！",
+        "你来吗？",
+    ]);
+    // 不在句中切开
+    for block in split_cjk_sentences(text, text.len() - 1) {
+        assert!(block.ends_with(['。', '！', '？']), "{block}");
+    }
+}
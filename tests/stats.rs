@@ -0,0 +1,47 @@
+//! `--stats` 与 `--stats-json` 汇总的用量
+
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use common::{command, run, stdout, uppercase, Mock, Reply};
+use serde_json::Value;
+
+/// 第一个请求返回 429, 之后同 [`uppercase`]
+fn retry_once() -> Mock {
+    let count = AtomicUsize::new(0);
+    Mock::start(move |request| match count.fetch_add(1, Ordering::SeqCst) {
+        0 => Reply::empty("429 Too Many Requests").header("retry-after", "0"),
+        _ => uppercase(request),
+    })
+}
+
+#[test]
+fn two_blocks_one_retry() {
+    let mock = retry_once();
+    let args = ["--stdin-separator", "--\\n", "--stats-json", "--price-per-million", "100", "-"];
+    let output = run(command(&mock.proxy()).args(args), "hello\n--\nworld!\n");
+    assert_eq!(mock.finish().len(), 3);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = stdout(&output);
+    let (translated, json) = stdout.trim_end().rsplit_once('\n').unwrap();
+    assert_eq!(translated, "HELLO\n--\nWORLD!");
+    let stats: Value = serde_json::from_str(json).unwrap();
+    assert_eq!(stats["requests"], 2, "{stats}");
+    assert_eq!(stats["retries"], 1, "{stats}");
+    assert_eq!(stats["cache_hits"], 0, "{stats}");
+    assert_eq!(stats["input_chars"], 13, "{stats}");
+    assert_eq!(stats["billed_chars"], 13, "{stats}");
+    assert_eq!(stats["estimated_cost"], 13.0 * 100.0 / 1e6, "{stats}");
+}
+
+#[test]
+fn stats_to_stderr() {
+    let mock = retry_once();
+    let output = run(command(&mock.proxy()).args(["--stats", "-"]), "hello\n");
+    mock.finish();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(stdout(&output), "HELLO\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("requests: 1, retries: 1, cache hits: 0"), "{stderr}");
+}
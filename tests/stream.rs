@@ -0,0 +1,28 @@
+//! `--stream` 与 `--low-mem` 发送的文本与一次读取全部输入时相同
+
+mod common;
+
+use common::{command, run, Mock};
+
+/// 以 `args` 翻译 `input`, 返回发送的文本, 去除末尾的换行
+fn sent(input: &str, args: &[&str]) -> String {
+    let mock = Mock::uppercase();
+    let output = run(command(&mock.proxy()).args(args).arg("-"), input);
+    assert!(output.status.success(), "{args:?}: {output:?}");
+    let requests = mock.finish();
+    assert_eq!(requests.len(), 1, "{args:?}");
+    requests[0].q().trim_end().to_owned()
+}
+
+#[test]
+fn filter_after_preprocessing() {
+    // 去除转义序列, 统一换行符并去除首尾空白后才是连续的空行
+    let input = "a\n\x1b[0m\r\n  \x1b[1m \n\r\n\x1b[0m\n\nb\n";
+    // 去重时不发送空行, 与逐块读取无关
+    let args = ["--strip-ansi", "--trim", "--no-dedup"];
+    let whole = sent(input, &args);
+    assert_eq!(whole, "a\n\nb");
+    for mode in ["--stream", "--low-mem"] {
+        assert_eq!(sent(input, &[&args[..], &[mode]].concat()), whole, "{mode}");
+    }
+}
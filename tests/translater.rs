@@ -0,0 +1,235 @@
+//! [`Translater`] 经由本地模拟服务器发送的请求
+
+mod common;
+
+use std::{future::Future, time::Duration};
+
+use baidu_fanyi::{
+    response::JSONData,
+    translater::{post_retry, TranslateProgress, Translater, DEFAULT_USER_AGENT},
+};
+use common::{Mock, Reply};
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Runtime::new().unwrap().block_on(future)
+}
+
+#[test]
+fn post_retry_after_too_many_requests() {
+    let mock = Mock::start({
+        let count = std::sync::atomic::AtomicUsize::new(0);
+        move |_| match count.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+            0 => Reply::empty("429 Too Many Requests").header("retry-after", "0"),
+            _ => Reply::empty("200 OK"),
+        }
+    });
+    let mut payload = JSONData::new();
+    payload.insert("q".into(), "你好".into());
+    let client = reqwest::Client::new();
+    let (response, retries) = block_on(post_retry(&client, &mock.url(), &payload)).unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(retries, 1);
+    let requests = mock.finish();
+    assert_eq!(requests.len(), 2);
+    for request in requests {
+        let content_type = "content-type: application/x-www-form-urlencoded\r\n";
+        assert_eq!(request.head.matches(content_type).count(), 1, "{}", request.head);
+        assert_eq!(request.body, "q=%E4%BD%A0%E5%A5%BD");
+    }
+}
+
+/// 使用 `translater` 的 [`Translater::client`] 发送请求, 返回请求头
+fn sent_head(translater: &Translater) -> String {
+    let mock = Mock::start(|_| Reply::empty("200 OK"));
+    block_on(translater.client().get(mock.url()).send()).unwrap();
+    mock.finish().remove(0).head
+}
+
+#[test]
+fn builder_headers() {
+    let translater = Translater::builder("id", "key")
+        .header("X-Org-Token", "secret").unwrap()
+        .user_agent("my-app/1.0").unwrap()
+        .build().unwrap();
+    let head = sent_head(&translater);
+    assert!(head.contains("x-org-token: secret\r\n"), "{head}");
+    assert!(head.contains("user-agent: my-app/1.0\r\n"), "{head}");
+}
+
+#[test]
+fn with_user_agent() {
+    let translater = Translater::new("id", "key");
+    let head = sent_head(&translater);
+    assert!(head.contains(&format!("user-agent: {DEFAULT_USER_AGENT}\r\n")), "{head}");
+
+    assert!(translater.clone().with_user_agent("a\nb").is_err());
+}
+
+#[test]
+fn with_user_agent_keeps_client() {
+    let mock = Mock::uppercase();
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("x-org-token", "secret".parse().unwrap());
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(mock.proxy()).unwrap())
+        .default_headers(headers)
+        .build()
+        .unwrap();
+    let translater = Translater::new_with_client("id", "key", client)
+        .with_user_agent("my-app/1.0")
+        .unwrap();
+    block_on(translater.translate("hello".into())).unwrap();
+    block_on(translater.detect("hello")).ok();
+    let requests = mock.finish();
+    assert_eq!(requests.len(), 2);
+    for request in requests {
+        assert_eq!(request.header("user-agent"), Some("my-app/1.0"), "{}", request.head);
+        assert_eq!(request.header("x-org-token"), Some("secret"), "{}", request.head);
+    }
+}
+
+#[test]
+fn payload_q_matches_sign() {
+    let cases = [
+        "a&b=c", "100% +1", "%26%25", "a+b c", "line1\nline2\r\n",
+        "中文，标点", "emoji 😀👍🏽", "  spaces  ", "#?/;:@$,",
+    ];
+    let mock = Mock::start(|_| Reply::empty("200 OK"));
+    let mut translater = Translater::new("appid", "key");
+    for q in cases {
+        translater.update_salt();
+        let payload = translater.build_payload(q.into());
+        block_on(post_retry(translater.client(), &mock.url(), &payload)).unwrap();
+        let request = mock.take().remove(0);
+        let salt = payload["salt"].as_str().unwrap();
+        assert_eq!(request.q(), q);
+        assert_eq!(request.form("salt").unwrap(), salt);
+        let reference = format!("{:x}", md5::compute(format!("appid{q}{salt}key")));
+        assert_eq!(request.form("sign").unwrap(), reference);
+    }
+    assert!(mock.finish().is_empty());
+}
+
+#[test]
+fn translate_with_metadata() {
+    let mock = Mock::start(|_| Reply::json(
+        r#"{"from":"zh","to":"en","trans_result":[{"src":"你好","dst":"Hello"}]}"#,
+    ));
+    let translater = Translater::new_with_client("id", "key", mock.client());
+    let (result, meta) = block_on(translater.translate_with_metadata("你好".into()))
+        .unwrap();
+    mock.finish();
+    assert_eq!(result.lines[0].dst, "Hello");
+    assert!(meta.latency > Duration::ZERO);
+    assert_eq!((meta.src_chars, meta.dst_chars), (2, 5));
+    assert_eq!(meta.retries, 0);
+    assert!(!meta.cache_hit);
+}
+
+#[test]
+fn translate_html_document() {
+    let mock = Mock::uppercase();
+    let translater = Translater::new_with_client("id", "key", mock.client());
+    let html = concat!(
+        "<html><head><title>Hello</title>",
+        "<meta name=\"description\" content=\"about cats\"></head>\n",
+        "<body>\n  <p>A <b>cat</b> &amp; a dog.</p>\n",
+        "  <img src=\"cat.png\" alt=\"a cat\">\n</body></html>",
+    );
+    let out = block_on(translater.translate_html_document(html)).unwrap();
+    // 所有文本合并为一个请求
+    assert_eq!(mock.finish().len(), 1);
+    assert_eq!(out, concat!(
+        "<html><head><title>HELLO</title>",
+        "<meta name=\"description\" content=\"ABOUT CATS\"></head>\n",
+        "<body>\n  <p>A <b>CAT</b> &amp; A DOG.</p>\n",
+        "  <img src=\"cat.png\" alt=\"A CAT\">\n</body></html>",
+    ));
+}
+
+#[test]
+fn sign_callback_receives_salt() {
+    let mock = Mock::uppercase();
+    let mut translater = Translater::new_with_client("id", "", mock.client())
+        .with_sign_callback(|query, salt| format!("{query}|{salt}"));
+    translater.update_salt();
+    block_on(translater.translate("hello".into())).unwrap();
+    let request = mock.finish().remove(0);
+    let salt = request.form("salt").unwrap();
+    assert_eq!(request.form("sign").unwrap(), format!("hello|{salt}"));
+}
+
+#[test]
+fn progress_in_order() {
+    let mock = Mock::uppercase();
+    let translater = Translater::new_with_client("id", "key", mock.client());
+    let (tx, mut rx) = tokio::sync::watch::channel(TranslateProgress::Connecting);
+    let states = block_on(async {
+        let recorder = tokio::spawn(async move {
+            let mut states = vec![*rx.borrow_and_update()];
+            while rx.changed().await.is_ok() {
+                states.push(*rx.borrow_and_update());
+            }
+            states
+        });
+        translater.translate_with_watch("hello".into(), tx).await.unwrap();
+        recorder.await.unwrap()
+    });
+    mock.finish();
+    // 接收端可能错过中间的状态, 但顺序不变且以 `Done` 结束
+    let order = [
+        TranslateProgress::Connecting,
+        TranslateProgress::Waiting,
+        TranslateProgress::Parsing,
+        TranslateProgress::Done,
+    ];
+    let index = |state| order.iter().position(|x| *x == state).unwrap();
+    assert!(states.windows(2).all(|w| index(w[0]) < index(w[1])), "{states:?}");
+    assert_eq!(states.last(), Some(&TranslateProgress::Done));
+}
+
+#[test]
+fn batch_keeps_input_order() {
+    // 越靠前的输入响应越慢
+    let mock = Mock::start(|request| {
+        let q = request.q();
+        let delay = u64::from(b'j' - q.as_bytes()[0]) * 20;
+        std::thread::sleep(Duration::from_millis(delay));
+        common::uppercase(request)
+    });
+    let translater = Translater::new_with_client("id", "key", mock.client());
+    let inputs = ('a'..='j').map(String::from).collect::<Vec<_>>();
+    let results = block_on(translater.translate_batch_ordered(inputs.clone(), 10)).unwrap();
+    let requests = mock.finish();
+    assert_eq!(requests.len(), 10);
+    // 请求按完成的顺序记录, 与输入顺序不同
+    assert_ne!(requests.iter().map(|request| request.q()).collect::<Vec<_>>(), inputs);
+    for (result, input) in results.iter().zip(&inputs) {
+        assert_eq!(&result.lines[0].src, input);
+        assert_eq!(result.lines[0].dst, input.to_uppercase());
+    }
+}
+
+#[test]
+fn fifty_blocks_share_one_client() {
+    let mock = Mock::uppercase();
+    // 只有这个 Client 会带上此请求头
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("x-client-id", "shared".parse().unwrap());
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(mock.proxy()).unwrap())
+        .default_headers(headers)
+        .build()
+        .unwrap();
+    let translater = Translater::new_with_client("id", "key", client);
+    let inputs = (0..50).map(|i| format!("block {i}")).collect::<Vec<_>>();
+    let results = block_on(translater.translate_batch_ordered(inputs, 8)).unwrap();
+    assert_eq!(results.len(), 50);
+    let requests = mock.finish();
+    assert_eq!(requests.len(), 50);
+    let content_type = "content-type: application/x-www-form-urlencoded\r\n";
+    for request in requests {
+        assert_eq!(request.header("x-client-id"), Some("shared"), "{}", request.head);
+        assert_eq!(request.head.matches(content_type).count(), 1, "{}", request.head);
+    }
+}
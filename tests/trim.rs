@@ -0,0 +1,39 @@
+//! `--trim` 等去除每行首尾的空白后再发送请求
+
+mod common;
+
+use common::{command, run, run_args, stdout, Mock};
+
+/// 以 `args` 翻译 `input`, 返回发送的文本与输出
+fn translate(input: &str, args: &[&str]) -> (String, String) {
+    let mock = Mock::uppercase();
+    let output = run(command(&mock.proxy()).args(args).args(["-m", "[%1s]%n"]), input);
+    assert!(output.status.success(), "{output:?}");
+    let requests = mock.finish();
+    assert_eq!(requests.len(), 1);
+    (requests[0].q(), stdout(&output))
+}
+
+#[test]
+fn trim_sends_trimmed_lines() {
+    let (sent, out) = translate("  hello  \n", &["--trim"]);
+    assert_eq!(sent, "hello\n");
+    assert_eq!(out, "[hello]\n");
+}
+
+#[test]
+fn trim_one_side() {
+    let (sent, _) = translate("  hello  \n", &["--trim-start"]);
+    assert_eq!(sent, "hello  \n");
+    let (sent, _) = translate("  hello  \n", &["--trim-end"]);
+    assert_eq!(sent, "  hello\n");
+    // `--stream` 同样先去除空白再过滤, 末尾的换行不会被当作多余的空白
+    let (sent, _) = translate("  hello  \n", &["--trim", "--stream"]);
+    assert_eq!(sent, "hello\n");
+}
+
+#[test]
+fn trim_modes_conflict() {
+    let output = run_args(&["--trim", "--trim-end", "-"], "");
+    assert_eq!(output.status.code(), Some(2));
+}
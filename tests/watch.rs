@@ -0,0 +1,50 @@
+//! `--watch` 在文件内容改变时重新翻译
+#![cfg(feature = "watch")]
+
+mod common;
+
+use std::{
+    fs,
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::{command, Mock, Request};
+
+/// 等待收到 `count` 个请求, 超时时 panic
+fn wait_requests(mock: &Mock, received: &mut Vec<Request>, count: usize) {
+    let start = Instant::now();
+    while received.len() < count {
+        assert!(start.elapsed() < Duration::from_secs(10), "{received:?}");
+        thread::sleep(Duration::from_millis(20));
+        received.extend(mock.take());
+    }
+}
+
+#[test]
+fn retranslate_on_change() {
+    let dir = common::temp_home();
+    let path = dir.join("scratch.txt");
+    fs::write(&path, "one\n").unwrap();
+    let mock = Mock::uppercase();
+    let mut child = command(&mock.proxy())
+        .args(["--watch", "--watch-debounce", "50"])
+        .arg(&path)
+        .spawn()
+        .unwrap();
+    let mut received = vec![];
+    wait_requests(&mock, &mut received, 1);
+    fs::write(&path, "two\n").unwrap();
+    wait_requests(&mock, &mut received, 2);
+    // 内容不变时不重新请求
+    fs::write(&path, "two\n").unwrap();
+    thread::sleep(Duration::from_millis(500));
+    received.extend(mock.take());
+    child.kill().unwrap();
+    let output = child.wait_with_output().unwrap();
+    mock.finish();
+    let sent = received.iter().map(Request::q).collect::<Vec<_>>();
+    assert_eq!(sent, ["one\n", "two\n"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("ONE\n") && stdout.contains("TWO\n"), "{stdout}");
+}